@@ -0,0 +1,26 @@
+use std::any::Any;
+
+use crate::{Canceled, Handshake, PushOutcome, Receipt};
+
+/// A value of any type, as exchanged by an [`AnyHandshake`].
+pub type AnyValue = Box<dyn Any + Send>;
+
+/// A handshake exchanging values of any type, e.g. for a plugin bus; see [`try_pull_downcast`](Handshake::try_pull_downcast).
+pub type AnyHandshake = Handshake<AnyValue>;
+
+impl Handshake<AnyValue> {
+    /// Boxes `value` and pushes it, see [`push`](Self::push).
+    pub fn try_push_any<U: Any + Send>(self, value: U) -> Result<Result<Receipt<AnyValue>, (Self, AnyValue)>, AnyValue> {
+        match self.push(Box::new(value)) {
+            PushOutcome::Delivered(receipt) => Ok(Ok(receipt)),
+            PushOutcome::Occupied(handshake, value) => Ok(Err((handshake, value))),
+            PushOutcome::PeerGone(value) => Err(value),
+        }
+    }
+
+    /// Like [`pull_now`](Self::pull_now), but unboxes the value as a `U`, handing
+    /// back the box when the peer pushed something else.
+    pub fn try_pull_downcast<U: Any>(self) -> Result<Result<Result<U, AnyValue>, Self>, Canceled> {
+        Ok(self.pull_now().into_result()?.map(|value| value.downcast().map(|value| *value)))
+    }
+}
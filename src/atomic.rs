@@ -0,0 +1,146 @@
+//! Handshakes exchanging small `Copy` values through a single atomic word.
+
+use std::{fmt::Debug, marker::PhantomData, mem::ManuallyDrop, sync::Arc};
+
+use crate::{
+    common::{BUSY, CANCELED, EMPTY, SET, TAKEN},
+    sync::{self, AtomicU64, AtomicU8, Ordering},
+    Canceled, State,
+};
+
+/// A value fitting in 64 bits, exchanged inline by an [`AtomicHandshake`].
+pub trait Inline: Copy {
+    fn into_bits(self) -> u64;
+    /// Rebuilds the value from what [`into_bits`](Self::into_bits) made of it.
+    fn from_bits(bits: u64) -> Self;
+}
+
+macro_rules! inline {
+    ($($ty:ty),*) => {$(
+        impl Inline for $ty {
+            fn into_bits(self) -> u64 { self as u64 }
+            fn from_bits(bits: u64) -> Self { bits as $ty }
+        }
+    )*};
+}
+
+inline!(u8, u16, u32, u64, usize, i8, i16, i32, i64, isize);
+
+impl Inline for bool {
+    fn into_bits(self) -> u64 { self as u64 }
+    fn from_bits(bits: u64) -> Self { bits != 0 }
+}
+
+impl Inline for char {
+    fn into_bits(self) -> u64 { self as u64 }
+    // only ever made from a char
+    fn from_bits(bits: u64) -> Self { char::from_u32(bits as u32).unwrap_or_default() }
+}
+
+impl Inline for f32 {
+    fn into_bits(self) -> u64 { self.to_bits() as u64 }
+    fn from_bits(bits: u64) -> Self { f32::from_bits(bits as u32) }
+}
+
+impl Inline for f64 {
+    fn into_bits(self) -> u64 { self.to_bits() }
+    fn from_bits(bits: u64) -> Self { f64::from_bits(bits) }
+}
+
+struct Inner {
+    state: AtomicU8,
+    // only written while busy, before the value is published as set
+    value: AtomicU64,
+}
+
+/// One end of a handshake exchanging an [`Inline`] value, e.g. an index or id.
+///
+/// Pushing and pulling are a couple of atomic operations on the shared word,
+/// with no lock, wakers or payload cell involved, so there is nothing to wait
+/// on either: poll with [`try_pull`](Self::try_pull), or use a [`Handshake`](crate::Handshake)
+/// to block or await.
+pub struct AtomicHandshake<T: Inline> {
+    inner: Arc<Inner>,
+    _value: PhantomData<T>,
+}
+
+impl<T: Inline> AtomicHandshake<T> {
+    pub fn new() -> (AtomicHandshake<T>, AtomicHandshake<T>) {
+        let inner = Arc::new(Inner { state: AtomicU8::new(EMPTY), value: AtomicU64::new(0) });
+        (AtomicHandshake { inner: inner.clone(), _value: PhantomData }, AtomicHandshake { inner, _value: PhantomData })
+    }
+
+    // moves from `from` to `to`, waiting out the peer's push, otherwise returns the state found
+    fn transition(&self, from: u8, to: u8) -> Result<(), u8> {
+        let mut spins = 0;
+        loop {
+            match self.inner.state.compare_exchange_weak(from, to, Ordering::AcqRel, Ordering::Acquire) {
+                Ok(_) => return Ok(()),
+                Err(BUSY) => sync::backoff(&mut spins),
+                Err(state) if state != from => return Err(state),
+                Err(_) => {} // spurious failure
+            }
+        }
+    }
+
+    /// Deposits `value` for the peer.
+    ///
+    /// Gives back the end and value when the peer already pushed (pull instead),
+    /// or just the value when the peer is gone.
+    pub fn try_push(self, value: T) -> Result<Result<(), (Self, T)>, T> {
+        match self.transition(EMPTY, BUSY) {
+            Ok(()) => {
+                self.inner.value.store(value.into_bits(), Ordering::Relaxed);
+                self.inner.state.store(SET, Ordering::Release);
+                self.finish();
+                Ok(Ok(()))
+            }
+            Err(SET) => Ok(Err((self, value))),
+            Err(_) => Err(value),
+        }
+    }
+
+    /// Takes the value deposited by the peer.
+    ///
+    /// Gives back the end when nothing was pushed yet, or [`Canceled`] when the peer is gone.
+    pub fn try_pull(self) -> Result<Result<T, Self>, Canceled> {
+        match self.transition(SET, TAKEN) {
+            Ok(()) => {
+                // taken is final, nobody writes the value anymore
+                let bits = self.inner.value.load(Ordering::Relaxed);
+                self.finish();
+                Ok(Ok(T::from_bits(bits)))
+            }
+            Err(EMPTY) => Ok(Err(self)),
+            Err(_) => Err(Canceled),
+        }
+    }
+
+    pub fn is_set(&self) -> bool {
+        self.inner.state.load(Ordering::Acquire) != EMPTY
+    }
+
+    pub fn state(&self) -> State {
+        State::of(self.inner.state.load(Ordering::Acquire))
+    }
+
+    // consumes `self` without cancelling
+    fn finish(self) {
+        let this = ManuallyDrop::new(self);
+        // never touched again
+        drop(unsafe { std::ptr::read(&this.inner) })
+    }
+}
+
+impl<T: Inline> Drop for AtomicHandshake<T> {
+    fn drop(&mut self) {
+        // a value left behind is plain bits, nothing to drop
+        if self.transition(EMPTY, CANCELED).is_err() { let _ = self.transition(SET, CANCELED); }
+    }
+}
+
+impl<T: Inline> Debug for AtomicHandshake<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AtomicHandshake").field("state", &self.state()).finish()
+    }
+}
@@ -0,0 +1,112 @@
+use std::{fmt::Debug, mem::ManuallyDrop, sync::Arc, time::{Duration, Instant}};
+
+use crate::{common, sync::{self, AtomicU32, Ordering}, Canceled, State};
+
+// the handshake's own states, so `State::of` reads them
+const EMPTY: u32 = common::EMPTY as u32;
+const SET: u32 = common::SET as u32;
+const TAKEN: u32 = common::TAKEN as u32;
+const CANCELED: u32 = common::CANCELED as u32;
+
+/// One side of a two-party barrier, for "both threads reached this point" with nothing to exchange.
+///
+/// A single atomic word underneath, shared by both sides: every step is one
+/// compare-and-swap on it, and a waiting side blocks on the word itself.
+pub struct Barrier2 {
+    // `SET` once one side arrived, `TAKEN` once both did, `CANCELED` if one left instead
+    word: Arc<AtomicU32>,
+}
+
+impl Barrier2 {
+    pub fn new() -> (Barrier2, Barrier2) {
+        let word = Arc::new(AtomicU32::new(EMPTY));
+        (Barrier2 { word: word.clone() }, Barrier2 { word })
+    }
+
+    // hands over the word without counting this side gone
+    fn into_word(self) -> Arc<AtomicU32> {
+        let this = ManuallyDrop::new(self);
+        unsafe { std::ptr::read(&this.word) }
+    }
+
+    // arrives first, or completes the barrier the other side arrived at,
+    // leaving `Ok(true)` in the latter case
+    fn arrive_at(word: &AtomicU32) -> Result<bool, Canceled> {
+        match word.compare_exchange(EMPTY, SET, Ordering::AcqRel, Ordering::Acquire) {
+            Ok(_) => Ok(false),
+            // the other side may take its arrival back in the meantime
+            Err(SET) => match word.compare_exchange(SET, TAKEN, Ordering::AcqRel, Ordering::Acquire) {
+                Ok(_) => { sync::wake_all(word); Ok(true) }
+                Err(_) => Barrier2::arrive_at(word),
+            },
+            Err(_) => Err(Canceled),
+        }
+    }
+
+    /// Marks this side arrived without waiting, whether the other side already was.
+    pub fn arrive(self) -> Result<bool, Canceled> {
+        Barrier2::arrive_at(&self.into_word())
+    }
+
+    /// Arrives and blocks until the other side arrived too.
+    pub fn wait(self) -> Result<(), Canceled> {
+        let word = self.into_word();
+        if Barrier2::arrive_at(&word)? { return Ok(()) }
+        loop {
+            match word.load(Ordering::Acquire) {
+                SET => sync::wait(&word, SET, None),
+                TAKEN => return Ok(()),
+                _ => return Err(Canceled),
+            }
+        }
+    }
+
+    /// Like [`wait`](Self::wait), but gives back this side if the other one didn't arrive within `timeout`.
+    pub fn wait_timeout(self, timeout: Duration) -> Result<Result<(), Self>, Canceled> {
+        let deadline = Instant::now() + timeout;
+        let word = self.into_word();
+        if Barrier2::arrive_at(&word)? { return Ok(Ok(())) }
+        loop {
+            match word.load(Ordering::Acquire) {
+                SET => {
+                    let now = Instant::now();
+                    if now < deadline { sync::wait(&word, SET, Some(deadline - now)); continue }
+                    // taken back unless the other side arrived just now
+                    if word.compare_exchange(SET, EMPTY, Ordering::AcqRel, Ordering::Acquire).is_ok() {
+                        return Ok(Err(Barrier2 { word }));
+                    }
+                }
+                TAKEN => return Ok(Ok(())),
+                _ => return Err(Canceled),
+            }
+        }
+    }
+
+    /// Whether the other side is already waiting here.
+    pub fn peer_arrived(&self) -> bool {
+        self.word.load(Ordering::Acquire) == SET
+    }
+
+    pub fn state(&self) -> State {
+        State::of(self.word.load(Ordering::Acquire) as u8)
+    }
+}
+
+impl Drop for Barrier2 {
+    fn drop(&mut self) {
+        // leaving before arriving, whether the other side is waiting or not
+        let mut state = self.word.load(Ordering::Acquire);
+        while matches!(state, EMPTY | SET) {
+            match self.word.compare_exchange(state, CANCELED, Ordering::AcqRel, Ordering::Acquire) {
+                Ok(_) => { if state == SET { sync::wake_all(&self.word) } return }
+                Err(found) => state = found,
+            }
+        }
+    }
+}
+
+impl Debug for Barrier2 {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Barrier2").field("state", &self.state()).finish()
+    }
+}
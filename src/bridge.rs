@@ -0,0 +1,81 @@
+use std::fmt::Debug;
+
+use crate::{Canceled, Handshake, PullFuture, PushOutcome, Receipt, State};
+
+/// The end of a bridged pair used from blocking code, see [`Handshake::bridge`].
+pub struct BlockingEnd<T> {
+    handshake: Handshake<T>,
+}
+
+/// The end of a bridged pair used from async code, see [`Handshake::bridge`].
+pub struct AsyncEnd<T> {
+    handshake: Handshake<T>,
+}
+
+impl<T> Handshake<T> {
+    /// Creates a pair connecting a blocking thread to an async task, each end
+    /// offering only the methods suiting its side.
+    ///
+    /// Either end may push for the other to pull. A blocked thread is unparked
+    /// and a waiting task woken by whatever the other end does, so neither side
+    /// needs a runtime or a thread of its own.
+    pub fn bridge() -> (BlockingEnd<T>, AsyncEnd<T>) {
+        let (u, v) = Handshake::new();
+        (BlockingEnd { handshake: u }, AsyncEnd { handshake: v })
+    }
+}
+
+impl<T> BlockingEnd<T> {
+    /// Deposits `value`, blocking until the async end collected it.
+    ///
+    /// [`Canceled`] when the async end went away without it, or pushed as well,
+    /// the value being dropped.
+    pub fn push(self, value: T) -> Result<(), Canceled> {
+        match self.handshake.push(value) {
+            PushOutcome::Delivered(receipt) => receipt.wait(),
+            PushOutcome::Occupied(..) | PushOutcome::PeerGone(_) => Err(Canceled),
+        }
+    }
+
+    /// Blocks until the async end pushed, or [`Canceled`] once it went away.
+    pub fn pull(self) -> Result<T, Canceled> {
+        self.handshake.pull_blocking()
+    }
+
+    pub fn state(&self) -> State {
+        self.handshake.state()
+    }
+}
+
+impl<T> AsyncEnd<T> {
+    /// Deposits `value` for the blocking end, the receipt resolving once it was collected.
+    ///
+    /// Gives back the value if the blocking end went away or pushed as well.
+    pub fn push(self, value: T) -> Result<Receipt<T>, T> {
+        match self.handshake.push(value) {
+            PushOutcome::Delivered(receipt) => Ok(receipt),
+            PushOutcome::Occupied(_, value) | PushOutcome::PeerGone(value) => Err(value),
+        }
+    }
+
+    /// Waits for the blocking end to push, see [`Handshake::pull`].
+    pub fn pull(self) -> PullFuture<T> {
+        self.handshake.pull()
+    }
+
+    pub fn state(&self) -> State {
+        self.handshake.state()
+    }
+}
+
+impl<T> Debug for BlockingEnd<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BlockingEnd").field("state", &self.state()).finish()
+    }
+}
+
+impl<T> Debug for AsyncEnd<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AsyncEnd").field("state", &self.state()).finish()
+    }
+}
@@ -0,0 +1,67 @@
+use std::{fmt::Debug, sync::{Arc, Mutex, OnceLock, PoisonError, TryLockError}};
+
+use crate::{Canceled, Handshake, PullOutcome};
+
+/// A pulling end shared by any number of consumers, each receiving a clone of the
+/// pushed value, see [`Handshake::into_broadcast`].
+pub struct Broadcast<T> {
+    shared: Arc<Shared<T>>,
+}
+
+struct Shared<T> {
+    // the end, until a consumer collected the value
+    end: Mutex<Option<Handshake<T>>>,
+    outcome: OnceLock<Result<T, Canceled>>,
+}
+
+impl<T: Clone> Handshake<T> {
+    /// Turns this end into a one-shot broadcast, cloned for every consumer.
+    pub fn into_broadcast(self) -> Broadcast<T> {
+        Broadcast { shared: Arc::new(Shared { end: Mutex::new(Some(self)), outcome: OnceLock::new() }) }
+    }
+}
+
+impl<T: Clone> Broadcast<T> {
+    /// Blocks until the peer pushed or went away, returning a clone of the value.
+    pub fn wait(&self) -> Result<T, Canceled> {
+        if let Some(outcome) = self.shared.outcome.get() { return outcome.clone() }
+        // the first consumer pulls, the others queue up behind it
+        let mut end = self.shared.end.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(end) = end.take() {
+            let _ = self.shared.outcome.set(end.pull_blocking());
+        }
+        drop(end);
+        // unset only if the consumer that took the end unwound
+        self.shared.outcome.get().cloned().unwrap_or(Err(Canceled))
+    }
+
+    /// Returns a clone of the value if the peer pushed already, or [`Canceled`] if it went away.
+    pub fn try_pull(&self) -> Option<Result<T, Canceled>> {
+        if let Some(outcome) = self.shared.outcome.get() { return Some(outcome.clone()) }
+        let mut end = match self.shared.end.try_lock() {
+            Ok(end) => end,
+            Err(TryLockError::Poisoned(poisoned)) => poisoned.into_inner(),
+            // another consumer is pulling
+            Err(TryLockError::WouldBlock) => return None,
+        };
+        match end.take()?.pull_now() {
+            PullOutcome::Delivered(value) => { let _ = self.shared.outcome.set(Ok(value)); }
+            PullOutcome::Pending(pending) => { *end = Some(pending); return None }
+            PullOutcome::PeerGone => { let _ = self.shared.outcome.set(Err(Canceled)); }
+        }
+        drop(end);
+        self.shared.outcome.get().cloned()
+    }
+}
+
+impl<T> Clone for Broadcast<T> {
+    fn clone(&self) -> Self {
+        Broadcast { shared: self.shared.clone() }
+    }
+}
+
+impl<T> Debug for Broadcast<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Broadcast").finish_non_exhaustive()
+    }
+}
@@ -0,0 +1,112 @@
+use std::{
+    collections::VecDeque,
+    fmt::Debug,
+    sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError},
+};
+
+use crate::{Canceled, Handshake};
+
+/// One end of an exchange holding up to a fixed number of values in each
+/// direction, see [`Handshake::buffered`].
+pub struct BufferedHandshake<T> {
+    inner: Arc<Inner<T>>,
+    // pushes into `queues[side]`, pulls from the other
+    side: usize,
+}
+
+struct Inner<T> {
+    queues: Mutex<Queues<T>>,
+    // signaled on every push and when an end goes away
+    changed: Condvar,
+    capacity: usize,
+}
+
+struct Queues<T> {
+    queues: [VecDeque<T>; 2],
+    gone: bool,
+}
+
+impl<T> Handshake<T> {
+    /// Creates a pair where each end can push up to `capacity` values before the
+    /// peer pulls, for producers and consumers drifting apart by a few items.
+    ///
+    /// # Panics
+    /// If `capacity` is zero, use [`Handshake::new`] for a pure rendezvous.
+    pub fn buffered(capacity: usize) -> (BufferedHandshake<T>, BufferedHandshake<T>) {
+        assert!(capacity > 0, "buffered handshake needs room for a value");
+        let inner = Arc::new(Inner {
+            queues: Mutex::new(Queues { queues: [VecDeque::new(), VecDeque::new()], gone: false }),
+            changed: Condvar::new(),
+            capacity,
+        });
+        (BufferedHandshake { inner: inner.clone(), side: 0 }, BufferedHandshake { inner, side: 1 })
+    }
+}
+
+impl<T> BufferedHandshake<T> {
+    fn lock(&self) -> MutexGuard<'_, Queues<T>> {
+        self.inner.queues.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Queues `value` for the peer.
+    ///
+    /// Gives back the value inside `Ok` when `capacity` values are already
+    /// waiting, or as the error when the peer is gone.
+    pub fn try_push(&self, value: T) -> Result<Result<(), T>, T> {
+        let mut queues = self.lock();
+        if queues.gone { return Err(value); }
+        let queue = &mut queues.queues[self.side];
+        if queue.len() == self.inner.capacity { return Ok(Err(value)); }
+        queue.push_back(value);
+        drop(queues);
+        self.inner.changed.notify_all();
+        Ok(Ok(()))
+    }
+
+    /// Takes the oldest value queued by the peer, if any.
+    ///
+    /// Values queued before the peer went away are still handed out, [`Canceled`] only follows them.
+    pub fn try_pull(&self) -> Result<Option<T>, Canceled> {
+        let mut queues = self.lock();
+        match queues.queues[1 - self.side].pop_front() {
+            Some(value) => Ok(Some(value)),
+            None if queues.gone => Err(Canceled),
+            None => Ok(None),
+        }
+    }
+
+    /// Blocks until the peer queued a value, or fails once it is gone and nothing is left.
+    pub fn pull(&self) -> Result<T, Canceled> {
+        let mut queues = self.lock();
+        loop {
+            if let Some(value) = queues.queues[1 - self.side].pop_front() { return Ok(value); }
+            if queues.gone { return Err(Canceled); }
+            queues = self.inner.changed.wait(queues).unwrap_or_else(PoisonError::into_inner);
+        }
+    }
+
+    /// How many values this end queued that the peer hasn't pulled yet.
+    pub fn pending(&self) -> usize {
+        self.lock().queues[self.side].len()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.inner.capacity
+    }
+}
+
+impl<T> Drop for BufferedHandshake<T> {
+    fn drop(&mut self) {
+        self.lock().gone = true;
+        self.inner.changed.notify_all()
+    }
+}
+
+impl<T> Debug for BufferedHandshake<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BufferedHandshake")
+            .field("pending", &self.pending())
+            .field("capacity", &self.capacity())
+            .finish()
+    }
+}
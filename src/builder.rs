@@ -0,0 +1,104 @@
+#[cfg(feature = "timer")]
+use std::time::{Duration, Instant};
+
+use std::task::Poll;
+
+use crate::{relay, Handshake, State};
+
+// what to run once the exchange is over
+type Callback = Box<dyn FnOnce(State) + Send>;
+
+/// Configures a handshake pair before making it, see [`Handshake::builder`].
+pub struct HandshakeBuilder<T> {
+    #[cfg(feature = "timer")]
+    deadline: Option<Instant>,
+    #[cfg(feature = "timer")]
+    fallback: Option<T>,
+    callbacks: Vec<Callback>,
+    _value: std::marker::PhantomData<fn(T) -> T>,
+}
+
+impl<T: Send + 'static> Handshake<T> {
+    /// Starts configuring a pair, instead of picking among constructors.
+    pub fn builder() -> HandshakeBuilder<T> {
+        HandshakeBuilder {
+            #[cfg(feature = "timer")]
+            deadline: None,
+            #[cfg(feature = "timer")]
+            fallback: None,
+            callbacks: Vec::new(),
+            _value: std::marker::PhantomData,
+        }
+    }
+}
+
+impl<T: Send + 'static> HandshakeBuilder<T> {
+    /// Cancels the exchange once `deadline` passes, see [`Handshake::with_deadline`].
+    #[cfg(feature = "timer")]
+    pub fn deadline(mut self, deadline: Instant) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// Like [`deadline`](Self::deadline), `ttl` from now.
+    #[cfg(feature = "timer")]
+    pub fn ttl(self, ttl: Duration) -> Self {
+        self.deadline(Instant::now() + ttl)
+    }
+
+    /// Answers for the first end with `fallback` at the deadline rather than
+    /// canceling, see [`Handshake::with_fallback`]. Does nothing without a deadline.
+    #[cfg(feature = "timer")]
+    pub fn fallback(mut self, fallback: T) -> Self {
+        self.fallback = Some(fallback);
+        self
+    }
+
+    /// Runs `callback` with the final state once the exchange completed or was
+    /// canceled, on the thread doing so.
+    pub fn on_complete<F: FnOnce(State) + Send + 'static>(mut self, callback: F) -> Self {
+        self.callbacks.push(Box::new(callback));
+        self
+    }
+
+    /// Runs `callback` if the exchange is canceled rather than completed.
+    pub fn on_lost<F: FnOnce() + Send + 'static>(self, callback: F) -> Self {
+        self.on_complete(move |state| if state == State::PeerGone { callback() })
+    }
+
+    /// Makes the pair, the first end being the one a fallback answers for.
+    pub fn build(self) -> (Handshake<T>, Handshake<T>) {
+        #[cfg(feature = "timer")]
+        let (u, v) = match (self.deadline, self.fallback) {
+            (Some(deadline), Some(fallback)) => Handshake::with_fallback(deadline, fallback),
+            (Some(deadline), None) => Handshake::with_deadline(deadline),
+            (None, _) => Handshake::new(),
+        };
+        #[cfg(not(feature = "timer"))]
+        let (u, v) = Handshake::new();
+        if self.callbacks.is_empty() { return (u, v) }
+        // watching the peer's side, which a fallback completes too
+        let watcher = v.watcher();
+        let mut callbacks = Some(self.callbacks);
+        relay::spawn(move |cx| {
+            if !watcher.is_finished() {
+                watcher.common().register(cx);
+                // re-check after registering so a wake-up can't slip through
+                if !watcher.is_finished() { return Poll::Pending }
+            }
+            let state = watcher.state();
+            callbacks.take().into_iter().flatten().for_each(|callback| (callback)(state));
+            Poll::Ready(())
+        });
+        (u, v)
+    }
+}
+
+impl<T> std::fmt::Debug for HandshakeBuilder<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut builder = f.debug_struct("HandshakeBuilder");
+        #[cfg(feature = "timer")]
+        builder.field("deadline", &self.deadline).field("fallback", &self.fallback.is_some());
+        builder.field("callbacks", &self.callbacks.len()).finish()
+    }
+}
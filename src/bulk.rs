@@ -0,0 +1,17 @@
+use crate::{Handshake, PullOutcome};
+
+/// Cancels every end, e.g. on shutdown, returning how many peers were still
+/// there to notice.
+pub fn cancel_all<T, S, I>(ends: I) -> usize
+where I: IntoIterator<Item = Handshake<T, S>> {
+    ends.into_iter().filter(Handshake::peer_alive).count()
+}
+
+/// Takes the value from every end whose peer already pushed, without blocking,
+/// reporting each end's outcome in order.
+///
+/// Ends still waiting come back as [`PullOutcome::Pending`] to drain again later.
+pub fn drain_ready<T, S, I>(ends: I) -> Vec<PullOutcome<T, S>>
+where I: IntoIterator<Item = Handshake<T, S>> {
+    ends.into_iter().map(Handshake::pull_now).collect()
+}
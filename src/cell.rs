@@ -0,0 +1,243 @@
+use std::{fmt::Debug, ptr::NonNull};
+#[cfg(feature = "futures")]
+use std::{pin::Pin, task::{Context, Poll}};
+
+use crate::{common::{self, Common}, Canceled, State};
+
+/// One end of a reusable handshake, returning to empty after every exchange so
+/// the same pair can rendezvous any number of times.
+///
+/// Dropping either end cancels the cell, though a value it deposited can still be pulled by the peer.
+pub struct HandshakeCell<T> {
+    // values are tagged with the side that deposited them
+    common: NonNull<Common<(bool, T)>>,
+    side: bool,
+    policy: OnOccupied,
+}
+
+/// What [`HandshakeCell::push_now`] does when this side's previous value is still
+/// waiting, see [`HandshakeCell::with_policy`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum OnOccupied {
+    /// Refuses the new value.
+    #[default]
+    Error,
+    /// Puts the new value in place of the old one, latest value wins.
+    Replace,
+    /// Leaves the old value be, handing the new one back.
+    Keep,
+}
+
+/// What came of [`HandshakeCell::push_now`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CellPush<T> {
+    /// Deposited for the peer.
+    Pushed,
+    /// Refused, a value still waits, the peer's or this side's under [`OnOccupied::Error`].
+    Occupied(T),
+    /// Deposited in place of this side's old value, handed back.
+    Replaced(T),
+    /// This side's old value left waiting, the new one handed back.
+    Kept(T),
+}
+
+impl<T> HandshakeCell<T> {
+    pub fn new() -> (HandshakeCell<T>, HandshakeCell<T>) {
+        HandshakeCell::with_policy(OnOccupied::Error)
+    }
+
+    /// Like [`new`](Self::new), both ends pushing under `policy`.
+    pub fn with_policy(policy: OnOccupied) -> (HandshakeCell<T>, HandshakeCell<T>) {
+        let common = Common::alloc(2);
+        (HandshakeCell { common, side: false, policy }, HandshakeCell { common, side: true, policy })
+    }
+
+    fn common(&self) -> &Common<(bool, T)> {
+        // shared state outlives both ends
+        unsafe { self.common.as_ref() }
+    }
+
+    // takes the peer's value unless it's our own waiting there
+    fn take_peer(&self) -> Result<T, u8> {
+        self.common().take_if(|(side, _)| *side != self.side).map(|(_, value)| value)
+    }
+
+    /// Deposits `value` for the peer.
+    ///
+    /// Gives the value back when the slot is still occupied (by either side),
+    /// or as an error when the peer is gone.
+    pub fn try_push(&mut self, value: T) -> Result<Result<(), T>, T> {
+        match self.common().put((self.side, value)) {
+            Ok(()) => Ok(Ok(())),
+            Err((common::SET, (_, value))) => Ok(Err(value)),
+            Err((_, (_, value))) => Err(value),
+        }
+    }
+
+    /// Deposits `value` for the peer, handling this side's value still waiting per
+    /// the cell's [`OnOccupied`] policy. Gives `value` back when the peer is gone.
+    pub fn push_now(&mut self, value: T) -> Result<CellPush<T>, T> {
+        let mut value = value;
+        loop {
+            match self.common().put((self.side, value)) {
+                Ok(()) => return Ok(CellPush::Pushed),
+                Err((common::SET, (_, rejected))) => value = rejected,
+                Err((_, (_, rejected))) => return Err(rejected),
+            }
+            let own = |(side, _): &(bool, T)| *side == self.side;
+            match self.policy {
+                OnOccupied::Error => return Ok(CellPush::Occupied(value)),
+                OnOccupied::Replace => match self.common().replace_if((self.side, value), own) {
+                    Ok((_, old)) => return Ok(CellPush::Replaced(old)),
+                    Err((common::SET, (_, rejected))) => return Ok(CellPush::Occupied(rejected)),
+                    // taken in the meantime, deposit anew
+                    Err((common::EMPTY, (_, rejected))) => value = rejected,
+                    Err((_, (_, rejected))) => return Err(rejected),
+                },
+                OnOccupied::Keep => match self.common().peek() {
+                    Some(waiting) if own(&waiting) => return Ok(CellPush::Kept(value)),
+                    Some(_) => return Ok(CellPush::Occupied(value)),
+                    // taken or canceled in the meantime, found out by depositing anew
+                    None => {}
+                },
+            }
+        }
+    }
+
+    /// Takes the value deposited by the peer, if there is one.
+    pub fn try_pull(&mut self) -> Result<Option<T>, Canceled> {
+        match self.take_peer() {
+            Ok(value) => Ok(Some(value)),
+            Err(common::EMPTY | common::SET | common::CLOSED) => Ok(None),
+            Err(_) => Err(Canceled),
+        }
+    }
+
+    /// Combines `value` with the peer's if it is there, or deposits it for the peer to combine.
+    ///
+    /// Blocks while this side's value from the previous round is still waiting for the peer.
+    pub fn join<U, F: FnOnce(T, T) -> U>(&mut self, value: T, f: F) -> Result<Option<U>, Canceled> {
+        let mut value = Some(value);
+        let combined = self.common().block_until(|| match self.take_peer() {
+            Ok(other) => Some(Ok(Some((other, value.take().unwrap())))),
+            // own value still waiting
+            Err(common::SET | common::CLOSED) => None,
+            Err(common::EMPTY) => match self.common().put((self.side, value.take().unwrap())) {
+                Ok(()) => Some(Ok(None)),
+                Err((_, (_, rejected))) => { value = Some(rejected); None }
+            },
+            Err(_) => Some(Err(Canceled)),
+        })?;
+        Ok(combined.map(|(other, value)| (f)(other, value)))
+    }
+
+    /// Blocks until the peer deposited a value and takes it, or [`Canceled`] once the peer is gone.
+    pub fn pull(&mut self) -> Result<T, Canceled> {
+        self.common().block_until(|| match self.take_peer() {
+            Ok(value) => Some(Ok(value)),
+            // own value waiting, or nothing yet
+            Err(common::EMPTY | common::SET | common::CLOSED) => None,
+            Err(_) => Some(Err(Canceled)),
+        })
+    }
+
+    /// Blocks until the slot is free and deposits `value`, giving it back once the peer is gone.
+    ///
+    /// Meant for a peer that only pulls, a value it deposits holds this up for good.
+    pub fn push(&mut self, value: T) -> Result<(), T> {
+        let mut value = Some(value);
+        self.common().block_until(|| match self.common().put((self.side, value.take().unwrap())) {
+            Ok(()) => Some(Ok(())),
+            Err((common::SET, (_, rejected))) => { value = Some(rejected); None }
+            Err((_, (_, rejected))) => Some(Err(rejected)),
+        })
+    }
+
+    pub fn state(&self) -> State {
+        State::of(self.common().state())
+    }
+}
+
+/// Yields every value the peer deposits, ending once the peer is gone.
+#[cfg(feature = "futures")]
+impl<T> futures_core::Stream for HandshakeCell<T> {
+    type Item = T;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        let this = self.get_mut();
+        match this.try_pull() {
+            Ok(Some(value)) => return Poll::Ready(Some(value)),
+            Ok(None) => {}
+            Err(Canceled) => return Poll::Ready(None),
+        }
+        this.common().register(cx);
+        // re-check after registering so a wake-up can't slip through
+        match this.try_pull() {
+            Ok(Some(value)) => Poll::Ready(Some(value)),
+            Ok(None) => Poll::Pending,
+            Err(Canceled) => Poll::Ready(None),
+        }
+    }
+}
+
+#[cfg(feature = "futures")]
+impl<T> HandshakeCell<T> {
+    // the state once no value sits in the slot, registering `cx` until then
+    fn poll_settled(&self, cx: &Context<'_>) -> Poll<u8> {
+        let mut state = self.common().state();
+        if matches!(state, common::SET | common::BUSY) {
+            self.common().register(cx);
+            // re-check after registering so a wake-up can't slip through
+            state = self.common().state();
+        }
+        if matches!(state, common::SET | common::BUSY) { Poll::Pending } else { Poll::Ready(state) }
+    }
+}
+
+/// Deposits values one at a time, ready whenever the slot is empty.
+///
+/// Meant for a peer that only pulls: a value the peer pushes in between
+/// readying and sending is taken for canceling, and the value sent is dropped.
+/// Flushing completes once the last value was taken, even if the peer left since.
+#[cfg(feature = "futures")]
+impl<T> futures_sink::Sink<T> for HandshakeCell<T> {
+    type Error = Canceled;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Canceled>> {
+        self.poll_settled(cx).map(|state| if state == common::EMPTY { Ok(()) } else { Err(Canceled) })
+    }
+
+    fn start_send(self: Pin<&mut Self>, value: T) -> Result<(), Canceled> {
+        match self.get_mut().try_push(value) {
+            Ok(Ok(())) => Ok(()),
+            _ => Err(Canceled),
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Canceled>> {
+        // lost means our value went with the peer
+        self.poll_settled(cx).map(|state| if state == common::LOST { Err(Canceled) } else { Ok(()) })
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Canceled>> {
+        self.poll_flush(cx)
+    }
+}
+
+impl<T> Drop for HandshakeCell<T> {
+    fn drop(&mut self) {
+        self.common().close(|(side, _)| *side == self.side);
+        // end owns a reference
+        unsafe { Common::release(self.common) }
+    }
+}
+
+unsafe impl<T: Send> Sync for HandshakeCell<T> {}
+
+unsafe impl<T: Send> Send for HandshakeCell<T> {}
+
+impl<T> Debug for HandshakeCell<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HandshakeCell").field("state", &self.state()).finish()
+    }
+}
@@ -0,0 +1,120 @@
+use std::{
+    collections::VecDeque,
+    fmt::Debug,
+    sync::{Arc, Mutex, PoisonError},
+};
+
+use crate::{common, sync::{AtomicBool, Ordering}, Canceled, Handshake, PullOutcome, PushOutcome};
+
+/// An unbuffered channel where every `send` blocks until matched with exactly one `recv`.
+///
+/// Handles are cloned freely to get any number of senders and receivers, each
+/// match being a handshake between the two parties.
+pub struct RendezvousChannel<T> {
+    inner: Arc<Inner<T>>,
+}
+
+struct Inner<T> {
+    // ends whose peer is blocked on the other side of a match
+    waiting: Mutex<Waiting<T>>,
+    closed: AtomicBool,
+}
+
+struct Waiting<T> {
+    senders: VecDeque<Handshake<T>>,
+    receivers: VecDeque<Handshake<T>>,
+}
+
+impl<T> RendezvousChannel<T> {
+    pub fn new() -> RendezvousChannel<T> {
+        RendezvousChannel { inner: Arc::new(Inner {
+            waiting: Mutex::new(Waiting { senders: VecDeque::new(), receivers: VecDeque::new() }),
+            closed: AtomicBool::new(false),
+        })}
+    }
+
+    fn is_closed(&self) -> bool {
+        self.inner.closed.load(Ordering::Acquire)
+    }
+
+    /// Blocks until a receiver took `value`, or hands it back once the channel is closed.
+    pub fn send(&self, value: T) -> Result<(), T> {
+        let receipt = {
+            let mut waiting = self.inner.waiting.lock().unwrap_or_else(PoisonError::into_inner);
+            if self.is_closed() { return Err(value); }
+            let mut value = value;
+            loop {
+                match waiting.receivers.pop_front() {
+                    Some(receiver) => match receiver.push(value) {
+                        // receiver blocked on the peer end, guaranteed to collect
+                        PushOutcome::Delivered(receipt) => break receipt,
+                        // receiver gave up
+                        PushOutcome::Occupied(_, rejected) | PushOutcome::PeerGone(rejected) => value = rejected,
+                    },
+                    None => {
+                        let (ours, theirs) = Handshake::new();
+                        let PushOutcome::Delivered(receipt) = ours.push(value) else { unreachable!() };
+                        waiting.senders.push_back(theirs);
+                        break receipt
+                    }
+                }
+            }
+        };
+        receipt.common().block_while(|state| state == common::SET && !self.is_closed());
+        match receipt.retract() {
+            Ok((_, value)) => Err(value),
+            // collected in the meantime
+            Err(_) => Ok(()),
+        }
+    }
+
+    /// Blocks until a sender hands over a value, or fails once the channel is closed.
+    pub fn recv(&self) -> Result<T, Canceled> {
+        let ours = {
+            let mut waiting = self.inner.waiting.lock().unwrap_or_else(PoisonError::into_inner);
+            while let Some(sender) = waiting.senders.pop_front() {
+                // otherwise the sender gave up
+                if let PullOutcome::Delivered(value) = sender.pull_now() { return Ok(value); }
+            }
+            if self.is_closed() { return Err(Canceled); }
+            let (ours, theirs) = Handshake::new();
+            waiting.receivers.push_back(theirs);
+            ours
+        };
+        ours.common().block_while(|state| state == common::EMPTY);
+        match ours.pull_now() {
+            PullOutcome::Delivered(value) => Ok(value),
+            _ => Err(Canceled),
+        }
+    }
+
+    /// Fails all pending and future operations, handing values back to blocked senders.
+    pub fn close(&self) {
+        let mut waiting = self.inner.waiting.lock().unwrap_or_else(PoisonError::into_inner);
+        self.inner.closed.store(true, Ordering::Release);
+        // blocked senders take their values back
+        waiting.senders.iter().for_each(|sender| sender.common().wake());
+        // blocked receivers see their peer gone
+        let receivers = std::mem::take(&mut waiting.receivers);
+        drop(waiting);
+        drop(receivers)
+    }
+}
+
+impl<T> Default for RendezvousChannel<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Clone for RendezvousChannel<T> {
+    fn clone(&self) -> Self {
+        RendezvousChannel { inner: self.inner.clone() }
+    }
+}
+
+impl<T> Debug for RendezvousChannel<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RendezvousChannel").field("closed", &self.is_closed()).finish()
+    }
+}
@@ -0,0 +1,72 @@
+//! Scripted faults in pushes and pulls, for testing how code built on handshakes
+//! copes with slow peers, peers going away and unlucky interleavings.
+//!
+//! Faults are scripted per thread and taken one by one by the next
+//! [`push`](crate::Handshake::push) or [`pull_now`](crate::Handshake::pull_now)
+//! on that thread, or anything built on them, such as pull futures.
+
+use std::{
+    cell::RefCell,
+    collections::VecDeque,
+    sync::{Arc, Condvar, Mutex, PoisonError},
+    time::Duration,
+};
+
+thread_local! {
+    static SCRIPT: RefCell<VecDeque<Fault>> = const { RefCell::new(VecDeque::new()) };
+}
+
+/// What happens to the next push or pull, see [`inject`].
+#[derive(Debug, Clone)]
+pub enum Fault {
+    /// Sleeps before going ahead.
+    Delay(Duration),
+    /// Fails as if the peer had gone away, dropping the end so the peer sees it gone too.
+    Cancel,
+    /// Waits for the gate to open before going ahead, to force an ordering between threads.
+    Wait(Gate),
+}
+
+/// Holds up a [`Fault::Wait`] until opened.
+#[derive(Debug, Clone, Default)]
+pub struct Gate {
+    inner: Arc<(Mutex<bool>, Condvar)>,
+}
+
+impl Gate {
+    pub fn new() -> Gate {
+        Gate::default()
+    }
+
+    /// Lets everything waiting on the gate, now or later, go ahead.
+    pub fn open(&self) {
+        *self.inner.0.lock().unwrap_or_else(PoisonError::into_inner) = true;
+        self.inner.1.notify_all()
+    }
+
+    fn wait(&self) {
+        let open = self.inner.0.lock().unwrap_or_else(PoisonError::into_inner);
+        drop(self.inner.1.wait_while(open, |open| !*open).unwrap_or_else(PoisonError::into_inner))
+    }
+}
+
+/// Scripts `fault` for the calling thread's push or pull after those already scripted.
+pub fn inject(fault: Fault) {
+    SCRIPT.with(|script| script.borrow_mut().push_back(fault))
+}
+
+/// Drops the faults the calling thread hasn't hit yet.
+pub fn clear() {
+    SCRIPT.with(|script| script.borrow_mut().clear())
+}
+
+// applies the next scripted fault, true if the operation is to fail as canceled
+pub(crate) fn strike() -> bool {
+    let Some(fault) = SCRIPT.with(|script| script.borrow_mut().pop_front()) else { return false };
+    match fault {
+        Fault::Delay(delay) => std::thread::sleep(delay),
+        Fault::Cancel => return true,
+        Fault::Wait(gate) => gate.wait(),
+    }
+    false
+}
@@ -0,0 +1,189 @@
+use std::task::{Context, Poll, Waker};
+
+use crate::{common, relay, Handshake, State};
+
+/// Resolves with whichever of `a` and `b` is pushed to first, canceling the other.
+///
+/// The returned end only receives: forwarding happens on the thread completing
+/// the winner, and the result is canceled once both contenders are.
+pub fn race<T: Send + 'static>(a: Handshake<T>, b: Handshake<T>) -> Handshake<T> {
+    let (out, ours) = Handshake::new();
+    let mut contenders = [Some(a), Some(b)];
+    let mut ours = Some(ours);
+    relay::spawn(move |cx| {
+        let Some(out) = ours.as_ref() else { return Poll::Ready(()) };
+        out.common().register(cx);
+        // nobody left to deliver to, cancel both
+        if out.state() == State::PeerGone { return Poll::Ready(()); }
+        for contender in contenders.iter_mut() {
+            let Some(handshake) = contender.take() else { continue };
+            match handshake.poll_pull(cx) {
+                Ok(Ok(value)) => {
+                    // loser is canceled along with the step
+                    let _ = ours.take().unwrap().push(value);
+                    return Poll::Ready(())
+                }
+                Ok(Err(handshake)) => *contender = Some(handshake),
+                Err(_) => {}
+            }
+        }
+        if contenders.iter().all(Option::is_none) { Poll::Ready(()) } else { Poll::Pending }
+    });
+    out
+}
+
+/// Resolves with both values once `a` and `b` were both pushed to.
+///
+/// Canceling either side cancels the result, and dropping the result cancels both.
+pub fn zip<A, B>(a: Handshake<A>, b: Handshake<B>) -> Handshake<(A, B)>
+where A: Send + 'static, B: Send + 'static {
+    let (out, ours) = Handshake::new();
+    let (mut a, mut b) = (Pulling::Waiting(a), Pulling::Waiting(b));
+    let mut ours = Some(ours);
+    relay::spawn(move |cx| {
+        let Some(out) = ours.as_ref() else { return Poll::Ready(()) };
+        out.common().register(cx);
+        // nobody left to deliver to, cancel both
+        if out.state() == State::PeerGone { return Poll::Ready(()); }
+        // either canceled, the result is canceled with the step
+        if !a.poll(cx) || !b.poll(cx) { return Poll::Ready(()); }
+        if !(a.is_done() && b.is_done()) { return Poll::Pending; }
+        let _ = ours.take().unwrap().push((a.take().unwrap(), b.take().unwrap()));
+        Poll::Ready(())
+    });
+    out
+}
+
+impl<A: Send + 'static, B: Send + 'static> Handshake<(A, B)> {
+    /// Splits the pair received from the peer into two ends, each receiving its half.
+    ///
+    /// The inverse of [`zip`]: canceling this side cancels both halves, and the
+    /// peer is canceled only once both halves are dropped.
+    pub fn unzip(self) -> (Handshake<A>, Handshake<B>) {
+        let ((a_out, a_ours), (b_out, b_ours)) = (Handshake::new(), Handshake::new());
+        let mut input = Pulling::Waiting(self);
+        let mut ours = Some((a_ours, b_ours));
+        relay::spawn(move |cx| {
+            let Some((a, b)) = ours.as_ref() else { return Poll::Ready(()) };
+            a.common().register(cx);
+            b.common().register(cx);
+            // nobody left to deliver to, cancel the input
+            if a.state() == State::PeerGone && b.state() == State::PeerGone { return Poll::Ready(()); }
+            // canceled, both halves are canceled with the step
+            if !input.poll(cx) { return Poll::Ready(()); }
+            let Some((x, y)) = input.take() else { return Poll::Pending };
+            let (a, b) = ours.take().unwrap();
+            let _ = a.push(x);
+            let _ = b.push(y);
+            Poll::Ready(())
+        });
+        (a_out, b_out)
+    }
+}
+
+impl<T: Send + 'static> Handshake<T> {
+    /// Adapts the value received from the peer with `f`.
+    ///
+    /// No thread is involved: `f` runs on whichever thread completes the peer's push.
+    /// Canceling either side cancels the other.
+    pub fn map<U, F>(self, f: F) -> Handshake<U>
+    where U: Send + 'static, F: FnOnce(T) -> U + Send + 'static {
+        let (out, ours) = Handshake::new();
+        let mut input = Pulling::Waiting(self);
+        let mut adapt = Some((ours, f));
+        relay::spawn(move |cx| {
+            let Some((out, _)) = adapt.as_ref() else { return Poll::Ready(()) };
+            out.common().register(cx);
+            // nobody left to deliver to, cancel the input
+            if out.state() == State::PeerGone || !input.poll(cx) { return Poll::Ready(()); }
+            let Some(value) = input.take() else { return Poll::Pending };
+            let (out, f) = adapt.take().unwrap();
+            let _ = out.push((f)(value));
+            Poll::Ready(())
+        });
+        out
+    }
+
+    /// Runs `callback` on the peer's value as soon as it is deposited, on the depositing thread.
+    ///
+    /// The value stays in place for this end to pull. Nothing runs if the peer
+    /// goes away or the value is collected before the callback got to it.
+    pub fn on_peer_push<F>(&self, callback: F)
+    where F: FnOnce(&T) + Send + 'static {
+        let watcher = self.watcher();
+        let mut callback = Some(callback);
+        relay::spawn(move |cx| loop {
+            let common = watcher.common();
+            if common.state() == common::EMPTY {
+                common.register(cx);
+                // re-check after registering so a wake-up can't slip through
+                if common.state() == common::EMPTY { return Poll::Pending }
+            }
+            if let Some(value) = common.peek() {
+                if let Some(callback) = callback.take() { (callback)(&value) }
+                return Poll::Ready(())
+            }
+            // retracted in the meantime, otherwise over
+            if common.state() != common::EMPTY { return Poll::Ready(()) }
+        });
+    }
+
+    /// Runs `callback` exactly once, as soon as a value is deposited or either end
+    /// goes away, with where the exchange stands then, e.g. to hook into a custom executor.
+    ///
+    /// Runs on the thread making that change, or right away if it already happened.
+    pub fn notify_on_complete<F>(&self, callback: F)
+    where F: FnOnce(State) + Send + 'static {
+        let watcher = self.watcher();
+        let mut callback = Some(callback);
+        relay::spawn(move |cx| {
+            let common = watcher.common();
+            if common.state() == common::EMPTY {
+                common.register(cx);
+                // re-check after registering so a wake-up can't slip through
+                if common.state() == common::EMPTY { return Poll::Pending }
+            }
+            if let Some(callback) = callback.take() { (callback)(watcher.state()) }
+            Poll::Ready(())
+        });
+    }
+
+    /// Wakes `waker` exactly once, see [`notify_on_complete`](Self::notify_on_complete).
+    pub fn register_waker(&self, waker: Waker) {
+        self.notify_on_complete(move |_| waker.wake())
+    }
+}
+
+// one input of a combinator, before and after its value arrived
+pub(crate) enum Pulling<T> {
+    Waiting(Handshake<T>),
+    Done(T),
+    Empty,
+}
+
+impl<T> Pulling<T> {
+    // polls for the value, false if canceled
+    pub(crate) fn poll(&mut self, cx: &Context<'_>) -> bool {
+        let handshake = match std::mem::replace(self, Pulling::Empty) {
+            Pulling::Waiting(handshake) => handshake,
+            other => { *self = other; return true }
+        };
+        match handshake.poll_pull(cx) {
+            Ok(Ok(value)) => *self = Pulling::Done(value),
+            Ok(Err(handshake)) => *self = Pulling::Waiting(handshake),
+            Err(_) => return false,
+        }
+        true
+    }
+
+    fn is_done(&self) -> bool {
+        matches!(self, Pulling::Done(_))
+    }
+
+    pub(crate) fn take(&mut self) -> Option<T> {
+        match std::mem::replace(self, Pulling::Empty) {
+            Pulling::Done(value) => Some(value),
+            other => { *self = other; None }
+        }
+    }
+}
@@ -0,0 +1,821 @@
+use std::{
+    cell::UnsafeCell,
+    fmt::Debug,
+    mem::{ManuallyDrop, MaybeUninit},
+    ops::Deref,
+    ptr::NonNull,
+    sync::Arc,
+    task::{Context, Wake, Waker},
+    time::Instant,
+};
+
+#[cfg(feature = "allocator")]
+use std::alloc::{handle_alloc_error, Layout};
+
+#[cfg(feature = "allocator")]
+use allocator_api2::alloc::Allocator;
+
+use crate::sync::{self, fence, AtomicU32, AtomicU8, Lock, Ordering, Thread};
+
+#[cfg(feature = "history")]
+use crate::history::EventKind::{self, Canceled, Created, Pulled, Pushed, Retracted};
+
+// the steps the `history` feature records, ignored without it
+#[cfg(not(feature = "history"))]
+enum EventKind { Created, Pushed, Pulled, Retracted, Canceled }
+
+#[cfg(not(feature = "history"))]
+use EventKind::{Canceled, Created, Pulled, Pushed, Retracted};
+
+// nothing deposited, both ends alive
+pub(crate) const EMPTY: u8 = 0;
+// a value is being moved in or out
+pub(crate) const BUSY: u8 = 1;
+// a value is waiting for the peer
+pub(crate) const SET: u8 = 2;
+// the peer took the value
+pub(crate) const TAKEN: u8 = 3;
+// an end dropped before anything was deposited
+pub(crate) const CANCELED: u8 = 4;
+// the peer dropped without taking the value
+pub(crate) const LOST: u8 = 5;
+// the peer is combining the value with its own
+pub(crate) const COMBINING: u8 = 6;
+// a value is waiting but its depositor went away
+pub(crate) const CLOSED: u8 = 7;
+// the peer swapped the deposited value for its own
+pub(crate) const SWAPPED: u8 = 8;
+// the peer dropped without taking the value, still there to reclaim
+pub(crate) const ORPHANED: u8 = 9;
+// the deadline passed before the exchange completed, any deposited value dropped
+pub(crate) const TIMED_OUT: u8 = 10;
+// the peer left the value in place for good, for everyone to read
+pub(crate) const SHARED: u8 = 11;
+
+// the bits of the state word holding the state, the rest are for blocked threads
+const STATE: u32 = 0xff;
+// some thread is blocked waiting on the word
+const WAITING: u32 = 1 << 8;
+// counts wake-ups from here up, so a wake that changes no state still changes the word
+const WOKEN: u32 = 1 << 9;
+
+/// State shared by both ends of a handshake and any receipts.
+pub(crate) struct Common<T> {
+    // the state in the low bits, blocked threads wait on the whole word
+    state: AtomicU32,
+    // bumped on every recycle, telling the exchanges sharing the slot apart
+    generation: AtomicU8,
+    refs: AtomicU32,
+    value: UnsafeCell<MaybeUninit<T>>,
+    wakers: Lock<Vec<Waker>>,
+    // hands the slot back to the allocator it came from
+    #[cfg(feature = "allocator")]
+    free: unsafe fn(NonNull<Common<T>>),
+    // where the handshake was made, for reporting it should it die
+    #[cfg(feature = "leak-check")]
+    origin: std::sync::OnceLock<std::backtrace::Backtrace>,
+    // every step taken so far, with the `history` feature
+    #[cfg(feature = "history")]
+    history: Lock<Vec<crate::history::Event>>,
+    // when the value waiting was deposited, with the `timestamps` feature
+    #[cfg(feature = "timestamps")]
+    pushed_at: Lock<Option<Instant>>,
+    // when the exchange was made, unknown for statics
+    #[cfg(feature = "timestamps")]
+    created_at: Lock<Option<Instant>>,
+    // the thread both ends stay on, while neither can move, with the `deadlock-detection` feature
+    #[cfg(feature = "deadlock-detection")]
+    owner: Lock<Option<std::thread::ThreadId>>,
+}
+
+impl<T> Common<T> {
+    /// Makes a slot referenced by `refs` owners, to be freed by whoever made it
+    /// unless `release` can bring the count to zero.
+    pub(crate) fn new(refs: u32) -> Common<T> {
+        tally(EMPTY);
+        #[cfg(not(loom))]
+        let common = Common::new_uncounted(refs);
+        #[cfg(loom)]
+        let common = Common {
+            state: AtomicU32::new(EMPTY as u32),
+            generation: AtomicU8::new(0),
+            refs: AtomicU32::new(refs),
+            value: UnsafeCell::new(MaybeUninit::uninit()),
+            wakers: Lock::new(Vec::new()),
+            #[cfg(feature = "allocator")]
+            free: free_boxed,
+            #[cfg(feature = "leak-check")]
+            origin: std::sync::OnceLock::new(),
+            #[cfg(feature = "history")]
+            history: Lock::new(Vec::new()),
+            #[cfg(feature = "timestamps")]
+            pushed_at: Lock::new(None),
+            #[cfg(feature = "timestamps")]
+            created_at: Lock::new(None),
+            #[cfg(feature = "deadlock-detection")]
+            owner: Lock::new(None),
+        };
+        #[cfg(feature = "leak-check")]
+        let _ = common.origin.set(crate::leak_check::origin());
+        common.log(Created);
+        common
+    }
+
+    /// Like [`new`](Self::new), but usable in a `static`, leaving it to the caller to count it created.
+    #[cfg(not(loom))]
+    pub(crate) const fn new_uncounted(refs: u32) -> Common<T> {
+        Common {
+            state: AtomicU32::new(EMPTY as u32),
+            generation: AtomicU8::new(0),
+            refs: AtomicU32::new(refs),
+            value: UnsafeCell::new(MaybeUninit::uninit()),
+            wakers: Lock::new(Vec::new()),
+            #[cfg(feature = "allocator")]
+            free: free_boxed,
+            #[cfg(feature = "leak-check")]
+            origin: std::sync::OnceLock::new(),
+            #[cfg(feature = "history")]
+            history: Lock::new(Vec::new()),
+            #[cfg(feature = "timestamps")]
+            pushed_at: Lock::new(None),
+            #[cfg(feature = "timestamps")]
+            created_at: Lock::new(None),
+            #[cfg(feature = "deadlock-detection")]
+            owner: Lock::new(None),
+        }
+    }
+
+    /// Allocates a slot referenced by `refs` owners.
+    pub(crate) fn alloc(refs: u32) -> NonNull<Common<T>> {
+        Common::boxed(Common::new(refs))
+    }
+
+    /// Allocates a slot referenced by `refs` owners with `value` already deposited.
+    pub(crate) fn alloc_with(refs: u32, value: T) -> NonNull<Common<T>> {
+        let mut common = Common::new(refs);
+        // nobody else sees the slot yet
+        common.value = UnsafeCell::new(MaybeUninit::new(value));
+        common.state = AtomicU32::new(SET as u32);
+        common.log(Pushed);
+        Common::boxed(common)
+    }
+
+    fn boxed(common: Common<T>) -> NonNull<Common<T>> {
+        // check expected to be elided during compilation
+        unsafe { NonNull::new_unchecked(Box::into_raw(Box::new(common))) }
+    }
+
+    /// Allocates a slot referenced by `refs` owners from `alloc`.
+    #[cfg(feature = "allocator")]
+    pub(crate) fn alloc_in<A: Allocator + Send + Sync + 'static>(refs: u32, alloc: A) -> NonNull<Common<T>> {
+        let layout = Layout::new::<InAlloc<T, A>>();
+        let ptr = alloc.allocate(layout).unwrap_or_else(|_| handle_alloc_error(layout)).cast::<InAlloc<T, A>>();
+        let mut common = Common::new(refs);
+        common.free = free_in::<T, A>;
+        unsafe { ptr.as_ptr().write(InAlloc { common, alloc }) };
+        // the slot comes first
+        ptr.cast()
+    }
+
+    /// Takes one more reference for a new owner.
+    pub(crate) fn acquire(&self) {
+        // an existing reference keeps the slot alive, nothing to synchronize
+        self.refs.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Gives up one reference, freeing the slot if it was the last.
+    ///
+    /// # Safety
+    /// `ptr` must own a reference and not be used afterwards.
+    pub(crate) unsafe fn release(ptr: NonNull<Common<T>>) {
+        if ptr.as_ref().refs.fetch_sub(1, Ordering::Release) != 1 { return; }
+        fence(Ordering::Acquire);
+        // last reference, drop pointer
+        #[cfg(feature = "allocator")]
+        return (ptr.as_ref().free)(ptr);
+        #[cfg(not(feature = "allocator"))]
+        free_boxed(ptr)
+    }
+
+    /// Resets a slot whose exchange completed for another one, if the caller holds its only reference.
+    ///
+    /// Hands out two references for the new exchange on success.
+    pub(crate) fn recycle(&self) -> bool {
+        // the peer released after taking, synchronize with that
+        if self.refs.load(Ordering::Acquire) != 1 || self.state() != TAKEN { return false; }
+        // sole owner, nobody else looks at the slot
+        self.wakers.with(Vec::clear);
+        #[cfg(feature = "history")]
+        self.history.with(Vec::clear);
+        #[cfg(feature = "timestamps")]
+        self.pushed_at.with(|at| *at = None);
+        #[cfg(feature = "deadlock-detection")]
+        self.owner.with(|owner| *owner = None);
+        self.log(Created);
+        self.generation.store(self.generation.load(Ordering::Relaxed).wrapping_add(1), Ordering::Relaxed);
+        self.refs.store(2, Ordering::Relaxed);
+        self.state.store(EMPTY as u32, Ordering::Release);
+        tally(EMPTY);
+        true
+    }
+
+    pub(crate) fn state(&self) -> u8 {
+        (self.state.load(Ordering::Acquire) & STATE) as u8
+    }
+
+    // moves from `from` to `to`, waiting out any `BUSY` period,
+    // otherwise returns the state that was found instead
+    fn transition(&self, from: u8, to: u8) -> Result<(), u8> {
+        let mut spins = 0;
+        loop {
+            match self.try_transition(from, to) {
+                Err(BUSY) => sync::backoff(&mut spins),
+                res => return res,
+            }
+        }
+    }
+
+    // like `transition`, but failing with `BUSY` rather than waiting it out
+    fn try_transition(&self, from: u8, to: u8) -> Result<(), u8> {
+        let mut word = self.state.load(Ordering::Acquire);
+        loop {
+            let state = (word & STATE) as u8;
+            if state != from { return Err(state) }
+            // the bits of blocked threads carry over
+            match self.state.compare_exchange_weak(word, word & !STATE | to as u32, Ordering::AcqRel, Ordering::Acquire) {
+                Ok(_) => return Ok(()),
+                Err(found) => word = found,
+            }
+        }
+    }
+
+    // leaves `held`, a state only its holder moves out of, for `state`
+    fn store(&self, held: u8, state: u8) {
+        // flips just the state bits, blocked threads may be setting theirs
+        self.state.fetch_xor((held ^ state) as u32, Ordering::Release);
+    }
+
+    // leaves `BUSY` for `state`, waking everyone
+    fn settle(&self, state: u8) {
+        self.store(BUSY, state);
+        self.wake();
+    }
+
+    /// Deposits `value` if nothing is there yet.
+    pub(crate) fn put(&self, value: T) -> Result<(), (u8, T)> {
+        if let Err(state) = self.transition(EMPTY, BUSY) {
+            return Err((state, value));
+        }
+        // unique access while busy
+        self.write(|slot| { slot.write(value); });
+        self.log(Pushed);
+        self.settle(SET);
+        Ok(())
+    }
+
+    /// Deposits the value `init` builds in place if nothing is there yet,
+    /// otherwise hands `init` back with the state found.
+    ///
+    /// # Panics
+    /// If `init` returns anything but the slot it was handed, leaving the slot empty.
+    pub(crate) fn put_with<F>(&self, init: F) -> Result<(), (u8, F)>
+    where F: FnOnce(&mut MaybeUninit<T>) -> &mut T {
+        if let Err(state) = self.transition(EMPTY, BUSY) {
+            return Err((state, init));
+        }
+        // back to empty should `init` unwind, nothing is deposited
+        struct Reset<'a, T>(&'a Common<T>);
+        impl<T> Drop for Reset<'_, T> {
+            fn drop(&mut self) { self.0.store(BUSY, EMPTY) }
+        }
+        let reset = Reset(self);
+        // unique access while busy
+        let slot = unsafe { &mut *self.value.get() };
+        let expected = slot.as_mut_ptr();
+        let built: *mut T = init(slot);
+        assert!(std::ptr::eq(built, expected), "push_with_uninit must return the slot it was handed");
+        std::mem::forget(reset);
+        self.log(Pushed);
+        self.settle(SET);
+        Ok(())
+    }
+
+    /// Takes a deposited value on behalf of the peer.
+    pub(crate) fn take(&self) -> Result<T, u8> {
+        self.transition(SET, TAKEN)?;
+        tally(TAKEN);
+        self.log(Pulled);
+        // taken is final, nobody else touches the value
+        let value = unsafe { (*self.value.get()).assume_init_read() };
+        self.wake();
+        Ok(value)
+    }
+
+    /// Like [`put`](Self::put), but failing with `BUSY` rather than waiting it out
+    /// and waking in place, for interrupt handlers.
+    #[cfg(feature = "embassy")]
+    pub(crate) fn put_isr(&self, value: T) -> Result<(), (u8, T)> {
+        // never waits, the busy period may belong to the code this handler interrupted
+        if let Err(state) = self.try_transition(EMPTY, BUSY) {
+            return Err((state, value));
+        }
+        // unique access while busy
+        self.write(|slot| { slot.write(value); });
+        self.log(Pushed);
+        self.store(BUSY, SET);
+        self.wake_in_place();
+        Ok(())
+    }
+
+    /// Like [`take`](Self::take), but failing with `BUSY` rather than waiting it
+    /// out and waking in place, for interrupt handlers.
+    #[cfg(feature = "embassy")]
+    pub(crate) fn take_isr(&self) -> Result<T, u8> {
+        self.try_transition(SET, TAKEN)?;
+        tally(TAKEN);
+        self.log(Pulled);
+        // taken is final, nobody else touches the value
+        let value = unsafe { (*self.value.get()).assume_init_read() };
+        self.wake_in_place();
+        Ok(value)
+    }
+
+    /// Takes a deposited value on behalf of the peer, only counting it as taken
+    /// once the returned claim completes.
+    pub(crate) fn claim(&self) -> Result<(T, Claim<T>), u8> {
+        self.transition(SET, COMBINING)?;
+        self.log(Pulled);
+        // combining is only left by the claim, nobody else touches the value
+        let value = unsafe { (*self.value.get()).assume_init_read() };
+        Ok((value, Claim { common: NonNull::from(self) }))
+    }
+
+    /// Exchanges a deposited value for `value` on behalf of the peer.
+    pub(crate) fn swap(&self, value: T) -> Result<T, (u8, T)> {
+        if let Err(state) = self.transition(SET, BUSY) {
+            return Err((state, value));
+        }
+        // unique access while busy
+        let other = self.write(|slot| std::mem::replace(unsafe { slot.assume_init_mut() }, value));
+        tally(TAKEN);
+        self.log(Pulled);
+        self.log(Pushed);
+        self.settle(SWAPPED);
+        Ok(other)
+    }
+
+    /// Takes the value swapped in by the peer on behalf of the depositor.
+    pub(crate) fn take_swapped(&self) -> Result<T, u8> {
+        self.transition(SWAPPED, TAKEN)?;
+        self.log(Pulled);
+        // taken is final, nobody else touches the value
+        Ok(unsafe { (*self.value.get()).assume_init_read() })
+    }
+
+    /// Swaps a deposited value `pred` accepts for `value` in place, giving back
+    /// the old one, or the state and `value` otherwise.
+    pub(crate) fn replace_if(&self, value: T, pred: impl FnOnce(&T) -> bool) -> Result<T, (u8, T)> {
+        if let Err(state) = self.transition(SET, BUSY) {
+            return Err((state, value));
+        }
+        // unique access while busy
+        if !pred(unsafe { (*self.value.get()).assume_init_ref() }) {
+            // nothing changed, nobody to wake
+            self.store(BUSY, SET);
+            return Err((SET, value));
+        }
+        let old = self.write(|slot| std::mem::replace(unsafe { slot.assume_init_mut() }, value));
+        self.log(Pushed);
+        self.settle(SET);
+        Ok(old)
+    }
+
+    /// Takes a deposited value back on behalf of its depositor.
+    pub(crate) fn retract(&self) -> Result<T, u8> {
+        self.transition(SET, BUSY)?;
+        // unique access while busy
+        let value = unsafe { (*self.value.get()).assume_init_read() };
+        self.log(Retracted);
+        self.settle(EMPTY);
+        Ok(value)
+    }
+
+    /// Takes back a value the peer went away without taking, on behalf of its depositor.
+    pub(crate) fn reclaim(&self) -> Result<T, u8> {
+        self.transition(ORPHANED, BUSY)?;
+        // unique access while busy
+        let value = unsafe { (*self.value.get()).assume_init_read() };
+        self.log(Retracted);
+        self.settle(LOST);
+        Ok(value)
+    }
+
+    /// Takes a deposited value if `pred` accepts it, leaving the slot empty
+    /// (or canceled if the depositor is gone), otherwise leaves it in place.
+    pub(crate) fn take_if(&self, pred: impl FnOnce(&T) -> bool) -> Result<T, u8> {
+        let from = match self.transition(SET, BUSY) {
+            Ok(()) => SET,
+            Err(CLOSED) => { self.transition(CLOSED, BUSY)?; CLOSED }
+            Err(state) => return Err(state),
+        };
+        // unique access while busy
+        if !pred(unsafe { (*self.value.get()).assume_init_ref() }) {
+            // nothing changed, nobody to wake
+            self.store(BUSY, from);
+            return Err(from);
+        }
+        let value = unsafe { (*self.value.get()).assume_init_read() };
+        tally(TAKEN);
+        self.log(Pulled);
+        self.settle(if from == SET { EMPTY } else { CANCELED });
+        Ok(value)
+    }
+
+    /// Leaves a deposited value in place for good on behalf of the peer, for
+    /// everyone to read from then on.
+    pub(crate) fn share(&self) -> Result<&T, u8> {
+        match self.transition(SET, SHARED).or_else(|_| self.transition(CLOSED, SHARED)) {
+            Ok(()) => {
+                tally(TAKEN);
+                self.log(Pulled);
+                self.wake()
+            }
+            Err(SHARED) => {}
+            Err(state) => return Err(state),
+        }
+        // shared is final, nobody moves the value anymore
+        Ok(unsafe { (*self.value.get()).assume_init_ref() })
+    }
+
+    /// The value left in place by [`share`](Self::share), once there.
+    pub(crate) fn shared(&self) -> Option<&T> {
+        // shared is final, nobody moves the value anymore
+        (self.state() == SHARED).then(|| unsafe { (*self.value.get()).assume_init_ref() })
+    }
+
+    // runs `f` on the value, which the caller holds busy
+    fn write<R>(&self, f: impl FnOnce(&mut MaybeUninit<T>) -> R) -> R {
+        f(unsafe { &mut *self.value.get() })
+    }
+
+    /// Copies out a deposited value, holding it busy for just the copy.
+    /// `None` if nothing is deposited.
+    pub(crate) fn read(&self) -> Option<T> where T: Copy {
+        let mut spins = 0;
+        loop {
+            match self.state() {
+                // never written again, no need to hold it
+                SHARED => return self.shared().copied(),
+                state @ (SET | CLOSED) => if self.transition(state, BUSY).is_ok() {
+                    // held busy, nobody else touches the value
+                    let value = unsafe { (*self.value.get()).assume_init() };
+                    // nothing changed, nobody to wake
+                    self.store(BUSY, state);
+                    return Some(value)
+                },
+                BUSY => sync::backoff(&mut spins),
+                _ => return None,
+            }
+        }
+    }
+
+    /// Holds a deposited value in place for reading, the peer waiting out the
+    /// busy period until the view drops.
+    pub(crate) fn peek(&self) -> Option<Peek<'_, T>> {
+        self.transition(SET, BUSY).ok()?;
+        Some(Peek { common: self })
+    }
+
+    /// Records that an end went away, leaving a deposited value behind for the
+    /// peer if `linger` accepts it and dropping it otherwise.
+    pub(crate) fn close(&self, linger: impl FnOnce(&T) -> bool) {
+        let from = match self.transition(EMPTY, CANCELED) {
+            Ok(()) => { self.end(CANCELED); return self.wake() }
+            Err(state @ (SET | CLOSED)) => state,
+            Err(_) => return,
+        };
+        if self.transition(from, BUSY).is_err() {
+            // taken in the meantime
+            return self.close(linger);
+        }
+        // unique access while busy
+        if from == SET && linger(unsafe { (*self.value.get()).assume_init_ref() }) {
+            self.log(Canceled);
+            return self.settle(CLOSED);
+        }
+        unsafe { (*self.value.get()).assume_init_drop() };
+        self.end(LOST);
+        self.settle(LOST)
+    }
+
+    /// Records that an end went away, orphaning any value the peer left for it.
+    pub(crate) fn cancel(&self) {
+        match self.transition(EMPTY, CANCELED) {
+            Ok(()) => { self.end(CANCELED); self.wake() }
+            Err(SET) => if self.transition(SET, ORPHANED).is_ok() {
+                self.end(LOST);
+                // left for the depositor to reclaim, dropped with the slot otherwise
+                self.wake();
+            } else {
+                // retracted in the meantime
+                self.cancel()
+            },
+            Err(_) => {}
+        }
+    }
+
+    /// Cancels an exchange that missed its deadline, dropping any value waiting in it.
+    #[cfg(feature = "timer")]
+    pub(crate) fn time_out(&self) {
+        match self.transition(EMPTY, TIMED_OUT) {
+            Ok(()) => { self.end(CANCELED); self.wake() }
+            Err(state @ (SET | CLOSED)) => if self.transition(state, BUSY).is_ok() {
+                // unique access while busy
+                unsafe { (*self.value.get()).assume_init_drop() };
+                self.end(LOST);
+                self.settle(TIMED_OUT)
+            } else {
+                // taken or retracted in the meantime
+                self.time_out()
+            },
+            Err(_) => {}
+        }
+    }
+
+    // counts the exchange dying as `state`, recording it with the `leak-check` feature
+    fn end(&self, state: u8) {
+        tally(state);
+        self.log(Canceled);
+        #[cfg(feature = "leak-check")]
+        crate::leak_check::record(
+            if state == LOST { crate::leak_check::LeakKind::Lost } else { crate::leak_check::LeakKind::Canceled },
+            id(NonNull::from(self)),
+            self.origin.get(),
+        )
+    }
+
+    // records a step towards the timeline of the `history` feature, and pushes for `timestamps`
+    fn log(&self, _kind: EventKind) {
+        #[cfg(feature = "timestamps")]
+        match _kind {
+            Created => self.created_at.with(|at| *at = Some(Instant::now())),
+            Pushed => self.pushed_at.with(|at| *at = Some(Instant::now())),
+            Retracted => self.pushed_at.with(|at| *at = None),
+            _ => {}
+        }
+        #[cfg(feature = "history")]
+        self.history.with(|history| history.push(crate::history::Event::now(_kind)))
+    }
+
+    #[cfg(feature = "timestamps")]
+    pub(crate) fn pushed_at(&self) -> Option<Instant> {
+        self.pushed_at.with(|at| *at)
+    }
+
+    #[cfg(feature = "timestamps")]
+    pub(crate) fn created_at(&self) -> Option<Instant> {
+        self.created_at.with(|at| *at)
+    }
+
+    /// Records the thread both ends stay on, `None` once one of them may move.
+    #[cfg(feature = "deadlock-detection")]
+    pub(crate) fn set_owner(&self, owner: Option<std::thread::ThreadId>) {
+        self.owner.with(|at| *at = owner)
+    }
+
+    #[cfg(feature = "history")]
+    pub(crate) fn history(&self) -> Vec<crate::history::Event> {
+        self.history.with(|history| history.clone())
+    }
+
+    /// Registers the waker of `cx` to be woken on the next state change.
+    pub(crate) fn register(&self, cx: &Context<'_>) {
+        self.wakers.with(|wakers| if !wakers.iter().any(|w| w.will_wake(cx.waker())) {
+            wakers.push(cx.waker().clone());
+        })
+    }
+
+    /// Forgets a waker registered earlier, if it is still around.
+    pub(crate) fn unregister(&self, waker: &Waker) {
+        self.wakers.with(|wakers| wakers.retain(|w| !w.will_wake(waker)));
+    }
+
+    /// Wakes everything registered and every blocked thread without changing state.
+    pub(crate) fn wake(&self) {
+        self.wake_blocked();
+        // woken outside the lock, wakers may well come back to register
+        self.wakers.with(std::mem::take).into_iter().for_each(Waker::wake);
+    }
+
+    /// Wakes everything registered without freeing the list, so nothing is deallocated.
+    ///
+    /// Woken inside the lock, fine for embassy's wakers, which only schedule their task.
+    #[cfg(feature = "embassy")]
+    fn wake_in_place(&self) {
+        self.wake_blocked();
+        self.wakers.with(|wakers| wakers.drain(..).for_each(Waker::wake));
+    }
+
+    // wakes the threads blocked on the state word, if any
+    fn wake_blocked(&self) {
+        // a thread flagging itself blocked after this sees the new word, and won't sleep on it
+        if self.state.fetch_add(WOKEN, Ordering::AcqRel) & WAITING != 0 {
+            self.state.fetch_and(!WAITING, Ordering::Relaxed);
+            sync::wake_all(&self.state)
+        }
+    }
+
+    /// Blocks the current thread for as long as `pending` holds.
+    pub(crate) fn block_while(&self, pending: impl Fn(u8) -> bool) -> u8 {
+        self.block_until(|| Some(self.state()).filter(|&state| !pending(state)))
+    }
+
+    /// Blocks the current thread, retrying `attempt` on every state change until it succeeds.
+    pub(crate) fn block_until<R>(&self, mut attempt: impl FnMut() -> Option<R>) -> R {
+        #[cfg(feature = "deadlock-detection")]
+        let mut since = Instant::now();
+        let (mut spins, mut busy) = (0, 0);
+        loop {
+            if let Some(res) = attempt() { return res; }
+            if crate::wait::spin(&mut spins) { continue; }
+            let word = self.flag_blocked();
+            // re-check after flagging so a wake-up can't slip through
+            if let Some(res) = attempt() { return res; }
+            // leaving `BUSY` unchanged wakes nobody, waited out instead
+            if word & STATE == BUSY as u32 { sync::backoff(&mut busy); continue; }
+            #[cfg(feature = "deadlock-detection")]
+            {
+                let (state, id) = (self.state(), id(NonNull::from(self)));
+                // anyone but the two ends may act on the exchange unless they hold the only references
+                let shared = self.refs.load(Ordering::Relaxed) != 2;
+                let _blocked = crate::deadlock::block(id, &self.state, word, self.owner.with(|owner| *owner), shared);
+                sync::wait(&self.state, word, crate::deadlock::remaining(since, id));
+                // any change counts as progress
+                if self.state() != state { since = Instant::now() }
+            }
+            #[cfg(not(feature = "deadlock-detection"))]
+            sync::wait(&self.state, word, None)
+        }
+    }
+
+    /// Like [`block_until`](Self::block_until), but gives up with `None` once `deadline` passed.
+    pub(crate) fn block_until_deadline<R>(&self, deadline: Instant, mut attempt: impl FnMut() -> Option<R>) -> Option<R> {
+        let (mut spins, mut busy) = (0, 0);
+        loop {
+            if let Some(res) = attempt() { return Some(res); }
+            if crate::wait::spin(&mut spins) && Instant::now() < deadline { continue; }
+            let word = self.flag_blocked();
+            // re-check after flagging so a wake-up can't slip through
+            if let Some(res) = attempt() { return Some(res); }
+            let now = Instant::now();
+            if now >= deadline { return None; }
+            // leaving `BUSY` unchanged wakes nobody, waited out instead
+            if word & STATE == BUSY as u32 { sync::backoff(&mut busy); continue; }
+            sync::wait(&self.state, word, Some(deadline - now))
+        }
+    }
+
+    // marks a thread about to wait on the state word, giving the word to wait on
+    fn flag_blocked(&self) -> u32 {
+        self.state.fetch_or(WAITING, Ordering::Acquire) | WAITING
+    }
+}
+
+impl<T> Drop for Common<T> {
+    fn drop(&mut self) {
+        if matches!(self.state(), SET | CLOSED | SWAPPED | ORPHANED | SHARED) {
+            // value never collected
+            unsafe { self.value.get_mut().assume_init_drop() };
+        }
+    }
+}
+
+unsafe fn free_boxed<T>(ptr: NonNull<Common<T>>) {
+    drop(Box::from_raw(ptr.as_ptr()))
+}
+
+// a slot along with the allocator it lives in
+#[cfg(feature = "allocator")]
+#[repr(C)]
+struct InAlloc<T, A> {
+    common: Common<T>,
+    alloc: A,
+}
+
+/// The layout of a slot allocated from an `A`.
+#[cfg(feature = "allocator")]
+pub(crate) fn layout_in<T, A>() -> Layout {
+    Layout::new::<InAlloc<T, A>>()
+}
+
+#[cfg(feature = "allocator")]
+unsafe fn free_in<T, A: Allocator>(ptr: NonNull<Common<T>>) {
+    let ptr = ptr.cast::<InAlloc<T, A>>().as_ptr();
+    // moved out before the memory holding it goes away
+    let alloc = std::ptr::read(&(*ptr).alloc);
+    std::ptr::drop_in_place(&mut (*ptr).common);
+    alloc.deallocate(NonNull::new_unchecked(ptr).cast(), Layout::new::<InAlloc<T, A>>());
+}
+
+/// Marks a claimed value as lost unless completed, e.g. when unwinding.
+///
+/// The claimer keeps its reference until the claim is gone.
+pub(crate) struct Claim<T> {
+    common: NonNull<Common<T>>,
+}
+
+impl<T> Claim<T> {
+    pub(crate) fn complete(self) {
+        tally(TAKEN);
+        let common = unsafe { ManuallyDrop::new(self).common.as_ref() };
+        common.store(COMBINING, TAKEN);
+        common.wake()
+    }
+}
+
+impl<T> Drop for Claim<T> {
+    fn drop(&mut self) {
+        let common = unsafe { self.common.as_ref() };
+        common.end(LOST);
+        common.store(COMBINING, LOST);
+        common.wake()
+    }
+}
+
+/// The id of the exchange using the slot behind `common`.
+pub(crate) fn id<T>(common: NonNull<Common<T>>) -> u64 {
+    // the address, stable for as long as the slot lives, above bits no address
+    // uses the generation, so a recycled slot gets a new id
+    let generation = unsafe { common.as_ref() }.generation.load(Ordering::Relaxed);
+    (generation as u64) << 56 | common.as_ptr() as usize as u64
+}
+
+// counts a slot made (`EMPTY`) or reaching a final state towards the totals of the `metrics` feature
+pub(crate) fn tally(_state: u8) {
+    #[cfg(feature = "metrics")]
+    crate::metrics::count(match _state {
+        EMPTY => &crate::metrics::CREATED,
+        TAKEN => &crate::metrics::COMPLETED,
+        CANCELED => &crate::metrics::CANCELED,
+        _ => &crate::metrics::LOST,
+    })
+}
+
+/// A view of a value waiting for the peer, see [`Handshake::peek`](crate::Handshake::peek).
+///
+/// The value stays put while the view is around, so anyone collecting or
+/// retracting it waits until the view drops.
+pub struct Peek<'a, T> {
+    common: &'a Common<T>,
+}
+
+impl<T> Deref for Peek<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        // held busy, nobody else touches the value
+        unsafe { (*self.common.value.get()).assume_init_ref() }
+    }
+}
+
+impl<T> Drop for Peek<'_, T> {
+    fn drop(&mut self) {
+        // nothing changed, nobody to wake
+        self.common.store(BUSY, SET)
+    }
+}
+
+// exclusive while around, like `&mut T` only sharing it needs `T: Sync`
+unsafe impl<T: Send> Send for Peek<'_, T> {}
+
+unsafe impl<T: Sync> Sync for Peek<'_, T> {}
+
+impl<T: Debug> Debug for Peek<'_, T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        Debug::fmt(&**self, f)
+    }
+}
+
+/// A waker unparking the current thread, for waits on several handshakes at once.
+///
+/// Made once per thread, so those allocate nothing either.
+pub(crate) fn thread_waker() -> Waker {
+    fn fresh() -> Waker {
+        Waker::from(Arc::new(ThreadWaker(sync::current())))
+    }
+    #[cfg(not(loom))]
+    thread_local! { static WAKER: Waker = fresh() }
+    // the thread's own is gone while it exits
+    #[cfg(not(loom))]
+    return WAKER.try_with(Waker::clone).unwrap_or_else(|_| fresh());
+    #[cfg(loom)]
+    fresh()
+}
+
+struct ThreadWaker(Thread);
+
+impl Wake for ThreadWaker {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark()
+    }
+}
@@ -0,0 +1,15 @@
+use std::sync::mpsc::{self, Receiver, Sender};
+
+use crate::{Canceled, Handshake};
+
+impl<T> Handshake<Sender<T>> {
+    /// Sets up a full-duplex link with the peer doing the same: each side makes a
+    /// channel and hands over its sender, keeping the receiver, blocking like
+    /// [`meet`](Self::meet).
+    ///
+    /// The sender goes to the peer's receiver and the receiver gets what the peer sends.
+    pub fn connect(self) -> Result<(Sender<T>, Receiver<T>), Canceled> {
+        let (tx, rx) = mpsc::channel();
+        Ok((self.meet(tx)?, rx))
+    }
+}
@@ -0,0 +1,23 @@
+use std::task::Poll;
+
+use crate::{combinators::Pulling, relay, Handshake};
+
+impl<T: Send + 'static> Handshake<T> {
+    /// Turns this end into a crossbeam receiver of the value the peer pushes, e.g.
+    /// to wait on it in a `crossbeam_channel::Select` alongside other channels.
+    ///
+    /// The receiver disconnects without a value if the peer goes away. Like the
+    /// combinators, forwarding happens on the thread completing the peer's push.
+    pub fn into_crossbeam_receiver(self) -> crossbeam_channel::Receiver<T> {
+        // room for the single value, sending never blocks
+        let (tx, rx) = crossbeam_channel::bounded(1);
+        let mut input = Pulling::Waiting(self);
+        relay::spawn(move |cx| {
+            if !input.poll(cx) { return Poll::Ready(()) }
+            let Some(value) = input.take() else { return Poll::Pending };
+            let _ = tx.try_send(value);
+            Poll::Ready(())
+        });
+        rx
+    }
+}
@@ -0,0 +1,112 @@
+//! Turns blocking waits that can never end into panics with a diagnostic, for debugging.
+//!
+//! A wait panics once only blocked threads could end it: the other end stays on
+//! the blocked thread itself, or its holder is blocked waiting on the first thread,
+//! directly or round a longer cycle. Who holds an end is known for ends that can't
+//! leave the thread that made them ([`Local`](crate::strategy::Local)) and for ends
+//! blocked in a call, never guessed for the rest. So an unused end left on the
+//! blocked thread goes unnoticed, as it can't be told from one handed to a thread
+//! that hasn't run yet, unless [`set_timeout`] opts into a timeout. Exchanges
+//! something else can act on, e.g. a watcher, deadline or cancel scope, are left alone.
+
+use std::{
+    cell::Cell,
+    sync::{Mutex, PoisonError},
+    thread::{self, ThreadId},
+    time::{Duration, Instant},
+};
+
+use crate::sync::{AtomicU32, Ordering};
+
+thread_local! {
+    static TIMEOUT: Cell<Option<Duration>> = const { Cell::new(None) };
+}
+
+/// Opts the calling thread into taking waits that go without progress for longer
+/// than `timeout` for deadlocks as well, off by default.
+pub fn set_timeout(timeout: Duration) {
+    TIMEOUT.with(|cell| cell.set(Some(timeout)))
+}
+
+// how much longer the wait that began at `since` may go on, panicking once overdue
+pub(crate) fn remaining(since: Instant, id: u64) -> Option<Duration> {
+    let timeout = TIMEOUT.with(Cell::get)?;
+    let waited = since.elapsed();
+    if waited >= timeout {
+        panic!("blocked on handshake {id:#x} for {waited:?} without the peer acting, \
+            most likely a deadlock: is the other end held by this same thread?");
+    }
+    Some(timeout - waited)
+}
+
+// a thread about to wait on an exchange only its two ends can act on
+struct Waiter {
+    thread: ThreadId,
+    exchange: u64,
+    // the state word, by address as it is only read while the waiter is there,
+    // and the value slept on: the thread is awake once the word moved on
+    word: usize,
+    expected: u32,
+    // the thread the other end stays on, for ends that can't move
+    peer: Option<ThreadId>,
+}
+
+impl Waiter {
+    fn asleep(&self) -> bool {
+        // the waiter holds the exchange alive until it's gone from the table
+        unsafe { &*(self.word as *const AtomicU32) }.load(Ordering::Acquire) == self.expected
+    }
+}
+
+static WAITERS: Mutex<Vec<Waiter>> = Mutex::new(Vec::new());
+
+/// The current thread registered as waiting, until dropped.
+pub(crate) struct Blocked(Option<ThreadId>);
+
+/// Registers the current thread as about to wait for `word` to move on from
+/// `expected`, panicking if only blocked threads could see to that.
+///
+/// `peer` is the thread the other end stays on, if it can't move, and `shared`
+/// whether anything but the two ends can act on the exchange.
+pub(crate) fn block(exchange: u64, word: &AtomicU32, expected: u32, peer: Option<ThreadId>, shared: bool) -> Blocked {
+    if shared { return Blocked(None) }
+    let thread = thread::current().id();
+    let waiter = Waiter { thread, exchange, word: word as *const AtomicU32 as usize, expected, peer };
+    let mut waiters = WAITERS.lock().unwrap_or_else(PoisonError::into_inner);
+    let cycle = waiter.asleep().then(|| cycle(&waiters, &waiter)).flatten();
+    if let Some(cycle) = cycle {
+        drop(waiters);
+        if cycle.len() == 1 {
+            panic!("deadlock: blocked on handshake {exchange:#x} whose other end is held by this same thread");
+        }
+        panic!("deadlock: blocked on handshake {exchange:#x}, threads {cycle:?} each waiting on the next");
+    }
+    waiters.push(waiter);
+    Blocked(Some(thread))
+}
+
+// the threads along a cycle of waits back to `from`, if there is one
+fn cycle(waiters: &[Waiter], from: &Waiter) -> Option<Vec<ThreadId>> {
+    let mut path = vec![from.thread];
+    let mut at = from;
+    loop {
+        // whoever holds the other end: the other thread blocked on the exchange, or the one it stays on
+        let holder = waiters.iter().chain(std::iter::once(from))
+            .find(|waiter| waiter.exchange == at.exchange && waiter.thread != at.thread && waiter.asleep())
+            .map(|waiter| waiter.thread)
+            .or(at.peer)?;
+        if holder == from.thread { return Some(path) }
+        // a cycle not through this thread, reported by whoever closed it
+        if path.contains(&holder) { return None }
+        path.push(holder);
+        // a holder that isn't blocked can still act
+        at = waiters.iter().find(|waiter| waiter.thread == holder && waiter.asleep())?;
+    }
+}
+
+impl Drop for Blocked {
+    fn drop(&mut self) {
+        let Some(thread) = self.0 else { return };
+        WAITERS.lock().unwrap_or_else(PoisonError::into_inner).retain(|waiter| waiter.thread != thread)
+    }
+}
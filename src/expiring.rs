@@ -0,0 +1,99 @@
+//! Handshakes whose pushed value goes stale after a time-to-live set when the pair is made.
+
+use std::{fmt::Debug, time::{Duration, Instant}};
+
+use crate::{common::{self, Common}, Canceled, Handshake, PushOutcome, State};
+
+/// Why [`ExpiringHandshake::try_pull`] came back empty handed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum PullError {
+    /// The peer went away.
+    Canceled,
+    /// The peer pushed, but the value outlived its time-to-live and was dropped.
+    Expired,
+}
+
+impl From<Canceled> for PullError {
+    fn from(_: Canceled) -> PullError {
+        PullError::Canceled
+    }
+}
+
+impl std::fmt::Display for PullError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PullError::Canceled => std::fmt::Display::fmt(&Canceled, f),
+            PullError::Expired => f.write_str("handshake value expired before it was pulled"),
+        }
+    }
+}
+
+impl std::error::Error for PullError {}
+
+/// One end of a handshake dropping pushed values not pulled within a time-to-live.
+///
+/// Expiry is only checked by a pull: a stale value stays in the slot until the
+/// peer next tries to pull and is dropped then, not when its time-to-live runs out.
+pub struct ExpiringHandshake<T> {
+    // values carry the instant they go stale
+    handshake: Handshake<(Instant, T)>,
+    ttl: Duration,
+}
+
+impl<T> ExpiringHandshake<T> {
+    pub fn new(ttl: Duration) -> (ExpiringHandshake<T>, ExpiringHandshake<T>) {
+        let (u, v) = Handshake::new();
+        (ExpiringHandshake { handshake: u, ttl }, ExpiringHandshake { handshake: v, ttl })
+    }
+
+    /// Deposits `value` for the peer, to be pulled within the time-to-live.
+    ///
+    /// `Ok(Ok(()))` once deposited, no receipt is handed out: whether the value
+    /// was pulled in time is only known to the puller. Gives back the end and
+    /// value when the peer already pushed (pull instead), or just the value when
+    /// the peer is gone.
+    pub fn try_push(self, value: T) -> Result<Result<(), (Self, T)>, T> {
+        let ttl = self.ttl;
+        match self.handshake.push((Instant::now() + ttl, value)) {
+            PushOutcome::Delivered(_) => Ok(Ok(())),
+            PushOutcome::Occupied(handshake, (_, value)) => Ok(Err((ExpiringHandshake { handshake, ttl }, value))),
+            PushOutcome::PeerGone((_, value)) => Err(value),
+        }
+    }
+
+    /// Takes the value deposited by the peer unless it expired.
+    ///
+    /// Gives back the end when nothing was pushed yet.
+    pub fn try_pull(self) -> Result<Result<T, Self>, PullError> {
+        let common = self.handshake.common();
+        match common.claim() {
+            Ok(((deadline, value), claim)) => {
+                let res = if Instant::now() < deadline {
+                    claim.complete();
+                    Ok(Ok(value))
+                } else {
+                    // an unfinished claim leaves the value lost
+                    drop((value, claim));
+                    Err(PullError::Expired)
+                };
+                unsafe { Common::release(self.handshake.into_common()) };
+                res
+            }
+            Err(common::EMPTY) => Ok(Err(self)),
+            Err(_) => {
+                drop(self);
+                Err(PullError::Canceled)
+            }
+        }
+    }
+
+    pub fn state(&self) -> State {
+        self.handshake.state()
+    }
+}
+
+impl<T> Debug for ExpiringHandshake<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ExpiringHandshake").field("state", &self.state()).field("ttl", &self.ttl).finish()
+    }
+}
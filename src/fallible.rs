@@ -0,0 +1,63 @@
+use std::fmt::{Debug, Display};
+
+use crate::{Canceled, Handshake, PullOutcome, PushOutcome, Receipt};
+
+/// Why pulling from a `Handshake<Result<T, E>>` produced no value, see [`Handshake::push_err`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Failure<E> {
+    /// The peer pushed an error instead of a value.
+    PeerError(E),
+    /// The peer went away.
+    Canceled,
+}
+
+impl<E> From<Canceled> for Failure<E> {
+    fn from(_: Canceled) -> Failure<E> {
+        Failure::Canceled
+    }
+}
+
+impl<E: Display> Display for Failure<E> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Failure::PeerError(error) => write!(f, "handshake peer failed: {error}"),
+            Failure::Canceled => Display::fmt(&Canceled, f),
+        }
+    }
+}
+
+impl<E: Debug + Display> std::error::Error for Failure<E> {}
+
+// what a handshake of results pushes
+type Push<T, E> = Result<Result<Receipt<Result<T, E>>, (Handshake<Result<T, E>>, E)>, E>;
+
+impl<T, E> Handshake<Result<T, E>> {
+    /// Deposits `error` for the peer in place of a value, reaching it as
+    /// [`Failure::PeerError`] rather than as a cancellation.
+    ///
+    /// Gives back the end and error when the peer already pushed, or just the
+    /// error when the peer is gone.
+    pub fn push_err(self, error: E) -> Push<T, E> {
+        let unwrap = |res: Result<T, E>| res.err().expect("pushed an error");
+        match self.push(Err(error)) {
+            PushOutcome::Delivered(receipt) => Ok(Ok(receipt)),
+            PushOutcome::Occupied(end, rejected) => Ok(Err((end, (unwrap)(rejected)))),
+            PushOutcome::PeerGone(rejected) => Err((unwrap)(rejected)),
+        }
+    }
+
+    /// Takes the value or error deposited by the peer, see [`pull_now`](Self::pull_now).
+    pub fn try_pull_result(self) -> Result<Result<T, Self>, Failure<E>> {
+        match self.pull_now() {
+            PullOutcome::Delivered(Ok(value)) => Ok(Ok(value)),
+            PullOutcome::Delivered(Err(error)) => Err(Failure::PeerError(error)),
+            PullOutcome::Pending(end) => Ok(Err(end)),
+            PullOutcome::PeerGone => Err(Failure::Canceled),
+        }
+    }
+
+    /// Blocks until the peer deposited a value or error, or went away.
+    pub fn pull_result(self) -> Result<T, Failure<E>> {
+        self.pull_blocking()?.map_err(Failure::PeerError)
+    }
+}
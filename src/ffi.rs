@@ -0,0 +1,63 @@
+//! C interface for completing handshakes owned by Rust code.
+//!
+//! Rust hands one end of a `Handshake<FfiValue>` to C via [`Handshake::into_raw`];
+//! C then completes it with `handshake_push`, `handshake_pull` or `handshake_cancel`.
+//! Calls that consume the end invalidate the pointer, calls that hand it back
+//! (`HANDSHAKE_OCCUPIED`, `HANDSHAKE_PENDING`) leave it valid.
+
+use std::ffi::{c_int, c_void};
+
+use crate::{Handshake, PullOutcome, PushOutcome};
+
+/// Opaque payload exchanged with C.
+#[repr(transparent)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FfiValue(pub *mut c_void);
+
+// pointers are only carried across, whoever dereferences them answers for it
+unsafe impl Send for FfiValue {}
+
+/// The operation completed and the end was consumed.
+pub const HANDSHAKE_OK: c_int = 0;
+/// The peer already pushed, pull instead; the end is still valid.
+pub const HANDSHAKE_OCCUPIED: c_int = 1;
+/// Nothing was pushed yet; the end is still valid.
+pub const HANDSHAKE_PENDING: c_int = 2;
+/// The peer went away and the end was consumed.
+pub const HANDSHAKE_CANCELED: c_int = -1;
+
+/// Deposits `value` for the peer.
+///
+/// # Safety
+/// `handshake` must come from [`Handshake::into_raw`] on a `Handshake<FfiValue>` and still be valid.
+#[no_mangle]
+pub unsafe extern "C" fn handshake_push(handshake: *const (), value: *mut c_void) -> c_int {
+    match Handshake::<FfiValue>::from_raw(handshake).push(FfiValue(value)) {
+        PushOutcome::Delivered(_) => HANDSHAKE_OK,
+        PushOutcome::Occupied(handshake, _) => { handshake.into_raw(); HANDSHAKE_OCCUPIED }
+        PushOutcome::PeerGone(_) => HANDSHAKE_CANCELED,
+    }
+}
+
+/// Takes the value deposited by the peer, writing it to `value` on success.
+///
+/// # Safety
+/// `handshake` must come from [`Handshake::into_raw`] on a `Handshake<FfiValue>` and still be valid,
+/// `value` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn handshake_pull(handshake: *const (), value: *mut *mut c_void) -> c_int {
+    match Handshake::<FfiValue>::from_raw(handshake).pull_now() {
+        PullOutcome::Delivered(FfiValue(pulled)) => { value.write(pulled); HANDSHAKE_OK }
+        PullOutcome::Pending(handshake) => { handshake.into_raw(); HANDSHAKE_PENDING }
+        PullOutcome::PeerGone => HANDSHAKE_CANCELED,
+    }
+}
+
+/// Drops the end, canceling the handshake if it wasn't completed.
+///
+/// # Safety
+/// `handshake` must come from [`Handshake::into_raw`] on a `Handshake<FfiValue>` and still be valid.
+#[no_mangle]
+pub unsafe extern "C" fn handshake_cancel(handshake: *const ()) {
+    drop(Handshake::<FfiValue>::from_raw(handshake))
+}
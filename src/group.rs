@@ -0,0 +1,81 @@
+use std::{fmt::Debug, task::Context, time::{Duration, Instant}};
+
+use crate::{common, sync, Handshake, State, Watcher};
+
+/// Keeps track of many outstanding exchanges under keys of the caller's
+/// choosing, to await them in one place and report on each.
+///
+/// An entry is done once its exchange completed or was canceled, see [`Watcher::is_finished`].
+pub struct HandshakeGroup<K, T> {
+    entries: Vec<(K, Watcher<T>)>,
+}
+
+impl<K, T> HandshakeGroup<K, T> {
+    pub fn new() -> HandshakeGroup<K, T> {
+        HandshakeGroup { entries: Vec::new() }
+    }
+
+    /// Starts tracking the exchange of `end` under `key`.
+    pub fn insert(&mut self, key: K, end: &Handshake<T>) {
+        self.entries.push((key, end.watcher()))
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Number of entries not done yet.
+    pub fn pending(&self) -> usize {
+        self.entries.iter().filter(|(_, watcher)| !watcher.is_finished()).count()
+    }
+
+    /// Where each entry stands, in insertion order.
+    pub fn status(&self) -> impl Iterator<Item = (&K, State)> + '_ {
+        self.entries.iter().map(|(key, watcher)| (key, watcher.state()))
+    }
+
+    /// Blocks until every entry is done or `timeout` elapsed, giving the keys
+    /// still pending in the latter case.
+    pub fn wait_all(&self, timeout: Duration) -> Result<(), Vec<&K>> {
+        let deadline = Instant::now() + timeout;
+        for (_, watcher) in &self.entries {
+            // later entries get checked even once the deadline passed
+            let _ = watcher.common().block_until_deadline(deadline, || watcher.is_finished().then_some(()));
+        }
+        let pending: Vec<_> = self.entries.iter().filter(|(_, w)| !w.is_finished()).map(|(key, _)| key).collect();
+        if pending.is_empty() { Ok(()) } else { Err(pending) }
+    }
+
+    /// Blocks until any entry is done, no longer tracking it and returning its
+    /// key along with its final state. `None` once the group is empty.
+    pub fn wait_any(&mut self) -> Option<(K, State)> {
+        if self.entries.is_empty() { return None }
+        let waker = common::thread_waker();
+        let cx = Context::from_waker(&waker);
+        let i = loop {
+            if let Some(i) = self.entries.iter().position(|(_, w)| w.is_finished()) { break i }
+            self.entries.iter().for_each(|(_, w)| w.common().register(&cx));
+            // re-check after registering so a wake-up can't slip through
+            if !self.entries.iter().any(|(_, w)| w.is_finished()) { sync::park() }
+        };
+        self.entries.iter().for_each(|(_, w)| w.common().unregister(&waker));
+        let (key, watcher) = self.entries.remove(i);
+        Some((key, watcher.state()))
+    }
+}
+
+impl<K, T> Default for HandshakeGroup<K, T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Debug, T> Debug for HandshakeGroup<K, T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_map().entries(self.status()).finish()
+    }
+}
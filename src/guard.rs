@@ -0,0 +1,63 @@
+use std::fmt::{Debug, Display};
+
+use crate::{Handshake, PushOutcome};
+
+/// What an [`abort_guard`](Handshake::abort_guard) pushes when dropped unfulfilled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Aborted;
+
+impl Display for Aborted {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("handshake aborted, the pusher gave up before answering")
+    }
+}
+
+impl std::error::Error for Aborted {}
+
+/// An end that pushes a fallback value if dropped without pushing, e.g. on an
+/// early return or a panic, so the peer always gets an answer.
+pub struct FulfillGuard<T> {
+    end: Option<Handshake<T>>,
+    fallback: Option<T>,
+}
+
+impl<T> Handshake<T> {
+    /// Wraps this end so that dropping it unfulfilled pushes `fallback`.
+    pub fn fulfill_guard(self, fallback: T) -> FulfillGuard<T> {
+        FulfillGuard { end: Some(self), fallback: Some(fallback) }
+    }
+}
+
+impl<T> Handshake<Result<T, Aborted>> {
+    /// Wraps this end so that dropping it unfulfilled pushes `Err(Aborted)`.
+    pub fn abort_guard(self) -> FulfillGuard<Result<T, Aborted>> {
+        self.fulfill_guard(Err(Aborted))
+    }
+}
+
+impl<T> FulfillGuard<T> {
+    /// Pushes `value` in place of the fallback.
+    pub fn push(mut self, value: T) -> PushOutcome<T> {
+        self.end.take().expect("end taken before drop").push(value)
+    }
+
+    /// Gives back the end without pushing anything, dropping the fallback.
+    pub fn disarm(mut self) -> Handshake<T> {
+        self.end.take().expect("end taken before drop")
+    }
+}
+
+impl<T> Drop for FulfillGuard<T> {
+    fn drop(&mut self) {
+        // dropped along with the end if the peer pushed or went away instead
+        if let (Some(end), Some(fallback)) = (self.end.take(), self.fallback.take()) {
+            let _ = end.push(fallback);
+        }
+    }
+}
+
+impl<T> Debug for FulfillGuard<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FulfillGuard").field("end", &self.end).finish_non_exhaustive()
+    }
+}
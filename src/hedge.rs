@@ -0,0 +1,66 @@
+use std::{fmt::Debug, sync::{Arc, Mutex, PoisonError}};
+
+use crate::{Handshake, PushOutcome, Receipt};
+
+/// A pushing end shared by producers racing to deliver the value, see [`Handshake::into_shared_pusher`].
+pub struct SharedPusher<T> {
+    // the end, until a producer pushed through it
+    end: Arc<Mutex<Option<Handshake<T>>>>,
+}
+
+/// Another producer already pushed, the value is handed back.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Superseded<T>(pub T);
+
+impl<T> Handshake<T> {
+    /// Turns this end into one cloned across producers, the first push winning.
+    pub fn into_shared_pusher(self) -> SharedPusher<T> {
+        SharedPusher { end: Arc::new(Mutex::new(Some(self))) }
+    }
+}
+
+impl<T> SharedPusher<T> {
+    /// Deposits `value` for the peer unless another producer got there first.
+    ///
+    /// Gives back just the value when the peer is gone or pushed itself.
+    pub fn try_push(&self, value: T) -> Result<Result<Receipt<T>, Superseded<T>>, T> {
+        let mut end = self.end.lock().unwrap_or_else(PoisonError::into_inner);
+        let Some(handshake) = end.take() else { return Ok(Err(Superseded(value))) };
+        match handshake.push(value) {
+            PushOutcome::Delivered(receipt) => Ok(Ok(receipt)),
+            PushOutcome::Occupied(handshake, value) => { *end = Some(handshake); Err(value) }
+            PushOutcome::PeerGone(value) => Err(value),
+        }
+    }
+
+    /// Whether a producer already pushed, or the peer went away.
+    pub fn is_done(&self) -> bool {
+        self.end.lock().unwrap_or_else(PoisonError::into_inner).as_ref().is_none_or(Handshake::is_canceled)
+    }
+}
+
+impl<T> Clone for SharedPusher<T> {
+    fn clone(&self) -> Self {
+        SharedPusher { end: self.end.clone() }
+    }
+}
+
+impl<T> Debug for SharedPusher<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SharedPusher").field("done", &self.is_done()).finish()
+    }
+}
+
+impl<T> Debug for Superseded<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Superseded(..)")
+    }
+}
+
+impl<T> std::fmt::Display for Superseded<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("another producer pushed first")
+    }
+}
+
+impl<T> std::error::Error for Superseded<T> {}
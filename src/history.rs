@@ -0,0 +1,58 @@
+//! Records what happened to each exchange and when, for reconstructing the
+//! interleaving behind a misbehaving one.
+//!
+//! Every handshake keeps its own timeline, read with [`Handshake::history`]
+//! or [`Watcher::history`]. Recording takes a lock and may allocate on every
+//! step, keep the feature to debugging.
+
+use std::{thread::ThreadId, time::Instant};
+
+use crate::{Handshake, Watcher};
+
+/// What happened to an exchange, see [`Event`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum EventKind {
+    /// The pair was made, or its slot recycled for a new exchange.
+    Created,
+    /// A value was deposited.
+    Pushed,
+    /// The deposited value was collected.
+    Pulled,
+    /// The depositor took its value back.
+    Retracted,
+    /// An end went away, or the deadline passed, before the exchange completed.
+    Canceled,
+}
+
+/// One step in the timeline of an exchange.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Event {
+    pub kind: EventKind,
+    pub at: Instant,
+    /// The thread taking the step.
+    pub thread: ThreadId,
+}
+
+impl Event {
+    pub(crate) fn now(kind: EventKind) -> Event {
+        Event { kind, at: Instant::now(), thread: std::thread::current().id() }
+    }
+}
+
+impl<T> Handshake<T> {
+    /// Everything that happened to the exchange so far, oldest first.
+    ///
+    /// Lacks the creation for a handshake in a `static`, which can't be recorded.
+    pub fn history(&self) -> Vec<Event> {
+        self.common().history()
+    }
+}
+
+impl<T> Watcher<T> {
+    /// Everything that happened to the exchange so far, see [`Handshake::history`].
+    pub fn history(&self) -> Vec<Event> {
+        self.common().history()
+    }
+}
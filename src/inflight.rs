@@ -0,0 +1,104 @@
+use std::{
+    collections::HashMap,
+    fmt::Debug,
+    hash::Hash,
+    sync::{Arc, Mutex, PoisonError},
+    thread::JoinHandle,
+    time::{Duration, Instant},
+};
+
+use crate::{Handshake, PushOutcome};
+
+/// Pending requests keyed by correlation id, each with a deadline, for matching
+/// responses to the callers waiting on them.
+///
+/// Expired entries are canceled in bulk by [`sweep`](Self::sweep), so their callers
+/// see the peer gone, and responses arriving after their deadline are turned away.
+pub struct InflightTable<K, T> {
+    // ends pushing the response to the caller
+    entries: Mutex<HashMap<K, (Handshake<T>, Instant)>>,
+}
+
+impl<K: Eq + Hash, T> InflightTable<K, T> {
+    pub fn new() -> InflightTable<K, T> {
+        InflightTable { entries: Mutex::new(HashMap::new()) }
+    }
+
+    /// Registers a request under `key` until `deadline`, returning the end to pull
+    /// its response from. Replacing a request still in flight cancels it.
+    pub fn insert(&self, key: K, deadline: Instant) -> Handshake<T> {
+        let (ours, theirs) = Handshake::new();
+        let mut entries = self.entries.lock().unwrap_or_else(PoisonError::into_inner);
+        let replaced = entries.insert(key, (ours, deadline));
+        // canceled outside the lock
+        drop(entries);
+        drop(replaced);
+        theirs
+    }
+
+    /// Hands `value` to the caller waiting under `key`, giving it back if there is
+    /// none, its deadline passed or it went away.
+    pub fn resolve(&self, key: &K, value: T) -> Result<(), T> {
+        let entry = self.entries.lock().unwrap_or_else(PoisonError::into_inner).remove(key);
+        match entry {
+            Some((end, deadline)) if Instant::now() < deadline => match end.push(value) {
+                PushOutcome::Delivered(_) => Ok(()),
+                PushOutcome::Occupied(_, value) | PushOutcome::PeerGone(value) => Err(value),
+            },
+            _ => Err(value),
+        }
+    }
+
+    /// Cancels every entry whose deadline passed, along with those whose caller
+    /// went away, returning the keys of the expired ones.
+    pub fn sweep(&self) -> Vec<K> {
+        let now = Instant::now();
+        let mut expired = Vec::new();
+        let mut gone = Vec::new();
+        let mut entries = self.entries.lock().unwrap_or_else(PoisonError::into_inner);
+        for (key, (end, deadline)) in std::mem::take(&mut *entries) {
+            if deadline <= now { expired.push((key, end)) }
+            else if end.is_canceled() { gone.push(end) }
+            else { entries.insert(key, (end, deadline)); }
+        }
+        // canceled outside the lock
+        drop(entries);
+        drop(gone);
+        expired.into_iter().map(|(key, _)| key).collect()
+    }
+
+    /// Number of requests in flight, expired or not.
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap_or_else(PoisonError::into_inner).len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<K: Eq + Hash + Send + 'static, T: Send + 'static> InflightTable<K, T> {
+    /// Starts a thread sweeping the table every `period`, which stops once the
+    /// table is dropped.
+    pub fn spawn_sweeper(self: &Arc<Self>, period: Duration) -> JoinHandle<()> {
+        let table = Arc::downgrade(self);
+        std::thread::spawn(move || loop {
+            std::thread::sleep(period);
+            let Some(table) = table.upgrade() else { return };
+            table.sweep();
+        })
+    }
+}
+
+impl<K: Eq + Hash, T> Default for InflightTable<K, T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K, T> Debug for InflightTable<K, T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let inflight = self.entries.lock().unwrap_or_else(PoisonError::into_inner).len();
+        f.debug_struct("InflightTable").field("inflight", &inflight).finish()
+    }
+}
@@ -0,0 +1,41 @@
+use crate::{common::{self, Common}, Handshake, PullOutcome, PushOutcome, Receipt};
+
+impl<T> Handshake<T> {
+    /// Deposits `value` for the peer from an interrupt handler on bare metal, e.g.
+    /// to hand a value to the main loop through a [`StaticHandshake`](crate::StaticHandshake).
+    ///
+    /// Never waits, allocates or frees: a compare-and-swap, a store and waking
+    /// the registered wakers inside the critical section. Occupied as well while
+    /// the code interrupted is midway through using the slot, to retry on the
+    /// next interrupt. [`state`](Self::state) is a single load, safe to poll there
+    /// too; dropping an end may free a heap slot, and the `history` feature allocates.
+    pub fn try_push_isr(self, value: T) -> PushOutcome<T> {
+        match self.common().put_isr(value) {
+            Ok(()) => PushOutcome::Delivered(Receipt::new(self.into_common())),
+            Err((common::SET | common::BUSY, value)) => PushOutcome::Occupied(self, value),
+            Err((_, value)) => {
+                // handshake was cancelled
+                unsafe { Common::release(self.into_common()) };
+                PushOutcome::PeerGone(value)
+            }
+        }
+    }
+
+    /// Takes the value deposited by the peer from an interrupt handler, see [`try_push_isr`](Self::try_push_isr).
+    ///
+    /// Pending as well while the code interrupted is midway through using the slot.
+    pub fn try_pull_isr(self) -> PullOutcome<T> {
+        match self.common().take_isr() {
+            Ok(value) => {
+                unsafe { Common::release(self.into_common()) };
+                PullOutcome::Delivered(value)
+            }
+            Err(common::EMPTY | common::BUSY) => PullOutcome::Pending(self),
+            Err(_) => {
+                // handshake was cancelled
+                unsafe { Common::release(self.into_common()) };
+                PullOutcome::PeerGone
+            }
+        }
+    }
+}
@@ -0,0 +1,45 @@
+use std::fmt::Debug;
+
+use crate::HandshakeCell;
+
+/// Pairs up the items of `left`, iterated on the calling thread, with those of
+/// `right`, iterated on a thread of its own, in lockstep through a
+/// [`HandshakeCell`], yielding what `f` makes of each pair.
+///
+/// The streaming analogue of [`join`](crate::Handshake::join): `right` runs at
+/// most one item ahead, and both stop once either runs out.
+pub fn exchange_iters<I, J, U, F>(left: I, right: J, f: F) -> ExchangeIters<I::IntoIter, J::Item, F>
+where
+    I: IntoIterator,
+    J: IntoIterator + Send + 'static,
+    J::Item: Send + 'static,
+    F: FnMut(I::Item, J::Item) -> U,
+{
+    let (ours, mut theirs) = HandshakeCell::new();
+    // stops once the iterator is dropped
+    std::thread::spawn(move || right.into_iter().try_for_each(|item| theirs.push(item).map(drop)));
+    ExchangeIters { left: left.into_iter(), cell: ours, f }
+}
+
+/// Iterator returned by [`exchange_iters`].
+pub struct ExchangeIters<I, T, F> {
+    left: I,
+    cell: HandshakeCell<T>,
+    f: F,
+}
+
+impl<I: Iterator, T, U, F: FnMut(I::Item, T) -> U> Iterator for ExchangeIters<I, T, F> {
+    type Item = U;
+
+    fn next(&mut self) -> Option<U> {
+        let item = self.left.next()?;
+        let other = self.cell.pull().ok()?;
+        Some((self.f)(item, other))
+    }
+}
+
+impl<I, T, F> Debug for ExchangeIters<I, T, F> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ExchangeIters").field("cell", &self.cell).finish_non_exhaustive()
+    }
+}
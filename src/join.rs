@@ -0,0 +1,234 @@
+use std::{fmt::Debug, future::Future, pin::Pin, task::{Context, Poll}};
+
+use crate::{common::{self, Common}, sync, Canceled, Handshake};
+
+/// Future returned by [`Handshake::join_async`].
+///
+/// Dropping it cancels the exchange along with the end it owns, orphaning a
+/// value already deposited. Take both back with [`abort`](Self::abort), or join
+/// with [`join_ref`](Handshake::join_ref) to keep the end e.g. across `select!` branches.
+pub struct JoinFuture<T, F> {
+    handshake: Option<Handshake<T>>,
+    // both go once deposited or combined
+    value: Option<T>,
+    f: Option<F>,
+}
+
+/// Future returned by [`Handshake::join_ref`], taking a deposited value back
+/// and leaving the end in place when dropped.
+pub struct JoinRef<'a, T, F> {
+    handshake: &'a mut Handshake<T>,
+    // both go once deposited or combined, and `f` once done either way
+    value: Option<T>,
+    f: Option<F>,
+}
+
+impl<T> Handshake<T> {
+    /// Like [`join`](Self::join), but resolves only once both sides arrived.
+    ///
+    /// Arriving second combines with `f` and resolves to its result, arriving
+    /// first deposits `value` and resolves to `None` once the peer has combined.
+    pub fn join_async<U, F: FnOnce(T, T) -> U>(self, value: T, f: F) -> JoinFuture<T, F> {
+        JoinFuture { handshake: Some(self), value: Some(value), f: Some(f) }
+    }
+
+    /// Like [`join_async`](Self::join_async), but borrows the end, so dropping the
+    /// future before it completes takes `value` back unless the peer already got
+    /// to combining with it, leaving the exchange to join again later.
+    ///
+    /// Once joined the end is spent, joining through it again sees [`Canceled`].
+    pub fn join_ref<U, F: FnOnce(T, T) -> U>(&mut self, value: T, f: F) -> JoinRef<'_, T, F> {
+        JoinRef { handshake: self, value: Some(value), f: Some(f) }
+    }
+}
+
+impl<T, F> JoinFuture<T, F> {
+    /// Gives up joining without canceling, giving back the end and value to join
+    /// again later, e.g. when another `select!` branch won.
+    ///
+    /// A value already deposited is taken back first. `None` once the future
+    /// completed, or when the peer already got to combining with the value.
+    pub fn abort(mut self) -> Option<(Handshake<T>, T)> {
+        let handshake = self.handshake.take()?;
+        let value = match self.value.take() {
+            Some(value) => value,
+            None => handshake.common().retract().ok()?,
+        };
+        Some((handshake, value))
+    }
+}
+
+// deposits or combines `value` through `handshake`, then waits for the peer to combine
+fn poll_join<T, U, F: FnOnce(T, T) -> U>(
+    handshake: &Handshake<T>,
+    value: &mut Option<T>,
+    f: &mut Option<F>,
+    cx: &mut Context<'_>,
+) -> Poll<Result<Option<U>, Canceled>> {
+    while let Some(own) = value.take() {
+        match handshake.common().put(own) {
+            // first to arrive, wait for the peer below
+            Ok(()) => {}
+            Err((common::SET, rejected)) => match handshake.common().claim() {
+                Ok((other, claim)) => {
+                    // an unwinding `f` leaves the value lost rather than consumed
+                    let combined = (f.take().unwrap())(other, rejected);
+                    claim.complete();
+                    return Poll::Ready(Ok(Some(combined)))
+                }
+                // retracted in the meantime
+                Err(_) => *value = Some(rejected),
+            },
+            // handshake was cancelled
+            Err(_) => return Poll::Ready(Err(Canceled)),
+        }
+    }
+    // a peek holds the value busy only for a while, the peer still to combine
+    let mut state = handshake.common().state();
+    if matches!(state, common::SET | common::BUSY | common::COMBINING) {
+        handshake.common().register(cx);
+        // re-check after registering so a wake-up can't slip through
+        state = handshake.common().state();
+    }
+    match state {
+        common::SET | common::BUSY | common::COMBINING => Poll::Pending,
+        common::TAKEN => Poll::Ready(Ok(None)),
+        // peer went away, or dropped the value while combining
+        _ => Poll::Ready(Err(Canceled)),
+    }
+}
+
+// never pinned, values and ends move freely
+impl<T, F> Unpin for JoinFuture<T, F> {}
+
+impl<T, U, F: FnOnce(T, T) -> U> Future for JoinFuture<T, F> {
+    type Output = Result<Option<U>, Canceled>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = &mut *self;
+        let handshake = this.handshake.as_ref().expect("join future polled after completion");
+        let res = poll_join(handshake, &mut this.value, &mut this.f, cx);
+        if res.is_ready() {
+            let handshake = this.handshake.take().unwrap();
+            unsafe { Common::release(handshake.into_common()) };
+        }
+        res
+    }
+}
+
+// never pinned either, the end is only borrowed
+impl<T, F> Unpin for JoinRef<'_, T, F> {}
+
+impl<T, U, F: FnOnce(T, T) -> U> Future for JoinRef<'_, T, F> {
+    type Output = Result<Option<U>, Canceled>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = &mut *self;
+        if this.f.is_none() { panic!("join future polled after completion") }
+        let res = poll_join(this.handshake, &mut this.value, &mut this.f, cx);
+        if res.is_ready() { this.f = None }
+        res
+    }
+}
+
+impl<T, F> Drop for JoinRef<'_, T, F> {
+    fn drop(&mut self) {
+        // deposited and not done: take it back unless the peer got to it
+        if self.value.is_none() && self.f.is_some() {
+            let _ = self.handshake.common().retract();
+        }
+    }
+}
+
+impl<T, F> Debug for JoinFuture<T, F> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("JoinFuture").field("handshake", &self.handshake).finish_non_exhaustive()
+    }
+}
+
+impl<T, F> Debug for JoinRef<'_, T, F> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("JoinRef").field("handshake", &self.handshake).finish_non_exhaustive()
+    }
+}
+
+// hands both values out for the caller to combine
+type Pair<T> = fn(T, T) -> (T, T);
+
+/// What came of the joins driven by [`join_all`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Joined<U> {
+    /// The values combined on this side, in iteration order, leaving out joins the peer combined.
+    pub combined: Vec<U>,
+    /// The indices of the joins whose peer went away.
+    pub canceled: Vec<usize>,
+}
+
+/// Future returned by [`join_all_async`].
+pub struct JoinAll<T, U, F> {
+    pending: Vec<(usize, JoinFuture<T, Pair<T>>)>,
+    combined: Vec<(usize, U)>,
+    canceled: Vec<usize>,
+    f: F,
+}
+
+/// Joins every end with its value, combining with `f` where the peer arrived
+/// first, and blocks until every join either completed or was canceled.
+///
+/// All values are deposited before waiting on any, so peers may go through their ends in any order.
+pub fn join_all<T, U, F, I>(joins: I, f: F) -> Joined<U>
+where I: IntoIterator<Item = (Handshake<T>, T)>, F: FnMut(T, T) -> U {
+    let mut joins = join_all_async(joins, f);
+    let waker = common::thread_waker();
+    let cx = &mut Context::from_waker(&waker);
+    loop {
+        match Pin::new(&mut joins).poll(cx) {
+            Poll::Ready(joined) => return joined,
+            Poll::Pending => sync::park(),
+        }
+    }
+}
+
+/// Like [`join_all`], but resolving once every join completed or was canceled.
+pub fn join_all_async<T, U, F, I>(joins: I, f: F) -> JoinAll<T, U, F>
+where I: IntoIterator<Item = (Handshake<T>, T)>, F: FnMut(T, T) -> U {
+    let pair: Pair<T> = |other, own| (other, own);
+    let pending = joins.into_iter().map(|(end, value)| end.join_async(value, pair)).enumerate().collect();
+    JoinAll { pending, combined: Vec::new(), canceled: Vec::new(), f }
+}
+
+// never pinned, values and ends move freely
+impl<T, U, F> Unpin for JoinAll<T, U, F> {}
+
+impl<T, U, F: FnMut(T, T) -> U> Future for JoinAll<T, U, F> {
+    type Output = Joined<U>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = &mut *self;
+        let mut i = 0;
+        while i < this.pending.len() {
+            let (index, join) = &mut this.pending[i];
+            let index = *index;
+            match Pin::new(join).poll(cx) {
+                Poll::Pending => { i += 1; continue }
+                Poll::Ready(Ok(Some((other, own)))) => this.combined.push((index, (this.f)(other, own))),
+                Poll::Ready(Ok(None)) => {}
+                Poll::Ready(Err(Canceled)) => this.canceled.push(index),
+            }
+            drop(this.pending.swap_remove(i));
+        }
+        if !this.pending.is_empty() { return Poll::Pending }
+        this.combined.sort_by_key(|&(index, _)| index);
+        this.canceled.sort_unstable();
+        Poll::Ready(Joined {
+            combined: std::mem::take(&mut this.combined).into_iter().map(|(_, combined)| combined).collect(),
+            canceled: std::mem::take(&mut this.canceled),
+        })
+    }
+}
+
+impl<T, U, F> Debug for JoinAll<T, U, F> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("JoinAll").field("pending", &self.pending.len()).finish_non_exhaustive()
+    }
+}
@@ -0,0 +1,74 @@
+//! Records exchanges that died silently, with where they were created, for debugging.
+//!
+//! A value dropped because the peer went away without it, or an end dropped
+//! before anything was deposited while the peer may still wait, is recorded
+//! along with a backtrace captured when the handshake was made. Capturing one
+//! per handshake is slow, keep the feature to debug builds.
+
+use std::{backtrace::Backtrace, sync::{Mutex, PoisonError}};
+
+static LEAKS: Mutex<Vec<Leak>> = Mutex::new(Vec::new());
+
+/// How an exchange died.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LeakKind {
+    /// A pushed value was left behind by a peer that went away without it.
+    Lost,
+    /// An end went away before anything was deposited, the peer possibly waiting on it.
+    Canceled,
+}
+
+/// An exchange that died, see [`leaks`].
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct Leak {
+    pub kind: LeakKind,
+    /// The id the exchange had, see [`Handshake::id`](crate::Handshake::id).
+    pub id: u64,
+    /// Where the handshake was made, empty for one in a `static`.
+    pub backtrace: String,
+}
+
+/// Takes everything recorded so far.
+pub fn leaks() -> Vec<Leak> {
+    std::mem::take(&mut *LEAKS.lock().unwrap_or_else(PoisonError::into_inner))
+}
+
+/// Prints everything recorded so far to stderr, returning how many there were.
+pub fn report() -> usize {
+    let leaks = leaks();
+    for leak in &leaks {
+        eprintln!("handshake {:#x} {}, created at:\n{}", leak.id, match leak.kind {
+            LeakKind::Lost => "lost its value",
+            LeakKind::Canceled => "was canceled before anything was deposited",
+        }, leak.backtrace);
+    }
+    leaks.len()
+}
+
+/// Reports once dropped, see [`report`].
+///
+/// Nothing runs as the process exits, hold one in `main` to report then.
+#[must_use = "reports once dropped"]
+#[derive(Debug)]
+pub struct ReportGuard(());
+
+pub fn guard() -> ReportGuard {
+    ReportGuard(())
+}
+
+impl Drop for ReportGuard {
+    fn drop(&mut self) {
+        report();
+    }
+}
+
+// captures where a handshake is being made
+pub(crate) fn origin() -> Backtrace {
+    Backtrace::force_capture()
+}
+
+pub(crate) fn record(kind: LeakKind, id: u64, origin: Option<&Backtrace>) {
+    let backtrace = origin.map(ToString::to_string).unwrap_or_default();
+    LEAKS.lock().unwrap_or_else(PoisonError::into_inner).push(Leak { kind, id, backtrace })
+}
@@ -1,123 +1,997 @@
-use std::{cell::UnsafeCell, fmt::Debug, ptr::NonNull, sync::OnceLock};
+use std::{cmp::Ordering, fmt::Debug, hash::{Hash, Hasher}, marker::PhantomData, mem::ManuallyDrop, ptr::NonNull, task::Context, time::{Duration, Instant}};
 
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+use common::Common;
+use strategy::{Local, SendSafe};
+
+// emits a `tracing` event with the `tracing` feature, compiling to nothing otherwise
+macro_rules! trace {
+    ($($arg:tt)*) => {{
+        #[cfg(feature = "tracing")]
+        tracing::trace!($($arg)*);
+    }};
+}
+
+mod any;
+#[cfg(any(target_has_atomic = "64", feature = "portable-atomic"))]
+pub mod atomic;
+mod barrier;
+mod bridge;
+mod builder;
+mod broadcast;
+mod buffered;
+mod bulk;
+mod cell;
+#[cfg(feature = "chaos")]
+pub mod chaos;
+mod channel;
+mod combinators;
+mod common;
+mod connect;
+#[cfg(feature = "crossbeam")]
+mod crossbeam;
+#[cfg(feature = "deadlock-detection")]
+pub mod deadlock;
+pub mod expiring;
+mod fallible;
+mod group;
+mod guard;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+mod hedge;
+mod inflight;
+mod iters;
+#[cfg(feature = "embassy")]
+mod isr;
+#[cfg(feature = "history")]
+pub mod history;
+mod join;
+#[cfg(feature = "leak-check")]
+pub mod leak_check;
+mod local;
+mod map;
+#[cfg(feature = "metrics")]
+pub mod metrics;
+#[cfg(feature = "futures")]
+mod oneshot;
+mod oneway;
+mod pairs;
+mod pipeline;
+mod poller;
+#[cfg(feature = "allocator")]
+mod pool;
+mod promise;
+mod pull;
+mod queue;
+#[cfg(feature = "rayon")]
+pub mod rayon;
+mod receipt;
+mod relay;
+#[cfg(feature = "remote")]
+pub mod remote;
+mod rendezvous;
+mod retry;
+mod ring;
+mod roles;
+mod rpc;
+mod scope;
+mod scoped;
+mod select;
+mod shared;
+mod shutdown;
+#[cfg(feature = "serde")]
+mod snapshot;
+#[cfg(all(feature = "shm", target_os = "linux"))]
+pub mod shm;
+#[cfg(not(loom))]
+mod signal;
+#[cfg(feature = "test-util")]
+pub mod sim;
+pub mod slot;
+#[cfg(not(loom))]
+mod statics;
+pub mod strategy;
+#[cfg(feature = "test-util")]
+pub mod test_util;
+mod sync;
+#[cfg(feature = "timer")]
+mod timer;
+#[cfg(feature = "timestamps")]
+mod timestamps;
+#[cfg(feature = "tokio-util")]
+mod token;
+pub mod wait;
+mod watcher;
+mod weak;
+
+pub use any::{AnyHandshake, AnyValue};
+pub use barrier::Barrier2;
+pub use bridge::{AsyncEnd, BlockingEnd};
+pub use builder::HandshakeBuilder;
+pub use broadcast::Broadcast;
+pub use buffered::BufferedHandshake;
+pub use bulk::{cancel_all, drain_ready};
+pub use cell::{CellPush, HandshakeCell, OnOccupied};
+pub use channel::RendezvousChannel;
+pub use combinators::{race, zip};
+pub use common::Peek;
+pub use fallible::Failure;
+pub use group::HandshakeGroup;
+pub use guard::{Aborted, FulfillGuard};
+pub use hedge::{SharedPusher, Superseded};
+pub use inflight::InflightTable;
+pub use iters::{exchange_iters, ExchangeIters};
+pub use join::{join_all, join_all_async, JoinAll, JoinFuture, JoinRef, Joined};
+pub use local::{LocalHandshake, LocalPullFuture};
+pub use map::RendezvousMap;
+pub use oneway::{Puller, Pusher};
+pub use pipeline::Pipeline;
+pub use poller::Poller;
+#[cfg(feature = "allocator")]
+pub use pool::HandshakePool;
+pub use promise::{Promise, Resolver};
+pub use pull::{PullFuture, PullRef};
+pub use queue::HandshakeQueue;
+pub use receipt::{Delivery, Receipt};
+pub use rendezvous::Rendezvous;
+pub use retry::{Attempts, Backoff, RetryPolicy};
+pub use ring::{ring_exchange, RingEnd};
+pub use roles::{PullEnd, PushEnd};
+pub use rpc::{rpc, Callee, Caller, Reply};
+pub use scope::CancelScope;
+pub use scoped::{exchange_between, lend_between, spawn_joined, ScopedHandshake};
+pub use select::{select_pull, select_pull_by_key, Next, Select};
+pub use shutdown::ShutdownHandshake;
+#[cfg(not(loom))]
+pub use signal::SignalHandshake;
+#[cfg(not(loom))]
+pub use statics::StaticHandshake;
+#[cfg(feature = "serde")]
+pub use snapshot::Snapshot;
+pub use watcher::Watcher;
+pub use weak::WeakHandshake;
+
+/// The peer went away before the exchange completed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct Canceled;
 
-#[derive(PartialEq, Eq, PartialOrd, Ord)]
-pub struct Handshake<T> {
+impl std::fmt::Display for Canceled {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("handshake canceled, the peer went away")
+    }
+}
+
+impl std::error::Error for Canceled {}
+
+/// Where an exchange currently stands, as seen from one of its ends.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[non_exhaustive]
+pub enum State {
+    /// Nothing has been pushed yet.
+    Empty,
+    /// A value is waiting to be pulled.
+    Set,
+    /// The pushed value was pulled by the peer.
+    TakenByPeer,
+    /// The peer went away without completing the exchange.
+    PeerGone,
+}
+
+impl State {
+    fn of(state: u8) -> State {
+        match state {
+            common::SET | common::COMBINING | common::CLOSED | common::SWAPPED => State::Set,
+            common::TAKEN | common::SHARED => State::TakenByPeer,
+            common::CANCELED | common::LOST | common::ORPHANED | common::TIMED_OUT => State::PeerGone,
+            // a value moving in or out isn't observable yet
+            _ => State::Empty,
+        }
+    }
+}
+
+/// Which end of an exchange something belongs to, as assigned by its users.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Side {
+    Left,
+    Right,
+}
+
+impl Side {
+    pub fn other(self) -> Side {
+        match self {
+            Side::Left => Side::Right,
+            Side::Right => Side::Left,
+        }
+    }
+}
+
+/// How a [`Handshake::join_with_context`] came together, handed to the combining closure.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub struct JoinContext {
+    /// The side that arrived second and is combining, the other one deposited first.
+    pub second: Side,
+}
+
+/// Result of [`Handshake::try_join`].
+#[derive(Debug, PartialEq, Eq)]
+pub enum JoinOutcome<T, U> {
+    /// The peer's value was combined with ours.
+    Joined(U),
+    /// The peer hasn't pushed yet, both end and value are handed back.
+    NotReady(Handshake<T>, T),
+    /// The peer went away, the value is handed back.
+    PeerGone(T),
+}
+
+/// Result of [`Handshake::try_push_validated`].
+#[derive(Debug)]
+pub enum ValidatedPush<T, E> {
+    /// The value passed and was deposited.
+    Pushed(Receipt<T>),
+    /// The value failed validation, the slot stays empty and end, value and error are handed back.
+    Rejected(Handshake<T>, T, E),
+    /// The peer already pushed, both end and value are handed back.
+    Occupied(Handshake<T>, T),
+    /// The peer went away, the value is handed back.
+    PeerGone(T),
+}
+
+/// Result of [`Handshake::try_pull_or_push`].
+#[derive(Debug)]
+pub enum PullOrPush<T> {
+    /// The peer's value was there and was taken, handed out first and ours second.
+    Pulled(T, T),
+    /// Nothing was there, ours was deposited for the peer.
+    Pushed(Receipt<T>),
+}
+
+/// Result of [`Handshake::push`].
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum PushOutcome<T, S = SendSafe> {
+    /// The value was deposited for the peer.
+    Delivered(Receipt<T>),
+    /// The peer already pushed, both end and value are handed back to pull instead.
+    Occupied(Handshake<T, S>, T),
+    /// The peer went away, the value is handed back.
+    PeerGone(T),
+}
+
+/// Result of [`Handshake::pull_now`].
+#[derive(Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum PullOutcome<T, S = SendSafe> {
+    /// The peer's value was taken.
+    Delivered(T),
+    /// Nothing was pushed yet, the end is handed back.
+    Pending(Handshake<T, S>),
+    /// The peer went away.
+    PeerGone,
+}
+
+/// Why [`Handshake::try_push`] gave the value back.
+#[derive(Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum PushError<T, S = SendSafe> {
+    /// The peer already pushed, both end and value are handed back to pull instead.
+    AlreadySet(Handshake<T, S>, T),
+    /// The peer went away.
+    PeerGone(T),
+}
+
+impl<T, S> std::fmt::Display for PushError<T, S> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PushError::AlreadySet(..) => f.write_str("handshake already set by the peer, pull instead"),
+            PushError::PeerGone(_) => std::fmt::Display::fmt(&Canceled, f),
+        }
+    }
+}
+
+impl<T: Debug, S: Debug> std::error::Error for PushError<T, S> {}
+
+/// Why [`Handshake::try_pull`] came back empty handed.
+#[derive(PartialEq, Eq)]
+#[non_exhaustive]
+pub enum PullError<T, S = SendSafe> {
+    /// Nothing was pushed yet, the end is handed back.
+    Empty(Handshake<T, S>),
+    /// The peer went away.
+    PeerGone,
+}
+
+impl<T, S> PullError<T, S> {
+    /// Gives back the end if the peer may still push.
+    pub fn into_handshake(self) -> Option<Handshake<T, S>> {
+        match self {
+            PullError::Empty(handshake) => Some(handshake),
+            PullError::PeerGone => None,
+        }
+    }
+}
+
+impl<T, S> std::fmt::Display for PullError<T, S> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PullError::Empty(_) => f.write_str("handshake empty, nothing pushed yet"),
+            PullError::PeerGone => std::fmt::Display::fmt(&Canceled, f),
+        }
+    }
+}
+
+impl<T, S> Debug for PullError<T, S> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PullError::Empty(handshake) => f.debug_tuple("Empty").field(handshake).finish(),
+            PullError::PeerGone => f.write_str("PeerGone"),
+        }
+    }
+}
+
+impl<T, S> std::error::Error for PullError<T, S> {}
+
+impl<T, S> PullOutcome<T, S> {
+    // the nested shape, for `?`
+    pub(crate) fn into_result(self) -> Result<Result<T, Handshake<T, S>>, Canceled> {
+        match self {
+            PullOutcome::Delivered(value) => Ok(Ok(value)),
+            PullOutcome::Pending(handshake) => Ok(Err(handshake)),
+            PullOutcome::PeerGone => Err(Canceled),
+        }
+    }
+}
+
+/// One end of a handshake.
+///
+/// Ends compare, order and hash by the exchange they belong to, never by the values exchanged.
+///
+/// Both ends share a single allocation holding the state, the payload and any
+/// registered wakers, 56 bytes when exchanging a `u64` on 64-bit targets.
+///
+/// The second parameter picks whether ends may move between threads, see [`strategy`].
+/// By default ends, their futures, receipts and watchers are `Send` and `Sync`
+/// whenever `T` is `Send`, the value only ever being touched by one side at a time.
+pub struct Handshake<T, S = SendSafe> {
     // NotNull is & unless deduced otherwise
-    common: NonNull<OnceLock<UnsafeCell<Option<T>>>>
+    common: NonNull<Common<T>>,
+    // only picks the `Send` and `Sync` impls, leaving the other auto traits alone
+    strategy: PhantomData<fn() -> S>,
 }
 
 impl<T> Handshake<T> {
     pub fn new() -> (Handshake<T>, Handshake<T>) {
-        // check expected to be elided during compilation
-        let common = unsafe { NonNull::new_unchecked(Box::into_raw(
-            Box::new(OnceLock::new())
-        ))};
-        (Handshake {common}, Handshake {common})
+        let common = Common::alloc(2);
+        trace!(id = common::id(common), "handshake created");
+        (Handshake::from_common(common), Handshake::from_common(common))
+    }
+
+    /// Creates a handshake whose value is already pushed, returning the pusher's receipt and the end to pull from.
+    ///
+    /// Cheaper than pushing into a fresh pair, the slot starting out set.
+    pub fn with_value(value: T) -> (Receipt<T>, Handshake<T>) {
+        let common = Common::alloc_with(2, value);
+        trace!(id = common::id(common), "handshake created with value");
+        (Receipt::new(common), Handshake::from_common(common))
+    }
+
+    /// Creates a handshake whose shared slot is allocated from `alloc`, e.g. an arena or pool.
+    #[cfg(feature = "allocator")]
+    pub fn new_in<A>(alloc: A) -> (Handshake<T>, Handshake<T>)
+    where A: allocator_api2::alloc::Allocator + Send + Sync + 'static {
+        let common = Common::alloc_in(2, alloc);
+        (Handshake::from_common(common), Handshake::from_common(common))
+    }
+
+    /// Creates `n` handshakes at once, the ends at each index belonging together.
+    pub fn pairs(n: usize) -> (Vec<Handshake<T>>, Vec<Handshake<T>>) {
+        (0..n).map(|_| Handshake::new()).unzip()
+    }
+
+    /// Creates `N` handshakes at once without going through a `Vec`, the ends at each index belonging together.
+    pub fn array<const N: usize>() -> ([Handshake<T>; N], [Handshake<T>; N]) {
+        let commons: [_; N] = std::array::from_fn(|_| Common::alloc(2));
+        (commons.map(|common| Handshake::from_common(common)), commons.map(|common| Handshake::from_common(common)))
+    }
+
+    /// Consumes the end into a raw pointer, e.g. to hand it across an FFI boundary.
+    pub fn into_raw(self) -> *const () {
+        self.into_common().as_ptr().cast_const().cast()
+    }
+
+    /// Restores an end from [`into_raw`](Self::into_raw).
+    ///
+    /// # Safety
+    /// `ptr` must come from `into_raw` on an end of the same payload type, and be restored only once.
+    pub unsafe fn from_raw(ptr: *const ()) -> Handshake<T> {
+        Handshake::from_common(NonNull::new_unchecked(ptr.cast_mut().cast()))
+    }
+
+    /// Like [`join`](Self::join) from the given `side`, the peer joining from the other,
+    /// but `f` receives the left value first and the right second, plus who arrived second.
+    pub fn join_with_context<U, F>(self, side: Side, value: T, f: F) -> Result<Option<U>, Canceled>
+    where F: FnOnce(T, T, JoinContext) -> U {
+        self.join(value, |other, own| {
+            let context = JoinContext { second: side };
+            match side {
+                Side::Left => (f)(own, other, context),
+                Side::Right => (f)(other, own, context),
+            }
+        })
+    }
+
+    /// Like [`join`](Self::join), but hands `value` to `on_cancel` instead of dropping it
+    /// when the peer is gone.
+    pub fn join_or_else<U, F, G>(self, value: T, f: F, on_cancel: G) -> Option<U>
+    where F: FnOnce(T, T) -> U, G: FnOnce(T) -> U {
+        self.rendezvous(value, f).unwrap_or_else(|value| Some((on_cancel)(value)))
+    }
+
+    /// Like [`join`](Self::join), but always combines on this side, blocking until the peer's value arrives.
+    ///
+    /// With the peer calling `join`, the outcome no longer depends on who arrives first.
+    /// Both ends calling this wait on each other forever.
+    pub fn join_combining<U, F: FnOnce(T, T) -> U>(self, value: T, f: F) -> Result<U, Canceled> {
+        let other = self.pull_blocking()?;
+        Ok((f)(other, value))
+    }
+
+    /// Like [`join`](Self::join), but when arriving first waits up to `timeout` for the peer to combine.
+    ///
+    /// Gives back the end and value if the peer didn't arrive in time, so the value can be used elsewhere.
+    pub fn join_timeout<U, F>(self, value: T, f: F, timeout: Duration) -> Result<Result<Option<U>, (Self, T)>, Canceled>
+    where F: FnOnce(T, T) -> U {
+        let deadline = Instant::now() + timeout;
+        let mut value = value;
+        loop {
+            match self.common().put(value) {
+                Ok(()) => {
+                    // first to arrive, wait for the peer to combine, through any peek at the value
+                    let state = self.common().block_until_deadline(deadline, || {
+                        Some(self.common().state()).filter(|&state| !matches!(state, common::SET | common::BUSY))
+                    });
+                    let state = match state {
+                        Some(state) => state,
+                        None => match self.common().retract() {
+                            Ok(value) => return Ok(Err((self, value))),
+                            // peer arrived just in time
+                            Err(state) => state,
+                        },
+                    };
+                    unsafe { Common::release(self.into_common()) };
+                    return match state {
+                        common::COMBINING | common::TAKEN => Ok(Ok(None)),
+                        _ => Err(Canceled),
+                    }
+                }
+                Err((common::SET, rejected)) => match self.common().claim() {
+                    Ok((other, claim)) => {
+                        // an unwinding `f` leaves the value lost rather than consumed
+                        let combined = (f)(other, rejected);
+                        claim.complete();
+                        unsafe { Common::release(self.into_common()) };
+                        return Ok(Ok(Some(combined)))
+                    }
+                    // retracted in the meantime
+                    Err(_) => value = rejected,
+                },
+                Err(_) => {
+                    // handshake was cancelled
+                    unsafe { Common::release(self.into_common()) };
+                    return Err(Canceled)
+                }
+            }
+        }
+    }
+
+    /// Swaps `value` for the peer's, blocking until the peer meets it too, like Java's `Exchanger`.
+    pub fn meet(self, value: T) -> Result<T, Canceled> {
+        let mut value = value;
+        loop {
+            match self.common().put(value) {
+                Ok(()) => {
+                    // first to arrive, wait for the peer to swap, through any peek at the value
+                    self.common().block_while(|state| matches!(state, common::SET | common::BUSY));
+                    return match self.common().take_swapped() {
+                        Ok(other) => { unsafe { Common::release(self.into_common()) }; Ok(other) }
+                        // own value dropped along with the end
+                        Err(_) => Err(Canceled),
+                    }
+                }
+                Err((common::SET, rejected)) => match self.common().swap(rejected) {
+                    Ok(other) => { unsafe { Common::release(self.into_common()) }; return Ok(other) }
+                    // retracted in the meantime
+                    Err((_, rejected)) => value = rejected,
+                },
+                Err(_) => {
+                    // handshake was cancelled
+                    unsafe { Common::release(self.into_common()) };
+                    return Err(Canceled)
+                }
+            }
+        }
+    }
+
+    /// Like [`meet`](Self::meet), but when arriving first waits up to `timeout` for the peer to swap.
+    ///
+    /// Gives back the end and value if the peer didn't arrive in time.
+    pub fn meet_timeout(self, value: T, timeout: Duration) -> Result<Result<T, (Self, T)>, Canceled> {
+        let deadline = Instant::now() + timeout;
+        let mut value = value;
+        loop {
+            match self.common().put(value) {
+                Ok(()) => {
+                    // first to arrive, wait for the peer to swap, through any peek at the value
+                    let swapped = self.common().block_until_deadline(deadline, || {
+                        Some(()).filter(|_| !matches!(self.common().state(), common::SET | common::BUSY))
+                    });
+                    if swapped.is_none() {
+                        // otherwise the peer arrived just in time
+                        if let Ok(value) = self.common().retract() { return Ok(Err((self, value))) }
+                    }
+                    return match self.common().take_swapped() {
+                        Ok(other) => { unsafe { Common::release(self.into_common()) }; Ok(Ok(other)) }
+                        // own value dropped along with the end
+                        Err(_) => Err(Canceled),
+                    }
+                }
+                Err((common::SET, rejected)) => match self.common().swap(rejected) {
+                    Ok(other) => { unsafe { Common::release(self.into_common()) }; return Ok(Ok(other)) }
+                    // retracted in the meantime
+                    Err((_, rejected)) => value = rejected,
+                },
+                Err(_) => {
+                    // handshake was cancelled
+                    unsafe { Common::release(self.into_common()) };
+                    return Err(Canceled)
+                }
+            }
+        }
+    }
+
+    /// Swaps `value_a` and `value_b` between this end and `peer` on the calling thread,
+    /// as [`meet`](Self::meet) on both ends would, for code owning both, e.g. tests.
+    ///
+    /// `f` receives what this end got, `value_b`, and what the peer got, `value_a`.
+    /// [`Canceled`] if the exchange was canceled from elsewhere, e.g. by its
+    /// [`CancelScope`] or deadline, the values dropped then.
+    ///
+    /// # Panics
+    /// If `peer` isn't the other end of this exchange.
+    pub fn complete_locally<U, F>(self, peer: Handshake<T>, value_a: T, value_b: T, f: F) -> Result<U, Canceled>
+    where F: FnOnce(T, T) -> U {
+        assert!(self.same_channel(&peer), "ends belong to different exchanges");
+        // both ends held, only a cancellation from elsewhere gets in the way
+        self.common().put(value_a).map_err(|_| Canceled)?;
+        let received_by_peer = peer.common().swap(value_b).map_err(|_| Canceled)?;
+        let received = self.common().take_swapped().map_err(|_| Canceled)?;
+        unsafe {
+            Common::release(peer.into_common());
+            Common::release(self.into_common());
+        }
+        Ok((f)(received, received_by_peer))
+    }
+
+    /// Combines `value` with the peer's if it is already there, without ever depositing it.
+    pub fn try_join<U, F: FnOnce(T, T) -> U>(self, value: T, f: F) -> JoinOutcome<T, U> {
+        match self.common().claim() {
+            Ok((other, claim)) => {
+                // an unwinding `f` leaves the value lost rather than consumed
+                let combined = (f)(other, value);
+                claim.complete();
+                unsafe { Common::release(self.into_common()) };
+                JoinOutcome::Joined(combined)
+            }
+            Err(common::EMPTY) => JoinOutcome::NotReady(self, value),
+            Err(_) => {
+                // handshake was cancelled
+                unsafe { Common::release(self.into_common()) };
+                JoinOutcome::PeerGone(value)
+            }
+        }
+    }
+
+    /// Like [`try_push`](Self::try_push), but only builds the value with `f` while the peer may still collect it.
+    ///
+    /// Gives back the end when the peer already pushed, or [`Canceled`] when it is gone,
+    /// dropping the built value should either happen while `f` runs.
+    pub fn try_push_with(self, f: impl FnOnce() -> T) -> Result<Result<Receipt<T>, Self>, Canceled> {
+        match self.common().state() {
+            common::EMPTY => {}
+            common::SET => return Ok(Err(self)),
+            _ => return Err(Canceled),
+        }
+        match self.push((f)()) {
+            PushOutcome::Delivered(receipt) => Ok(Ok(receipt)),
+            PushOutcome::Occupied(handshake, _) => Ok(Err(handshake)),
+            PushOutcome::PeerGone(_) => Err(Canceled),
+        }
+    }
+
+    /// Like [`try_push`](Self::try_push), but only deposits `value` if `validate` accepts it,
+    /// e.g. to gatekeep untrusted output.
+    pub fn try_push_validated<E>(self, value: T, validate: impl FnOnce(&T) -> Result<(), E>) -> ValidatedPush<T, E> {
+        if let Err(error) = (validate)(&value) { return ValidatedPush::Rejected(self, value, error) }
+        match self.push(value) {
+            PushOutcome::Delivered(receipt) => ValidatedPush::Pushed(receipt),
+            PushOutcome::Occupied(handshake, value) => ValidatedPush::Occupied(handshake, value),
+            PushOutcome::PeerGone(value) => ValidatedPush::PeerGone(value),
+        }
+    }
+
+    /// Takes the peer's value if it is there, otherwise deposits `value`, in one step.
+    ///
+    /// Gives back the value when the peer is gone.
+    pub fn try_pull_or_push(self, value: T) -> Result<PullOrPush<T>, T> {
+        let mut value = value;
+        loop {
+            match self.common().put(value) {
+                Ok(()) => return Ok(PullOrPush::Pushed(Receipt::new(self.into_common()))),
+                Err((common::SET, rejected)) => match self.common().take() {
+                    Ok(other) => {
+                        unsafe { Common::release(self.into_common()) };
+                        return Ok(PullOrPush::Pulled(other, rejected))
+                    }
+                    // retracted in the meantime
+                    Err(common::EMPTY) => value = rejected,
+                    Err(_) => {
+                        // handshake was cancelled
+                        unsafe { Common::release(self.into_common()) };
+                        return Err(rejected)
+                    }
+                },
+                Err((_, rejected)) => {
+                    // handshake was cancelled
+                    unsafe { Common::release(self.into_common()) };
+                    return Err(rejected)
+                }
+            }
+        }
+    }
+}
+
+impl<T> Handshake<T, Local> {
+    /// Creates a pair whose ends stay on this thread, see [`Local`].
+    pub fn new_local() -> (Handshake<T, Local>, Handshake<T, Local>) {
+        let common = Common::alloc(2);
+        #[cfg(feature = "deadlock-detection")]
+        unsafe { common.as_ref() }.set_owner(Some(std::thread::current().id()));
+        trace!(id = common::id(common), "local handshake created");
+        (Handshake::from_common(common), Handshake::from_common(common))
+    }
+}
+
+impl<T, S> Handshake<T, S> {
+    pub(crate) fn from_common(common: NonNull<Common<T>>) -> Handshake<T, S> {
+        Handshake { common, strategy: PhantomData }
     }
 
+    fn common(&self) -> &Common<T> {
+        // shared state outlives both ends
+        unsafe { self.common.as_ref() }
+    }
+
+    // consumes `self` without cancelling, handing its reference to the caller
+    fn into_common(self) -> NonNull<Common<T>> {
+        ManuallyDrop::new(self).common
+    }
+
+    /// Meets the peer with `value`, the second to arrive calling `f` with the peer's value first and its own second.
+    ///
+    /// Arriving first deposits `value` for the peer to combine and returns `None`.
     pub fn join<U, F: FnOnce(T, T) -> U>(self, value: T, f: F) -> Result<Option<U>, Canceled> {
-        let mut value = Some(value);
-        // access safe lock
-        let res = unsafe { self.common.as_ref() }.get_or_init(||
-            UnsafeCell::new(Some(value.take().unwrap()))
-        );
-        let combined = value.map_or(Ok(None), |value| {
-            // unique access if value present
-            let combined = unsafe { &mut*res.get() }
-                .take()
-                .map_or(Err(Canceled), |other| {
-                    Ok(Some((f)(other, value)))
-                });
-            // last reference, drop pointer
-            drop(unsafe { Box::from_raw(self.common.as_ptr()) });
-            combined
-        });
-        std::mem::forget(self); // consumes `self`
-        combined
+        let _id = self.id();
+        let res = self.rendezvous(value, f).map_err(|_| Canceled);
+        trace!(id = _id, combined = matches!(res, Ok(Some(_))), canceled = res.is_err(), "handshake joined");
+        res
     }
 
-    pub fn try_push(self, value: T) -> Result<Result<(), (Self, T)>, T> {
-        let mut value = Some(value);
-        // access safe lock
-        let res = unsafe { self.common.as_ref() }.get_or_init(||
-            UnsafeCell::new(Some(value.take().unwrap()))
-        );
-        if let Some(value) = value {
-            // value present, lock inhabited
-            if unsafe { &*res.get() }.is_none() {
+    /// Like [`join`](Self::join), but `f` may fail, its error being returned instead of `Some`.
+    ///
+    /// The failure reaches the peer as a lost value, so a peer waiting with
+    /// [`join_async`](Self::join_async) or on its receipt sees it canceled.
+    pub fn try_join_with<U, E, F>(self, value: T, f: F) -> Result<Result<Option<U>, E>, Canceled>
+    where F: FnOnce(T, T) -> Result<U, E> {
+        let _id = self.id();
+        let res = self.try_rendezvous(value, f).map_err(|_| Canceled);
+        trace!(id = _id, combined = matches!(res, Ok(Some(Ok(_)))), canceled = res.is_err(), "handshake joined");
+        res.map(Option::transpose)
+    }
+
+    // deposits or combines, handing `value` back if the peer is gone
+    fn rendezvous<U, F: FnOnce(T, T) -> U>(self, value: T, f: F) -> Result<Option<U>, T> {
+        let res = self.try_rendezvous(value, |a, b| Ok::<_, std::convert::Infallible>((f)(a, b)));
+        res.map(|combined| combined.map(|Ok(combined)| combined))
+    }
+
+    // like `rendezvous`, leaving the peer's value lost rather than consumed when `f` fails
+    fn try_rendezvous<U, E, F>(self, value: T, f: F) -> Result<Option<Result<U, E>>, T>
+    where F: FnOnce(T, T) -> Result<U, E> {
+        let mut value = value;
+        loop {
+            match self.common().put(value) {
+                Ok(()) => {
+                    // first to arrive, peer combines
+                    unsafe { Common::release(self.into_common()) };
+                    return Ok(None)
+                }
+                Err((common::SET, rejected)) => match self.common().claim() {
+                    Ok((other, claim)) => {
+                        // an unwinding or failing `f` leaves the value lost rather than consumed
+                        let combined = (f)(other, rejected);
+                        if combined.is_ok() { claim.complete() } else { drop(claim) }
+                        unsafe { Common::release(self.into_common()) };
+                        return Ok(Some(combined))
+                    }
+                    // retracted in the meantime
+                    Err(_) => value = rejected,
+                },
+                Err((_, rejected)) => {
+                    // handshake was cancelled
+                    unsafe { Common::release(self.into_common()) };
+                    return Err(rejected)
+                }
+            }
+        }
+    }
+
+    /// Deposits `value` for the peer, never panicking whatever the peer did.
+    pub fn push(self, value: T) -> PushOutcome<T, S> {
+        trace!(id = self.id(), state = ?self.state(), "handshake pushing");
+        // dropping the end cancels
+        #[cfg(feature = "chaos")]
+        if chaos::strike() { return PushOutcome::PeerGone(value) }
+        match self.common().put(value) {
+            Ok(()) => PushOutcome::Delivered(Receipt::new(self.into_common())),
+            // value present, pull instead
+            Err((common::SET, value)) => PushOutcome::Occupied(self, value),
+            Err((_, value)) => {
                 // handshake was cancelled
-                drop(unsafe { Box::from_raw(self.common.as_ptr()) });
-                std::mem::forget(self); // consumes `self`
-                Err(value)
-            } else {
-                Ok(Err((self, value)))
+                unsafe { Common::release(self.into_common()) };
+                PushOutcome::PeerGone(value)
+            }
+        }
+    }
+
+    /// Like [`push`](Self::push), but `init` builds the value right in the shared
+    /// slot, sparing large values a move through the stack. It proves the slot
+    /// initialized by returning it, e.g. `|slot| slot.write(value)`.
+    ///
+    /// Gives back the end and `init` without running it when the slot is taken,
+    /// see [`state`](Self::state) for whether the peer pushed or went away.
+    ///
+    /// # Panics
+    /// If `init` panics or returns anything but the slot it was handed, leaving
+    /// the exchange as it was.
+    pub fn push_with_uninit<F>(self, init: F) -> Result<Receipt<T>, (Self, F)>
+    where F: FnOnce(&mut std::mem::MaybeUninit<T>) -> &mut T {
+        match self.common().put_with(init) {
+            Ok(()) => Ok(Receipt::new(self.into_common())),
+            Err((_, init)) => Err((self, init)),
+        }
+    }
+
+    /// Deposits `value` for the peer, never panicking whatever the peer did.
+    ///
+    /// Gives back the end and value when the peer already pushed (pull instead),
+    /// or just the value when the peer is gone.
+    pub fn try_push(self, value: T) -> Result<Receipt<T>, PushError<T, S>> {
+        match self.push(value) {
+            PushOutcome::Delivered(receipt) => Ok(receipt),
+            PushOutcome::Occupied(handshake, value) => Err(PushError::AlreadySet(handshake, value)),
+            PushOutcome::PeerGone(value) => Err(PushError::PeerGone(value)),
+        }
+    }
+
+    /// Takes the value deposited by the peer, never panicking whatever the peer did.
+    pub fn pull_now(self) -> PullOutcome<T, S> {
+        trace!(id = self.id(), state = ?self.state(), "handshake pulling");
+        // dropping the end cancels
+        #[cfg(feature = "chaos")]
+        if chaos::strike() { return PullOutcome::PeerGone }
+        match self.common().take() {
+            Ok(value) => {
+                unsafe { Common::release(self.into_common()) };
+                PullOutcome::Delivered(value)
             }
-        } else {
-            std::mem::forget(self); // consumes `self`
-            Ok(Ok(()))
-        }
-    }
-
-    pub fn try_pull(self) -> Result<Result<T, Self>, Canceled> {
-        // access safe lock
-        if let Some(res) = unsafe { self.common.as_ref() }.get() {
-            // unique access if value present
-            if let Some(value) = unsafe { &mut*res.get() }.take() {
-                // last reference, drop pointer
-                drop(unsafe { Box::from_raw(self.common.as_ptr()) });
-                std::mem::forget(self); // consumes `self`
-                Ok(Ok(value))
-            } else {
+            Err(common::EMPTY) => PullOutcome::Pending(self),
+            Err(_) => {
                 // handshake was cancelled
-                drop(unsafe { Box::from_raw(self.common.as_ptr()) });
-                std::mem::forget(self); // consumes `self`
-                Err(Canceled)
+                unsafe { Common::release(self.into_common()) };
+                PullOutcome::PeerGone
+            }
+        }
+    }
+
+    /// Like [`pull_now`](Self::pull_now), but retries up to `spins` times with a
+    /// spin-loop hint in between, e.g. on a pinned busy-polling thread, never
+    /// parking or yielding to the scheduler.
+    pub fn try_pull_for_spins(self, spins: usize) -> PullOutcome<T, S> {
+        let mut end = self;
+        for _ in 0..spins {
+            match end.pull_now() {
+                PullOutcome::Pending(retry) => end = retry,
+                outcome => return outcome,
+            }
+            std::hint::spin_loop()
+        }
+        end.pull_now()
+    }
+
+    /// Takes the value deposited by the peer, never panicking whatever the peer did.
+    ///
+    /// Gives back the end when nothing was pushed yet.
+    pub fn try_pull(self) -> Result<T, PullError<T, S>> {
+        match self.pull_now() {
+            PullOutcome::Delivered(value) => Ok(value),
+            PullOutcome::Pending(handshake) => Err(PullError::Empty(handshake)),
+            PullOutcome::PeerGone => Err(PullError::PeerGone),
+        }
+    }
+
+    /// Identifies the exchange, the same from both ends, its receipts and watchers.
+    ///
+    /// Unique among live exchanges, though one made after this one is gone may get it again.
+    pub fn id(&self) -> u64 {
+        common::id(self.common)
+    }
+
+    // pulls, blocking until the peer pushed or went away
+    pub(crate) fn pull_blocking(self) -> Result<T, Canceled> {
+        let mut end = self;
+        loop {
+            end.common().block_while(|state| state == common::EMPTY);
+            match end.pull_now() {
+                PullOutcome::Delivered(value) => return Ok(value),
+                // retracted in the meantime
+                PullOutcome::Pending(retry) => end = retry,
+                PullOutcome::PeerGone => return Err(Canceled),
             }
+        }
+    }
 
-        } else {
-            Ok(Err(self))
+    /// Whether both ends belong to the same exchange.
+    pub fn same_channel(&self, other: &Self) -> bool {
+        self.common == other.common
+    }
+
+    // pulls, registering `cx` to be woken on the next change if nothing is there yet
+    pub(crate) fn poll_pull(self, cx: &Context<'_>) -> Result<Result<T, Self>, Canceled> {
+        match self.pull_now() {
+            PullOutcome::Pending(handshake) => {
+                handshake.common().register(cx);
+                // re-check after registering so a wake-up can't slip through
+                handshake.pull_now().into_result()
+            }
+            outcome => outcome.into_result(),
         }
     }
 
+    // probes below are single atomic loads, never contending with pushers
+
     pub fn is_set(&self) -> bool {
-        // access safe lock
-        unsafe { self.common.as_ref() }.get().is_some()
+        self.common().state() != common::EMPTY
+    }
+
+    /// Reads the value the peer deposited without collecting it, if there is one.
+    ///
+    /// Keep the view short lived, collecting the value waits for it to drop,
+    /// so the same thread must not touch the other end while holding it.
+    pub fn peek(&self) -> Option<Peek<'_, T>> {
+        self.common().peek()
+    }
+
+    /// Copies the value the peer deposited without collecting it, if there is one.
+    ///
+    /// Unlike [`peek`](Self::peek) the value is only held in place for the copy,
+    /// so the observer can't hold up the exchange for longer than that.
+    pub fn peek_copied(&self) -> Option<T> where T: Copy {
+        self.common().read()
+    }
+
+    /// Blocks until the peer pushed or went away, without consuming the end.
+    pub fn wait(&self) -> State {
+        State::of(self.common().block_while(|state| State::of(state) == State::Empty))
+    }
+
+    /// Like [`wait`](Self::wait), but gives up after `timeout`, returning [`State::Empty`] then.
+    pub fn wait_timeout(&self, timeout: Duration) -> State {
+        let deadline = Instant::now() + timeout;
+        self.common()
+            .block_until_deadline(deadline, || Some(self.state()).filter(|&state| state != State::Empty))
+            .unwrap_or(State::Empty)
+    }
+
+    /// Whether the peer went away without completing the exchange.
+    pub fn is_canceled(&self) -> bool {
+        self.state() == State::PeerGone
+    }
+
+    /// Whether the exchange can still complete, the peer not having gone away,
+    /// e.g. to skip building a value nobody will collect.
+    ///
+    /// Unlike [`is_set`](Self::is_set) this tells "not yet" from "never".
+    pub fn peer_alive(&self) -> bool {
+        !self.is_canceled()
+    }
+
+    pub fn state(&self) -> State {
+        State::of(self.common().state())
     }
 }
 
-impl<T> Drop for Handshake<T> {
+impl<T, S> Drop for Handshake<T, S> {
     fn drop(&mut self) {
-        let mut canceled = false;
-        // access safe lock
-        let _ = unsafe { self.common.as_ref() }.get_or_init(|| {
-            canceled = true;
-            UnsafeCell::new(None)
-        });
-        if canceled { return; }; // handshake cancelled
-        // otherwise last reference, drop pointer
-        drop(unsafe { Box::from_raw(self.common.as_ptr()) });
+        trace!(id = self.id(), state = ?self.state(), "handshake end dropped, canceling");
+        self.common().cancel();
+        // end owns a reference
+        unsafe { Common::release(self.common) }
+    }
+}
+
+impl<T, S> PartialEq for Handshake<T, S> {
+    fn eq(&self, other: &Self) -> bool {
+        self.same_channel(other)
+    }
+}
+
+impl<T, S> Eq for Handshake<T, S> {}
+
+impl<T, S> PartialOrd for Handshake<T, S> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T, S> Ord for Handshake<T, S> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.common.cmp(&other.common)
+    }
+}
+
+impl<T, S> Hash for Handshake<T, S> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.common.hash(state)
     }
 }
 
-unsafe impl<T: Send> Sync for Handshake<T> {}
+unsafe impl<T: Send> Sync for Handshake<T, SendSafe> {}
 
-unsafe impl<T: Send> Send for Handshake<T> {}
+unsafe impl<T: Send> Send for Handshake<T, SendSafe> {}
 
-impl<T: Debug> Debug for Handshake<T> {
+impl<T, S> Debug for Handshake<T, S> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        // access safe lock
-        f.debug_struct("Handshake").field("common", unsafe { self.common.as_ref() }).finish()
+        let mut debug = f.debug_struct("Handshake");
+        debug
+            .field("id", &format_args!("{:#x}", self.id()))
+            .field("state", &self.state())
+            .field("peer_alive", &self.peer_alive());
+        #[cfg(feature = "timestamps")]
+        debug.field("age", &self.created_at().map(|at| at.elapsed()));
+        debug.finish()
     }
 }
 
-#[cfg(test)]
+impl<T, S> std::fmt::Display for Handshake<T, S> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "handshake {:#x} ({:?})", self.id(), self.state())
+    }
+}
+
+#[cfg(all(test, not(loom)))]
 mod test {
-    use std::convert::identity;
+    use std::{future::Future, pin::{pin, Pin}, task::{Context, Poll}, time::Duration};
 
-    use crate::{Canceled, Handshake};
+    use crate::{cancel_all, common, drain_ready, exchange_between, exchange_iters, join_all, join_all_async, lend_between, race, ring_exchange, rpc, select_pull, select_pull_by_key, spawn_joined, zip, Aborted, CancelScope, Canceled, Delivery, Handshake, HandshakeCell, HandshakeGroup, HandshakeQueue, InflightTable, JoinOutcome, LocalHandshake, Pipeline, Poller, Promise, PullError, PullOrPush, PullOutcome, PushError, PushOutcome, RendezvousChannel, RendezvousMap, Select, State, Superseded, ValidatedPush};
+
+    fn block_on<F: Future>(future: F) -> F::Output {
+        let waker = common::thread_waker();
+        let mut cx = Context::from_waker(&waker);
+        let mut future = pin!(future);
+        loop {
+            if let Poll::Ready(res) = future.as_mut().poll(&mut cx) { return res; }
+            std::thread::park()
+        }
+    }
 
     #[test]
     fn drop_test() {
@@ -145,120 +1019,2931 @@ mod test {
 
         let mut dropped = false;
         let (u, v) = Handshake::<Loud>::new();
-        u.try_push(Loud { flag: &mut dropped }).unwrap().unwrap();
+        u.try_push(Loud { flag: &mut dropped }).unwrap();
         drop(v);
 
-        assert_eq!(dropped, true);
+        assert!(dropped);
     }
 
     #[test]
     fn pull_test() {
         let (u, v) = Handshake::<()>::new();
-        assert_eq!(u.try_pull(), Ok(Err(v)));
+        assert_eq!(u.try_pull(), Err(PullError::Empty(v)));
 
         let (u, v) = Handshake::<()>::new();
-        assert_eq!(v.try_pull(), Ok(Err(u)))
+        assert_eq!(v.try_pull(), Err(PullError::Empty(u)))
     }
 
     #[test]
     fn push_test() {
         let (u, v) = Handshake::<()>::new();
-        assert_eq!(u.try_push(()), Ok(Ok(())));
+        assert!(u.try_push(()).is_ok());
         drop(v);
 
         let (u, v) = Handshake::<()>::new();
-        assert_eq!(v.try_push(()), Ok(Ok(())));
+        assert!(v.try_push(()).is_ok());
         drop(u)
     }
 
     #[test]
-    fn double_push_test() {
+    fn receipt_test() {
         let (u, v) = Handshake::<()>::new();
-        u.try_push(()).unwrap().unwrap();
-        drop(v.try_push(()).unwrap().err().unwrap());
+        let receipt = u.try_push(()).unwrap();
+        assert_eq!(receipt.status(), Delivery::Pending);
+        v.try_pull().unwrap();
+        assert_eq!(receipt.status(), Delivery::Consumed);
+        assert_eq!(receipt.wait(), Ok(()));
 
         let (u, v) = Handshake::<()>::new();
-        v.try_push(()).unwrap().unwrap();
-        drop(u.try_push(()).unwrap().err().unwrap())
+        let receipt = u.try_push(()).unwrap();
+        drop(v);
+        assert_eq!(receipt.status(), Delivery::Lost);
+        assert_eq!(receipt.wait(), Err(Canceled));
     }
 
     #[test]
-    fn pull_cancel_test() {
-        let (u, v) = Handshake::<()>::new();
-        drop(u);
-        assert_eq!(v.try_pull(), Err(Canceled));
+    fn retract_test() {
+        let (u, v) = Handshake::<usize>::new();
+        let receipt = u.try_push(1).unwrap();
+        let (u, value) = receipt.retract().unwrap();
+        assert_eq!(value, 1);
+        assert_eq!(v.try_pull(), Err(PullError::Empty(u)));
 
-        let (u, v) = Handshake::<()>::new();
+        let (u, v) = Handshake::<usize>::new();
+        let receipt = u.try_push(1).unwrap();
+        v.try_pull().unwrap();
+        assert_eq!(receipt.retract().unwrap_err(), Delivery::Consumed);
+
+        let (u, v) = Handshake::<usize>::new();
+        let receipt = u.try_push(1).unwrap();
         drop(v);
-        assert_eq!(u.try_pull(), Err(Canceled));
+        assert_eq!(receipt.retract().unwrap_err(), Delivery::Lost);
     }
 
     #[test]
-    fn push_cancel_test() {
-        let (u, v) = Handshake::<()>::new();
-        drop(u);
-        assert_eq!(v.try_push(()), Err(()));
-
-        let (u, v) = Handshake::<()>::new();
+    fn retract_cancel_test() {
+        let (u, v) = Handshake::<usize>::new();
+        let (u, _) = u.try_push(1).unwrap().retract().unwrap();
         drop(v);
-        assert_eq!(u.try_push(()), Err(()));
+        assert_eq!(u.try_push(2).unwrap_err(), PushError::PeerGone(2));
     }
 
     #[test]
-    fn push_pull_test() {
-        let (u, v) = Handshake::<()>::new();
-        u.try_push(()).unwrap().unwrap();
-        v.try_pull().unwrap().unwrap();
-
-        let (u, v) = Handshake::<()>::new();
-        v.try_push(()).unwrap().unwrap();
-        u.try_pull().unwrap().unwrap()
+    fn receipt_wait_test() {
+        let (u, v) = Handshake::<usize>::new();
+        let receipt = u.try_push(1).unwrap();
+        let puller = std::thread::spawn(move || v.try_pull().unwrap());
+        assert_eq!(receipt.wait(), Ok(()));
+        assert_eq!(puller.join().unwrap(), 1)
     }
 
     #[test]
-    fn join_test() {
+    fn double_push_test() {
         let (u, v) = Handshake::<()>::new();
-        assert_eq!(u.join((), |_, _| ()).unwrap(), None);
-        assert_eq!(v.join((), |_, _| ()).unwrap(), Some(()));
+        u.try_push(()).unwrap();
+        drop(v.try_push(()).unwrap_err());
 
         let (u, v) = Handshake::<()>::new();
-        assert_eq!(v.join((), |_, _| ()).unwrap(), None);
-        assert_eq!(u.join((), |_, _| ()).unwrap(), Some(()))
+        v.try_push(()).unwrap();
+        let err = u.try_push(()).unwrap_err();
+        assert_eq!(err.to_string(), "handshake already set by the peer, pull instead");
+        let PushError::AlreadySet(u, ()) = err else { panic!("expected the end back") };
+        assert_eq!(u.try_pull(), Ok(()))
     }
 
     #[test]
-    // Due to the innefective `OnceLock` API and
-    // the requirement to keep `self` around for either `std::mem::forget(self)` or return
-    // there is a break in aliasing rules as a `&` is coexisting with a `&mut` (even though the `&` is not used).
-    // This means that these functions (join, try_push, try_pull) do not pass tests involving miri,
-    // however it would appear they are still perfectly safe.
-    fn collision_check() {
-        use rand::prelude::*;
-        const N: usize = 64;
+    fn try_push_with_test() {
+        let (u, v) = Handshake::<usize>::new();
+        drop(v);
+        assert_eq!(u.try_push_with(|| unreachable!()).unwrap_err(), Canceled);
+        let (u, v) = Handshake::<usize>::new();
+        v.try_push(1).unwrap();
+        let u = u.try_push_with(|| unreachable!()).unwrap().unwrap_err();
+        assert_eq!(u.try_pull().unwrap(), 1);
+        let (u, v) = Handshake::<usize>::new();
+        assert!(u.try_push_with(|| 2).unwrap().is_ok());
+        assert_eq!(v.try_pull().unwrap(), 2)
+    }
 
-        let mut left: Vec<Handshake<usize>> = vec![];
-        let mut right: Vec<Handshake<usize>> = vec![];
-        for _ in 0..N {
-            let (u, v) = Handshake::<usize>::new();
-            left.push(u);
-            right.push(v)
+    #[test]
+    fn peek_test() {
+        let (u, v) = Handshake::<String>::new();
+        assert!(v.peek().is_none());
+        let receipt = u.try_push("a".into()).unwrap();
+        assert_eq!(receipt.peek().as_deref().map(String::as_str), Some("a"));
+        assert_eq!(v.peek().map(|value| value.len()), Some(1));
+        assert_eq!(v.try_pull().unwrap(), "a");
+        assert!(receipt.peek().is_none())
+    }
+
+    #[test]
+    fn peek_copied_test() {
+        let (u, v) = Handshake::<[u64; 4]>::new();
+        let watcher = v.watcher();
+        assert_eq!(v.peek_copied(), None);
+        let mut receipt = u.try_push([0; 4]).unwrap();
+        let observer = std::thread::spawn(move || {
+            // never sees a half written value
+            for _ in 0..if cfg!(miri) { 100 } else { 10_000 } {
+                if let Some(value) = watcher.peek_copied() { assert!(value.iter().all(|&x| x == value[0])) }
+            }
+        });
+        let last = if cfg!(miri) { 10 } else { 1_000 };
+        for i in 1..last {
+            let (u, _) = receipt.retract().unwrap();
+            receipt = u.try_push([i; 4]).unwrap();
         }
-        let mut rng = rand::thread_rng();
-        left.shuffle(&mut rng);
-        right.shuffle(&mut rng);
-        let left_thread = std::thread::spawn(|| left
-            .into_iter()
-            .enumerate()
-            .map(|(n, u)| {u.join(n, |x, y| (x, y)).unwrap()})
-            .filter_map(identity).collect::<Vec<(usize, usize)>>()
-        );
-        let right_thread = std::thread::spawn(|| right
-            .into_iter()
-            .enumerate()
-            .map(|(n, v)| {v.join(n, |x, y| (x, y)).unwrap()})
-            .filter_map(identity).collect::<Vec<(usize, usize)>>()
-        );
-        let total = left_thread.join().unwrap().len() + right_thread.join().unwrap().len();
-        assert_eq!(total, N)
+        observer.join().unwrap();
+        assert_eq!(receipt.peek_copied(), Some([last - 1; 4]));
+        assert_eq!(v.try_pull(), Ok([last - 1; 4]));
+        assert_eq!(receipt.peek_copied(), None)
+    }
+
+    #[test]
+    fn watcher_test() {
+        let (u, v) = Handshake::<usize>::new();
+        let watcher = u.watcher();
+        let other = watcher.clone();
+        assert_eq!(watcher.id(), v.id());
+        assert!(!watcher.is_finished());
+        let waiting = std::thread::spawn(move || other.wait());
+        u.try_push(1).unwrap();
+        assert_eq!(watcher.state(), State::Set);
+        assert_eq!(v.try_pull().unwrap(), 1);
+        assert_eq!(waiting.join().unwrap(), State::TakenByPeer);
+        assert_eq!(block_on(watcher), State::TakenByPeer);
+
+        let (u, v) = Handshake::<usize>::new();
+        let watcher = v.watcher();
+        drop((u, v));
+        assert_eq!(watcher.wait(), State::PeerGone)
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn on_peer_push_test() {
+        use std::sync::mpsc;
+
+        let (u, v) = Handshake::<usize>::new();
+        let (tx, rx) = mpsc::channel();
+        v.on_peer_push(move |value| tx.send(*value).unwrap());
+        assert!(rx.try_recv().is_err());
+        u.try_push(1).unwrap();
+        assert_eq!(rx.recv().unwrap(), 1);
+        // still there to pull
+        assert_eq!(v.try_pull().unwrap(), 1);
+
+        let (u, v) = Handshake::<usize>::new();
+        v.on_peer_push(|_| unreachable!());
+        drop(u);
+        assert_eq!(v.try_pull().unwrap_err(), PullError::PeerGone)
+    }
+
+    #[test]
+    #[cfg(feature = "deadlock-detection")]
+    #[should_panic(expected = "held by this same thread")]
+    fn deadlock_test() {
+        let (u, _v) = Handshake::<usize, crate::Local>::new_local();
+        // the peer end can't have left, nobody will ever push
+        let _ = u.pull_blocking();
+    }
+
+    #[test]
+    #[cfg(feature = "deadlock-detection")]
+    fn deadlock_cycle_test() {
+        use std::panic::{catch_unwind, AssertUnwindSafe};
+
+        // both ends pulling, whichever blocks second closes the cycle and panics,
+        // canceling the other
+        let (u, v) = Handshake::<usize>::new();
+        let other = std::thread::spawn(move || catch_unwind(AssertUnwindSafe(|| u.pull_blocking())));
+        let here = catch_unwind(AssertUnwindSafe(|| v.pull_blocking()));
+        let other = other.join().unwrap();
+        assert!(matches!((here, other), (Err(_), Ok(Err(Canceled))) | (Ok(Err(Canceled)), Err(_))));
+
+        // an end handed to another thread isn't taken for held here
+        let (u, v) = Handshake::<usize>::new();
+        let receipt = u.try_push(1).unwrap();
+        let puller = std::thread::spawn(move || v.try_pull());
+        assert_eq!(receipt.wait(), Ok(()));
+        assert_eq!(puller.join().unwrap(), Ok(1))
+    }
+
+    #[test]
+    #[cfg(feature = "deadlock-detection")]
+    #[should_panic(expected = "most likely a deadlock")]
+    fn deadlock_timeout_test() {
+        crate::deadlock::set_timeout(std::time::Duration::from_millis(50));
+        let (u, _v) = Handshake::<usize>::new();
+        // the peer end stays right here, which only the opt-in timeout notices
+        u.try_push(1).unwrap().wait().unwrap()
+    }
+
+    #[test]
+    #[cfg(feature = "metrics")]
+    fn metrics_test() {
+        // other tests run alongside, only ever adding to the totals
+        let before = crate::metrics::snapshot();
+        let (u, v) = Handshake::<usize>::new();
+        u.try_push(1).unwrap();
+        v.try_pull().unwrap();
+        let (u, v) = Handshake::<usize>::new();
+        drop((u, v));
+        let (u, v) = Handshake::<usize>::new();
+        u.try_push(1).unwrap();
+        drop(v);
+        let after = crate::metrics::snapshot();
+        assert!(after.created >= before.created + 3);
+        assert!(after.completed > before.completed);
+        assert!(after.canceled > before.canceled);
+        assert!(after.lost > before.lost)
+    }
+
+    #[test]
+    #[cfg(feature = "tracing")]
+    fn tracing_test() {
+        use std::sync::{Arc, Mutex};
+
+        use tracing::{span, subscriber, Event, Metadata, Subscriber};
+
+        // records the message of every event
+        struct Recorder(Arc<Mutex<Vec<String>>>);
+
+        impl Subscriber for Recorder {
+            fn enabled(&self, _: &Metadata<'_>) -> bool { true }
+            fn new_span(&self, _: &span::Attributes<'_>) -> span::Id { span::Id::from_u64(1) }
+            fn record(&self, _: &span::Id, _: &span::Record<'_>) {}
+            fn record_follows_from(&self, _: &span::Id, _: &span::Id) {}
+            fn event(&self, event: &Event<'_>) {
+                let mut message = String::new();
+                struct Message<'a>(&'a mut String);
+                impl tracing::field::Visit for Message<'_> {
+                    fn record_debug(&mut self, field: &tracing::field::Field, value: &dyn std::fmt::Debug) {
+                        if field.name() == "message" { *self.0 = format!("{value:?}") }
+                    }
+                }
+                event.record(&mut Message(&mut message));
+                self.0.lock().unwrap().push(message);
+            }
+            fn enter(&self, _: &span::Id) {}
+            fn exit(&self, _: &span::Id) {}
+        }
+
+        let events = Arc::new(Mutex::new(Vec::new()));
+        subscriber::with_default(Recorder(events.clone()), || {
+            let (u, v) = Handshake::<usize>::new();
+            u.try_push(1).unwrap();
+            drop(v);
+        });
+        let events = events.lock().unwrap();
+        for message in ["handshake created", "handshake pushing", "handshake end dropped, canceling"] {
+            assert!(events.iter().any(|event| event == message), "missing {message}")
+        }
+    }
+
+    #[test]
+    fn sender_receiver_test() {
+        use std::sync::mpsc::{RecvError, SendError, TryRecvError};
+
+        let (u, v) = Handshake::<usize>::new();
+        let (mut tx, mut rx) = u.into_sender_receiver(v);
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Empty));
+        assert_eq!(tx.send(1), Ok(()));
+        assert_eq!(tx.send(2), Err(SendError(2)));
+        assert_eq!(rx.recv(), Ok(1));
+        assert_eq!(rx.recv(), Err(RecvError));
+
+        let (u, v) = Handshake::<usize>::new();
+        let (tx, mut rx) = u.into_sender_receiver(v);
+        drop(tx);
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Disconnected))
+    }
+
+    #[test]
+    #[cfg(feature = "rayon")]
+    fn rayon_test() {
+        // a single worker runs both tasks one after the other
+        let pool = ::rayon::ThreadPoolBuilder::new().num_threads(1).build().unwrap();
+        let mut sums = Vec::new();
+        pool.scope(|s| {
+            let sums = &mut sums;
+            crate::rayon::exchange(s, || 1, || 2, move |a, b| sums.push((a, b)))
+        });
+        assert_eq!(sums, [(1, 2)])
+    }
+
+    #[test]
+    #[cfg(feature = "crossbeam")]
+    fn crossbeam_test() {
+        let (other_tx, other_rx) = crossbeam_channel::unbounded::<usize>();
+        let (u, v) = Handshake::<usize>::new();
+        let rx = v.into_crossbeam_receiver();
+        let mut select = crossbeam_channel::Select::new();
+        select.recv(&other_rx);
+        let index = select.recv(&rx);
+        let pusher = std::thread::spawn(move || { u.try_push(1).unwrap(); });
+        let op = select.select();
+        assert_eq!(op.index(), index);
+        assert_eq!(op.recv(&rx), Ok(1));
+        pusher.join().unwrap();
+        drop(other_tx);
+
+        let (u, v) = Handshake::<usize>::new();
+        let rx = v.into_crossbeam_receiver();
+        drop(u);
+        assert!(rx.recv().is_err())
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn snapshot_test() {
+        use crate::Snapshot;
+
+        let (u, v) = Handshake::<String>::new();
+        assert_eq!(v.snapshot(), Snapshot::Empty);
+        let _receipt = u.try_push("checkpoint".to_string()).unwrap();
+        let json = serde_json::to_string(&v.snapshot()).unwrap();
+        drop(v);
+
+        let snapshot: Snapshot<String> = serde_json::from_str(&json).unwrap();
+        let (pusher, puller) = snapshot.restore();
+        assert!(pusher.is_none());
+        assert_eq!(puller.try_pull().unwrap(), "checkpoint");
+
+        let (pusher, puller) = Snapshot::<String>::Empty.restore();
+        pusher.unwrap().try_push("again".to_string()).unwrap();
+        assert_eq!(puller.try_pull().unwrap(), "again");
+
+        let (u, v) = Handshake::<String>::new();
+        drop(u);
+        let (_, puller) = v.snapshot().restore();
+        assert_eq!(puller.try_pull(), Err(PullError::PeerGone));
+    }
+
+    #[test]
+    fn with_value_test() {
+        let (receipt, v) = Handshake::with_value(7);
+        assert_eq!(v.state(), State::Set);
+        assert_eq!(receipt.status(), Delivery::Pending);
+        assert_eq!(v.try_pull().unwrap(), 7);
+        assert_eq!(receipt.status(), Delivery::Consumed);
+
+        let (receipt, v) = Handshake::with_value(String::from("dropped"));
+        drop(v);
+        assert_eq!(receipt.wait(), Err(Canceled));
+    }
+
+    #[test]
+    fn pipeline_test() {
+        let (mut a, mut b) = Pipeline::<usize>::new();
+        let other = std::thread::spawn(move || (0..4).map(|i| b.exchange(i * 10).unwrap()).collect::<Vec<_>>());
+        let ours: Vec<_> = (0..4).map(|i| a.exchange(i).unwrap()).collect();
+        assert_eq!(ours, [0, 10, 20, 30]);
+        assert_eq!(other.join().unwrap(), [0, 1, 2, 3]);
+        assert_eq!(a.rounds(), 4);
+        // the peer is gone along with its thread
+        assert_eq!(a.exchange(4), Err(Canceled))
+    }
+
+    #[test]
+    #[cfg(feature = "futures")]
+    fn oneshot_test() {
+        use futures_channel::oneshot;
+
+        let (u, v) = Handshake::<usize>::new();
+        let rx = v.into_oneshot_receiver();
+        u.try_push(1).unwrap();
+        assert_eq!(block_on(rx), Ok(1));
+
+        let (u, v) = Handshake::<usize>::new();
+        u.into_oneshot_sender().send(2).unwrap();
+        assert_eq!(v.try_pull().unwrap(), 2);
+
+        let (tx, rx) = oneshot::channel::<usize>();
+        let v = Handshake::from(rx);
+        drop(tx);
+        assert_eq!(v.try_pull().unwrap_err(), PullError::PeerGone);
+
+        let (tx, rx) = oneshot::channel::<usize>();
+        Handshake::from(tx).try_push(3).unwrap();
+        assert_eq!(block_on(rx), Ok(3))
+    }
+
+    #[test]
+    #[cfg(feature = "futures")]
+    fn cell_stream_test() {
+        use futures_core::Stream;
+
+        let (mut u, mut v) = HandshakeCell::<usize>::new();
+        let producer = std::thread::spawn(move || {
+            for n in 0..3 { while u.try_push(n).unwrap().is_err() { std::thread::yield_now() } }
+        });
+        let mut next = || block_on(std::future::poll_fn(|cx| pin!(&mut v).poll_next(cx)));
+        assert_eq!([next(), next(), next()], [Some(0), Some(1), Some(2)]);
+        producer.join().unwrap();
+        assert_eq!(next(), None)
+    }
+
+    #[test]
+    #[cfg(feature = "futures")]
+    fn cell_sink_test() {
+        use futures_sink::Sink;
+
+        let (mut u, mut v) = HandshakeCell::<usize>::new();
+        let consumer = std::thread::spawn(move || {
+            let mut values = Vec::new();
+            while values.len() < 3 {
+                if let Some(value) = v.try_pull().unwrap() { values.push(value) } else { std::thread::yield_now() }
+            }
+            values
+        });
+        for n in 0..3 {
+            block_on(std::future::poll_fn(|cx| pin!(&mut u).poll_ready(cx))).unwrap();
+            pin!(&mut u).start_send(n).unwrap();
+        }
+        block_on(std::future::poll_fn(|cx| pin!(&mut u).poll_flush(cx))).unwrap();
+        assert_eq!(consumer.join().unwrap(), [0, 1, 2]);
+        assert_eq!(block_on(std::future::poll_fn(|cx| pin!(&mut u).poll_ready(cx))), Err(Canceled))
+    }
+
+    #[test]
+    #[cfg(feature = "tokio-util")]
+    fn token_test() {
+        use tokio_util::sync::CancellationToken;
+
+        let token = CancellationToken::new();
+        let (u, v) = Handshake::<usize>::new();
+        let _u = u.linked_to(token.clone());
+        let puller = std::thread::spawn(move || block_on(v.pull()));
+        token.cancel();
+        assert_eq!(puller.join().unwrap(), Err(Canceled));
+
+        let token = CancellationToken::new();
+        let (u, v) = Handshake::<usize>::new();
+        let receipt = u.linked_to(token.clone()).try_push(1).unwrap();
+        assert_eq!(v.try_pull().unwrap(), 1);
+        token.cancel();
+        assert_eq!(receipt.status(), Delivery::Consumed)
+    }
+
+    #[test]
+    fn rpc_test() {
+        let (caller, callee) = rpc::<usize, String>();
+        let server = std::thread::spawn(move || {
+            let (request, reply) = callee.accept().unwrap();
+            reply.respond(request.to_string()).unwrap()
+        });
+        assert_eq!(caller.call(1), Ok("1".to_string()));
+        server.join().unwrap();
+
+        let (caller, callee) = rpc::<usize, String>();
+        let answer = caller.send(2).unwrap();
+        let (_, reply) = callee.try_accept().unwrap().unwrap();
+        // dropping the reply leaves the caller canceled
+        drop(reply);
+        assert_eq!(block_on(answer), Err(Canceled))
+    }
+
+    #[test]
+    fn state_test() {
+        let (u, v) = Handshake::<()>::new();
+        assert_eq!(u.state(), State::Empty);
+        let (v, _) = v.try_push(()).unwrap().retract().unwrap();
+        assert_eq!(u.state(), State::Empty);
+        let receipt = v.try_push(()).unwrap();
+        assert_eq!(u.state(), State::Set);
+        assert_eq!(receipt.state(), State::Set);
+        u.try_pull().unwrap();
+        assert_eq!(receipt.state(), State::TakenByPeer);
+
+        let (u, v) = Handshake::<()>::new();
+        let watcher = u.watcher();
+        assert!(!u.is_canceled() && u.peer_alive() && !watcher.is_canceled());
+        drop(v);
+        assert_eq!(u.state(), State::PeerGone);
+        assert!(u.is_canceled() && !u.peer_alive() && watcher.is_canceled());
+    }
+
+    #[test]
+    fn identity_test() {
+        use std::collections::HashSet;
+
+        // payload needs neither `Eq` nor `Hash`
+        struct Opaque;
+
+        let (u, v) = Handshake::<Opaque>::new();
+        let (x, y) = Handshake::<Opaque>::new();
+        assert!(u.same_channel(&v));
+        assert!(!u.same_channel(&x));
+        assert_eq!(u, v);
+        assert_ne!(v, y);
+
+        let set: HashSet<_> = [u, v, x, y].into_iter().collect();
+        assert_eq!(set.len(), 2);
+    }
+
+    #[test]
+    fn pull_cancel_test() {
+        let (u, v) = Handshake::<()>::new();
+        drop(u);
+        assert_eq!(v.try_pull(), Err(PullError::PeerGone));
+
+        let (u, v) = Handshake::<()>::new();
+        drop(v);
+        assert_eq!(u.try_pull(), Err(PullError::PeerGone));
+    }
+
+    #[test]
+    fn push_cancel_test() {
+        let (u, v) = Handshake::<()>::new();
+        drop(u);
+        assert!(matches!(v.try_push(()), Err(PushError::PeerGone(()))));
+
+        let (u, v) = Handshake::<()>::new();
+        drop(v);
+        assert!(matches!(u.try_push(()), Err(PushError::PeerGone(()))));
+    }
+
+    #[test]
+    fn unusual_sequence_test() {
+        // pulling and pushing from the same end after giving up
+        let (u, v) = Handshake::<()>::new();
+        let u = u.try_pull().unwrap_err().into_handshake().unwrap();
+        let (u, ()) = u.try_push(()).unwrap().retract().unwrap();
+        let u = u.try_pull().unwrap_err().into_handshake().unwrap();
+        drop(v);
+        assert_eq!(u.try_pull(), Err(PullError::PeerGone));
+
+        // joining over a retracted value
+        let (u, v) = Handshake::<()>::new();
+        let (u, ()) = u.try_push(()).unwrap().retract().unwrap();
+        assert_eq!(v.join((), |_, _| ()), Ok(None));
+        assert_eq!(u.join((), |_, _| ()), Ok(Some(())));
+
+        // receipts outliving both ends
+        let (u, v) = Handshake::<()>::new();
+        let receipt = u.try_push(()).unwrap();
+        assert_eq!(v.join((), |_, _| ()), Ok(Some(())));
+        assert_eq!(receipt.wait(), Ok(()));
+    }
+
+    #[test]
+    fn push_pull_test() {
+        let (u, v) = Handshake::<()>::new();
+        u.try_push(()).unwrap();
+        v.try_pull().unwrap();
+
+        let (u, v) = Handshake::<()>::new();
+        v.try_push(()).unwrap();
+        u.try_pull().unwrap()
+    }
+
+    #[test]
+    fn join_test() {
+        let (u, v) = Handshake::<()>::new();
+        assert_eq!(u.join((), |_, _| ()).unwrap(), None);
+        assert_eq!(v.join((), |_, _| ()).unwrap(), Some(()));
+
+        let (u, v) = Handshake::<()>::new();
+        assert_eq!(v.join((), |_, _| ()).unwrap(), None);
+        assert_eq!(u.join((), |_, _| ()).unwrap(), Some(()));
+
+        // the first value is kept for the second to combine with
+        let (u, v) = Handshake::new();
+        assert_eq!(u.join(1, |x, y| x * 10 + y).unwrap(), None);
+        assert_eq!(v.join(2, |x, y| x * 10 + y).unwrap(), Some(12))
+    }
+
+    #[test]
+    fn any_test() {
+        use crate::AnyHandshake;
+
+        let (u, v) = AnyHandshake::new();
+        u.try_push_any(String::from("plugin")).unwrap().unwrap();
+        assert_eq!(v.try_pull_downcast::<String>().unwrap().unwrap().unwrap(), "plugin");
+
+        let (u, v) = AnyHandshake::new();
+        u.try_push_any(1u8).unwrap().unwrap();
+        let other = v.try_pull_downcast::<String>().unwrap().unwrap().unwrap_err();
+        assert_eq!(other.downcast_ref::<u8>(), Some(&1));
+
+        let (u, v) = Handshake::<Box<dyn FnOnce() -> usize + Send>>::new();
+        assert!(u.try_push(Box::new(|| 2)).is_ok());
+        assert_eq!((v.try_pull().unwrap())(), 2)
+    }
+
+    #[test]
+    fn on_lost_test() {
+        let lost = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let (u, v) = Handshake::new();
+        let sink = lost.clone();
+        u.try_push(String::from("orphan")).unwrap().on_lost(move |value| sink.lock().unwrap().push(value));
+        assert!(lost.lock().unwrap().is_empty());
+        drop(v);
+        assert_eq!(*lost.lock().unwrap(), ["orphan"]);
+
+        // already orphaned
+        let (u, v) = Handshake::new();
+        let receipt = u.try_push(String::from("late")).unwrap();
+        drop(v);
+        assert_eq!(receipt.status(), Delivery::Lost);
+        let sink = lost.clone();
+        receipt.on_lost(move |value| sink.lock().unwrap().push(value));
+        assert_eq!(*lost.lock().unwrap(), ["orphan", "late"]);
+
+        let (u, v) = Handshake::new();
+        let sink = lost.clone();
+        u.try_push(String::from("pulled")).unwrap().on_lost(move |value| sink.lock().unwrap().push(value));
+        assert_eq!(v.try_pull().unwrap(), "pulled");
+        assert_eq!(lost.lock().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn broadcast_test() {
+        let (u, v) = Handshake::<String>::new();
+        let broadcast = v.into_broadcast();
+        assert_eq!(broadcast.try_pull(), None);
+        let consumers: Vec<_> = (0..4).map(|_| {
+            let broadcast = broadcast.clone();
+            std::thread::spawn(move || broadcast.wait())
+        }).collect();
+        u.try_push("news".to_string()).unwrap();
+        for consumer in consumers { assert_eq!(consumer.join().unwrap().unwrap(), "news") }
+        assert_eq!(broadcast.try_pull(), Some(Ok("news".to_string())));
+
+        let (u, v) = Handshake::<String>::new();
+        let broadcast = v.into_broadcast();
+        drop(u);
+        assert_eq!(broadcast.clone().wait(), Err(Canceled));
+        assert_eq!(broadcast.try_pull(), Some(Err(Canceled)))
+    }
+
+    #[test]
+    fn shared_pusher_test() {
+        let (u, v) = Handshake::<usize>::new();
+        let pusher = u.into_shared_pusher();
+        let workers: Vec<_> = (0..4).map(|n| {
+            let pusher = pusher.clone();
+            std::thread::spawn(move || pusher.try_push(n).unwrap().map(drop).map_err(|superseded| superseded.0))
+        }).collect();
+        let results: Vec<_> = workers.into_iter().map(|worker| worker.join().unwrap()).collect();
+        let winner = v.try_pull().unwrap();
+        assert_eq!(results.iter().filter(|res| res.is_ok()).count(), 1);
+        assert!(results[winner].is_ok());
+        assert!(pusher.is_done());
+        assert_eq!(pusher.try_push(9).unwrap().unwrap_err(), Superseded(9));
+
+        let (u, v) = Handshake::<usize>::new();
+        let pusher = u.into_shared_pusher();
+        drop(v);
+        assert!(pusher.is_done());
+        assert_eq!(pusher.try_push(1).unwrap_err(), 1)
+    }
+
+    #[test]
+    fn slot_test() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use crate::slot::{DefaultSlot, RawSlot, SlotHandshake};
+
+        #[derive(Default)]
+        struct Counting {
+            inner: DefaultSlot<usize>,
+            sets: AtomicUsize,
+        }
+
+        impl RawSlot<usize> for Counting {
+            fn try_set(&self, value: usize) -> Result<(), (State, usize)> {
+                self.sets.fetch_add(1, Ordering::Relaxed);
+                self.inner.try_set(value)
+            }
+            fn try_take(&self) -> Result<usize, State> { self.inner.try_take() }
+            fn cancel(&self) { self.inner.cancel() }
+            fn state(&self) -> State { self.inner.state() }
+        }
+
+        let (u, v) = SlotHandshake::new(Counting::default());
+        let v = v.try_pull().unwrap().unwrap_err();
+        v.try_push(1).unwrap().unwrap();
+        let (u, rejected) = u.try_push(2).unwrap().unwrap_err();
+        assert_eq!((rejected, u.slot().sets.load(Ordering::Relaxed)), (2, 2));
+        assert_eq!(u.try_pull().unwrap().unwrap(), 1);
+
+        let (u, v) = SlotHandshake::new(DefaultSlot::<usize>::new());
+        drop(u);
+        assert_eq!(v.state(), State::PeerGone);
+        assert_eq!(v.try_pull().unwrap_err(), Canceled);
+
+        // on its own
+        let slot = std::sync::Arc::new(DefaultSlot::new());
+        assert!(slot.try_set(1).is_ok());
+        assert_eq!(slot.retract(), Ok(1));
+        assert_eq!(slot.retract(), Err(State::Empty));
+        let setter = std::thread::spawn({ let slot = slot.clone(); move || slot.try_set(2) });
+        assert_eq!(slot.wait_while(|state| state == State::Empty), State::Set);
+        assert!(setter.join().unwrap().is_ok());
+        assert_eq!(slot.try_take(), Ok(2))
+    }
+
+    #[test]
+    fn signal_test() {
+        use crate::SignalHandshake;
+
+        static SLOT: SignalHandshake<usize> = SignalHandshake::new();
+        assert_eq!(SLOT.try_pull(), None);
+        assert_eq!(SLOT.try_push(1), Ok(()));
+        assert_eq!(SLOT.try_push(2), Err(2));
+        assert!(SLOT.is_set());
+        assert_eq!(SLOT.try_pull(), Some(1));
+        assert_eq!(SLOT.try_push(3), Ok(()));
+        assert_eq!(SLOT.try_pull(), Some(3));
+
+        let slot = SignalHandshake::new();
+        slot.try_push(String::from("dropped with the slot")).unwrap()
+    }
+
+    #[test]
+    fn realtime_test() {
+        use crate::{sync, SignalHandshake};
+
+        // allocations are counted in tests/realtime.rs, with its own allocator
+        let slot = SignalHandshake::new();
+        let before = sync::locks();
+        for i in 0..64 {
+            slot.try_push(i).unwrap();
+            assert_eq!(slot.try_pull(), Some(i))
+        }
+        assert_eq!(sync::locks(), before);
+        // the general path does lock, to wake
+        let (u, v) = Handshake::new();
+        drop(u.try_push(1));
+        assert_eq!(v.try_pull(), Ok(1));
+        assert!(sync::locks() > before)
+    }
+
+    #[test]
+    fn static_test() {
+        use crate::StaticHandshake;
+
+        static SLOT: StaticHandshake<usize> = StaticHandshake::new();
+        let (u, v) = SLOT.split().unwrap();
+        assert!(SLOT.split().is_none());
+        let receipt = u.try_push(1).unwrap();
+        assert_eq!(v.try_pull().unwrap(), 1);
+        assert_eq!(receipt.status(), Delivery::Consumed);
+        drop(receipt);
+        assert_eq!(SLOT.state(), State::TakenByPeer)
+    }
+
+    #[cfg(feature = "embassy")]
+    #[test]
+    fn isr_test() {
+        use crate::StaticHandshake;
+
+        static SLOT: StaticHandshake<usize> = StaticHandshake::new();
+        let (isr, main) = SLOT.split().unwrap();
+        let waker = common::thread_waker();
+        let mut pull = pin!(main.pull());
+        assert!(pull.as_mut().poll(&mut Context::from_waker(&waker)).is_pending());
+        assert!(matches!(isr.try_push_isr(1), PushOutcome::Delivered(_)));
+        assert_eq!(block_on(pull), Ok(1));
+
+        // midway through a peek, as if interrupted
+        let (u, v) = Handshake::new();
+        let PushOutcome::Delivered(receipt) = u.push(2) else { panic!() };
+        let peek = receipt.peek().unwrap();
+        let PullOutcome::Pending(v) = v.try_pull_isr() else { panic!("waited") };
+        drop(peek);
+        assert!(matches!(v.try_pull_isr(), PullOutcome::Delivered(2)))
+    }
+
+    #[test]
+    fn try_push_validated_test() {
+        let validate = |value: &i32| if *value >= 0 { Ok(()) } else { Err("negative") };
+        let (u, v) = Handshake::new();
+        let ValidatedPush::Rejected(u, value, error) = u.try_push_validated(-1, validate) else { panic!("accepted") };
+        assert_eq!((value, error, v.state()), (-1, "negative", State::Empty));
+        assert!(matches!(u.try_push_validated(1, validate), ValidatedPush::Pushed(_)));
+        assert_eq!(v.try_pull().unwrap(), 1);
+
+        let (u, v) = Handshake::new();
+        drop(v);
+        assert!(matches!(u.try_push_validated(1, validate), ValidatedPush::PeerGone(1)))
+    }
+
+    #[test]
+    fn try_pull_or_push_test() {
+        let (u, v) = Handshake::new();
+        let PullOrPush::Pushed(receipt) = u.try_pull_or_push(1).unwrap() else { panic!("nothing to pull") };
+        let PullOrPush::Pulled(other, own) = v.try_pull_or_push(2).unwrap() else { panic!("value to pull") };
+        assert_eq!((other, own, receipt.status()), (1, 2, Delivery::Consumed));
+
+        let (u, v) = Handshake::new();
+        drop(v);
+        assert_eq!(u.try_pull_or_push(3).unwrap_err(), 3)
+    }
+
+    #[test]
+    fn expiring_test() {
+        use crate::expiring::{ExpiringHandshake, PullError};
+
+        let (u, v) = ExpiringHandshake::new(Duration::from_secs(60));
+        u.try_push(1).unwrap().unwrap();
+        assert_eq!(v.try_pull().unwrap().unwrap(), 1);
+
+        let (u, v) = ExpiringHandshake::new(Duration::ZERO);
+        u.try_push(String::from("stale")).unwrap().unwrap();
+        assert_eq!(v.try_pull().unwrap_err(), PullError::Expired);
+
+        let (u, v) = ExpiringHandshake::<usize>::new(Duration::ZERO);
+        let v = v.try_pull().unwrap().unwrap_err();
+        drop(u);
+        assert_eq!(v.try_pull().unwrap_err(), PullError::Canceled)
+    }
+
+    // the wait strategy is process-wide, tests changing it take turns
+    static STRATEGY: std::sync::Mutex<()> = std::sync::Mutex::new(());
+
+    // runs `f` with blocked threads spinning, so they see every state the exchange goes through
+    fn spinning(f: impl FnOnce()) {
+        use crate::wait::{self, WaitStrategy};
+
+        let _turn = STRATEGY.lock().unwrap_or_else(std::sync::PoisonError::into_inner);
+        wait::set_wait_strategy(WaitStrategy::Spin);
+        let res = std::panic::catch_unwind(std::panic::AssertUnwindSafe(f));
+        wait::set_wait_strategy(WaitStrategy::Park);
+        if let Err(panic) = res { std::panic::resume_unwind(panic) }
+    }
+
+    #[test]
+    fn wait_strategy_test() {
+        use crate::wait::{self, WaitStrategy};
+
+        let _turn = STRATEGY.lock().unwrap_or_else(std::sync::PoisonError::into_inner);
+        for strategy in [WaitStrategy::Spin, WaitStrategy::SpinThenPark, WaitStrategy::Park] {
+            wait::set_wait_strategy(strategy);
+            assert_eq!(wait::wait_strategy(), strategy);
+            let (u, v) = Handshake::new();
+            let other = std::thread::spawn(move || v.meet(2));
+            assert_eq!(u.meet(1), Ok(2));
+            assert_eq!(other.join().unwrap(), Ok(1));
+        }
+    }
+
+    #[test]
+    fn meet_test() {
+        let (u, v) = Handshake::new();
+        let other = std::thread::spawn(move || v.meet("from v"));
+        assert_eq!(u.meet("from u"), Ok("from v"));
+        assert_eq!(other.join().unwrap(), Ok("from u"));
+
+        let (u, v) = Handshake::new();
+        drop(v);
+        assert_eq!(u.meet(1), Err(Canceled));
+
+        // waiting on through a peek at the value, seen by a spinning waiter
+        spinning(|| {
+            let (u, v) = Handshake::new();
+            let other = std::thread::spawn(move || u.meet(1));
+            while !v.is_set() { std::thread::yield_now() }
+            let peek = v.peek().unwrap();
+            std::thread::sleep(Duration::from_millis(10));
+            drop(peek);
+            assert_eq!(v.meet(2), Ok(1));
+            assert_eq!(other.join().unwrap(), Ok(2));
+        })
+    }
+
+    #[test]
+    fn join_combining_test() {
+        for _ in 0..16 {
+            let (u, v) = Handshake::new();
+            let other = std::thread::spawn(move || u.join(1, |x, y| x * 10 + y));
+            assert_eq!(v.join_combining(2, |x, y| x * 10 + y), Ok(12));
+            assert_eq!(other.join().unwrap(), Ok(None));
+        }
+        let (u, v) = Handshake::<usize>::new();
+        drop(u);
+        assert_eq!(v.join_combining(2, |x, y| x + y), Err(Canceled))
+    }
+
+    #[test]
+    fn join_timeout_test() {
+        let timeout = Duration::from_millis(10);
+        let (u, v) = Handshake::new();
+        let (u, value) = u.join_timeout(1, |x, y| x + y, timeout).unwrap().unwrap_err();
+        assert_eq!((u.state(), v.state(), value), (State::Empty, State::Empty, 1));
+
+        let joiner = std::thread::spawn(move || u.join_timeout(1, |x, y| x * 10 + y, Duration::from_secs(60)));
+        while !v.is_set() { std::thread::yield_now() }
+        assert_eq!(v.join(2, |x, y| x * 10 + y), Ok(Some(12)));
+        assert_eq!(joiner.join().unwrap(), Ok(Ok(None)));
+
+        let (u, v) = Handshake::new();
+        assert_eq!(u.join(1, |x, y| x * 10 + y), Ok(None));
+        assert_eq!(v.join_timeout(2, |x, y| x * 10 + y, timeout), Ok(Ok(Some(12))));
+
+        let (u, v) = Handshake::<usize>::new();
+        drop(u);
+        assert_eq!(v.join_timeout(2, |x, y| x + y, timeout), Err(Canceled));
+
+        // waiting on through a peek at the value, seen by a spinning waiter
+        spinning(|| {
+            let (u, v) = Handshake::new();
+            let joiner = std::thread::spawn(move || u.join_timeout(1, |x, y| x * 10 + y, Duration::from_secs(60)));
+            while !v.is_set() { std::thread::yield_now() }
+            let peek = v.peek().unwrap();
+            std::thread::sleep(Duration::from_millis(10));
+            drop(peek);
+            assert_eq!(v.join(2, |x, y| x * 10 + y), Ok(Some(12)));
+            assert_eq!(joiner.join().unwrap(), Ok(Ok(None)));
+        })
+    }
+
+    #[test]
+    fn wait_test() {
+        let (u, v) = Handshake::<usize>::new();
+        assert_eq!(v.wait_timeout(Duration::from_millis(10)), State::Empty);
+        let pusher = std::thread::spawn(move || { u.try_push(1).unwrap(); });
+        assert_eq!(v.wait(), State::Set);
+        pusher.join().unwrap();
+        assert_eq!(v.wait_timeout(Duration::from_millis(10)), State::Set);
+        assert_eq!(v.try_pull().unwrap(), 1);
+
+        let (u, v) = Handshake::<usize>::new();
+        let dropper = std::thread::spawn(move || drop(u));
+        assert_eq!(v.wait(), State::PeerGone);
+        dropper.join().unwrap()
+    }
+
+    #[test]
+    fn join_with_context_test() {
+        use crate::{JoinContext, Side};
+
+        let combine = |left: &str, right: &str, context: JoinContext| format!("{left}-{right} by {:?}", context.second);
+        let (u, v) = Handshake::new();
+        assert_eq!(u.join_with_context(Side::Left, "l", combine), Ok(None));
+        assert_eq!(v.join_with_context(Side::Right, "r", combine), Ok(Some("l-r by Right".to_string())));
+
+        let (u, v) = Handshake::new();
+        assert_eq!(v.join_with_context(Side::Right, "r", combine), Ok(None));
+        assert_eq!(u.join_with_context(Side::Left, "l", combine), Ok(Some("l-r by Left".to_string())));
+        assert_eq!(Side::Left.other(), Side::Right)
+    }
+
+    #[test]
+    fn join_or_else_test() {
+        let (u, v) = Handshake::<usize>::new();
+        drop(v);
+        assert_eq!(u.join_or_else(1, |x, y| x + y, |x| x * 10), Some(10));
+
+        let (u, v) = Handshake::<usize>::new();
+        assert_eq!(u.join_or_else(1, |x, y| x + y, |_| unreachable!()), None);
+        assert_eq!(v.join_or_else(2, |x, y| x + y, |_| unreachable!()), Some(3));
+    }
+
+    #[test]
+    fn try_join_test() {
+        let (u, v) = Handshake::<usize>::new();
+        let JoinOutcome::NotReady(u, value) = u.try_join(1, |x, y| x + y) else { panic!() };
+        assert_eq!(value, 1);
+        v.try_push(2).unwrap();
+        assert_eq!(u.try_join(value, |x, y| x * 10 + y), JoinOutcome::Joined(21));
+
+        let (u, v) = Handshake::<usize>::new();
+        drop(v);
+        assert_eq!(u.try_join(1, |x, y| x + y), JoinOutcome::PeerGone(1));
+    }
+
+    #[test]
+    fn join_panic_test() {
+        use std::{panic::{catch_unwind, AssertUnwindSafe}, sync::{atomic::{AtomicUsize, Ordering}, Arc}};
+
+        #[derive(Debug)]
+        struct Counted(Arc<AtomicUsize>);
+
+        impl Drop for Counted {
+            fn drop(&mut self) {
+                self.0.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+
+        let drops = Arc::new(AtomicUsize::new(0));
+        let (u, v) = Handshake::<Counted>::new();
+        let receipt = u.try_push(Counted(drops.clone())).unwrap();
+        let res = catch_unwind(AssertUnwindSafe(|| {
+            v.join(Counted(drops.clone()), |_, _| -> () { panic!("combine failed") })
+        }));
+        assert!(res.is_err());
+        assert_eq!(receipt.status(), Delivery::Lost);
+        assert_eq!(receipt.wait(), Err(Canceled));
+        assert_eq!(drops.load(Ordering::Relaxed), 2);
+    }
+
+    #[test]
+    fn select_pull_test() {
+        let (mut pending, peers): (Vec<_>, Vec<_>) = (0..8).map(|_| Handshake::<usize>::new()).unzip();
+        let mut peers = peers.into_iter().map(Some).collect::<Vec<_>>();
+        let peer = peers[5].take().unwrap();
+        let pusher = std::thread::spawn(move || {
+            std::thread::sleep(std::time::Duration::from_millis(10));
+            peer.try_push(5).unwrap();
+        });
+        assert_eq!(select_pull(&mut pending), (5, Ok(5)));
+        assert_eq!(pending.len(), 7);
+        pusher.join().unwrap();
+
+        drop(peers[2].take());
+        assert_eq!(select_pull(&mut pending), (2, Err(Canceled)));
+        assert_eq!(pending.len(), 6);
+    }
+
+    #[test]
+    fn pull_shared_test() {
+        use std::sync::Arc;
+
+        let (u, v) = Handshake::<Arc<String>>::new();
+        let watcher = v.watcher();
+        assert_eq!(v.pull_shared(), Ok(None));
+        let PushOutcome::Delivered(receipt) = u.push(Arc::new("news".to_string())) else { panic!() };
+        assert_eq!(receipt.shared(), None);
+        let value = v.pull_shared().unwrap().unwrap();
+        assert_eq!(*value, "news");
+        assert!(Arc::ptr_eq(&value, &v.pull_shared().unwrap().unwrap()));
+        assert!(Arc::ptr_eq(&value, &receipt.shared().unwrap()));
+        assert!(Arc::ptr_eq(&value, &watcher.shared().unwrap()));
+        assert_eq!(receipt.status(), Delivery::Consumed);
+        assert_eq!(v.pull_now(), PullOutcome::PeerGone);
+        drop(receipt);
+        assert_eq!(Arc::strong_count(&value), 2);
+        drop(watcher);
+        assert_eq!(Arc::strong_count(&value), 1);
+
+        let (u, v) = Handshake::<Arc<usize>>::new();
+        drop(u);
+        assert_eq!(v.pull_shared(), Err(Canceled));
+    }
+
+    #[test]
+    fn select_pull_by_key_test() {
+        let (mut pending, peers): (Vec<_>, Vec<_>) = (0..4).map(|_| Handshake::<usize>::new()).unzip();
+        let mut priorities = vec![1, 3, 0, 3];
+        for (i, peer) in peers.into_iter().enumerate() {
+            assert!(matches!(peer.push(i), PushOutcome::Delivered(_)));
+        }
+        let mut next = |pending: &mut Vec<_>| {
+            let (i, res) = select_pull_by_key(pending, |i, _| priorities[i]);
+            priorities.remove(i);
+            res.unwrap()
+        };
+        // highest first, the first of equals on ties
+        assert_eq!([next(&mut pending), next(&mut pending), next(&mut pending), next(&mut pending)], [1, 3, 0, 2]);
+    }
+
+    #[test]
+    fn select_test() {
+        let (pending, peers): (Vec<_>, Vec<_>) = (0..4).map(|_| Handshake::<usize>::new()).unzip();
+        let mut select = Select::new(pending);
+        let mut peers = peers.into_iter();
+        drop(peers.next());
+        let pusher = std::thread::spawn(move || {
+            for (i, peer) in peers.enumerate().rev() {
+                std::thread::sleep(std::time::Duration::from_millis(5));
+                peer.try_push(i + 1).unwrap();
+            }
+        });
+        assert_eq!(block_on(select.next()), Some((0, Err(Canceled))));
+        assert_eq!(block_on(select.next()), Some((3, Ok(3))));
+        assert_eq!(block_on(select.next()), Some((2, Ok(2))));
+        assert_eq!(block_on(select.next()), Some((1, Ok(1))));
+        assert_eq!(block_on(select.next()), None);
+        assert!(select.is_empty());
+        pusher.join().unwrap();
+    }
+
+    #[test]
+    fn race_test() {
+        let (a, a_peer) = Handshake::<usize>::new();
+        let (b, b_peer) = Handshake::<usize>::new();
+        let winner = race(a, b);
+        assert_eq!(winner.state(), State::Empty);
+        let receipt = b_peer.try_push(2).unwrap();
+        assert_eq!(a_peer.state(), State::PeerGone);
+        assert_eq!(receipt.status(), Delivery::Consumed);
+        assert_eq!(winner.try_pull(), Ok(2));
+
+        // first contender canceled, second still wins
+        let (a, a_peer) = Handshake::<usize>::new();
+        let (b, b_peer) = Handshake::<usize>::new();
+        let winner = race(a, b);
+        drop(a_peer);
+        let winner = winner.try_pull().unwrap_err().into_handshake().unwrap();
+        b_peer.try_push(2).unwrap();
+        assert_eq!(winner.try_pull(), Ok(2));
+
+        // both contenders canceled
+        let (a, a_peer) = Handshake::<usize>::new();
+        let (b, b_peer) = Handshake::<usize>::new();
+        let winner = race(a, b);
+        drop((a_peer, b_peer));
+        assert_eq!(winner.try_pull(), Err(PullError::PeerGone));
+
+        // consumer gone, contenders canceled
+        let (a, a_peer) = Handshake::<usize>::new();
+        let (b, b_peer) = Handshake::<usize>::new();
+        drop(race(a, b));
+        assert_eq!((a_peer.state(), b_peer.state()), (State::PeerGone, State::PeerGone));
+    }
+
+    #[test]
+    fn zip_test() {
+        let (a, a_peer) = Handshake::<usize>::new();
+        let (b, b_peer) = Handshake::<&str>::new();
+        let both = zip(a, b);
+        b_peer.try_push("b").unwrap();
+        let both = both.try_pull().unwrap_err().into_handshake().unwrap();
+        a_peer.try_push(1).unwrap();
+        assert_eq!(both.try_pull(), Ok((1, "b")));
+
+        // either canceled cancels the result and the other
+        let (a, a_peer) = Handshake::<usize>::new();
+        let (b, b_peer) = Handshake::<&str>::new();
+        let both = zip(a, b);
+        let receipt = a_peer.try_push(1).unwrap();
+        drop(b_peer);
+        assert_eq!(both.try_pull(), Err(PullError::PeerGone));
+        assert_eq!(receipt.status(), Delivery::Consumed);
+
+        // result dropped cancels both
+        let (a, a_peer) = Handshake::<usize>::new();
+        let (b, b_peer) = Handshake::<&str>::new();
+        drop(zip(a, b));
+        assert_eq!((a_peer.state(), b_peer.state()), (State::PeerGone, State::PeerGone));
+    }
+
+    #[test]
+    fn roles_test() {
+        let (u, v) = Handshake::<usize>::new();
+        let (pusher, puller) = u.into_roles(v);
+        let puller = puller.try_pull().unwrap().unwrap_err();
+        let receipt = pusher.push(1).unwrap();
+        assert_eq!(puller.recv(), Ok(1));
+        assert_eq!(receipt.status(), Delivery::Consumed);
+
+        let (u, v) = Handshake::<usize>::new();
+        let (pusher, puller) = u.into_roles(v);
+        drop(puller);
+        assert_eq!(pusher.push(2).unwrap_err(), 2);
+
+        let (u, v) = Handshake::<usize>::new();
+        let (pusher, puller) = u.into_roles(v);
+        drop(pusher);
+        assert_eq!(block_on(puller.pull()), Err(Canceled))
+    }
+
+    #[test]
+    fn bridge_test() {
+        let (blocking, task) = Handshake::<usize>::bridge();
+        let thread = std::thread::spawn(move || blocking.push(1));
+        assert_eq!(block_on(task.pull()), Ok(1));
+        assert_eq!(thread.join().unwrap(), Ok(()));
+
+        let (blocking, task) = Handshake::<usize>::bridge();
+        let thread = std::thread::spawn(move || blocking.pull());
+        let receipt = task.push(2).unwrap();
+        assert_eq!(block_on(receipt), Ok(()));
+        assert_eq!(thread.join().unwrap(), Ok(2));
+
+        let (blocking, task) = Handshake::<usize>::bridge();
+        drop(task);
+        assert_eq!(blocking.push(3), Err(Canceled))
+    }
+
+    #[test]
+    fn recycle_test() {
+        let (u, v) = Handshake::<usize>::new();
+        let receipt = u.try_push(1).unwrap();
+        let receipt = receipt.recycle().unwrap_err();
+        let watcher = v.watcher();
+        assert_eq!(v.try_pull(), Ok(1));
+        // the watcher still holds on to the slot
+        let receipt = receipt.recycle().unwrap_err();
+        drop(watcher);
+        let id = receipt.id();
+        let (u, v) = receipt.recycle().unwrap();
+        assert!(u.same_channel(&v));
+        assert_ne!(u.id(), id);
+        assert_eq!(u.state(), State::Empty);
+        let receipt = v.try_push(2).unwrap();
+        assert_eq!(u.try_pull(), Ok(2));
+        assert_eq!(receipt.status(), Delivery::Consumed);
+
+        let (u, v) = Handshake::<usize>::new();
+        let receipt = u.try_push(3).unwrap();
+        drop(v);
+        assert!(receipt.recycle().is_err())
+    }
+
+    #[test]
+    fn exchange_between_test() {
+        let (a, b) = std::thread::scope(|s| {
+            exchange_between(s, |u| u.meet(1), |v| v.meet(2))
+        });
+        assert_eq!((a, b), (Ok(2), Ok(1)));
+
+        let res = std::panic::catch_unwind(|| std::thread::scope(|s| {
+            exchange_between(s, |_: Handshake<usize>| panic!("gone"), |v| v.meet(2))
+        }));
+        assert!(res.is_err())
+    }
+
+    #[test]
+    fn buffered_test() {
+        let (u, v) = Handshake::<usize>::buffered(2);
+        assert_eq!(u.try_push(1), Ok(Ok(())));
+        assert_eq!(u.try_push(2), Ok(Ok(())));
+        assert_eq!(u.try_push(3), Ok(Err(3)));
+        assert_eq!(v.try_push(4), Ok(Ok(())));
+        assert_eq!(u.pending(), 2);
+        assert_eq!(v.try_pull(), Ok(Some(1)));
+        assert_eq!(u.try_pull(), Ok(Some(4)));
+        assert_eq!(u.try_pull(), Ok(None));
+
+        let puller = std::thread::spawn(move || (v.pull(), v.pull()));
+        u.try_push(5).unwrap().unwrap();
+        // still handed out after the pusher went away
+        drop(u);
+        assert_eq!(puller.join().unwrap(), (Ok(2), Ok(5)));
+    }
+
+    #[test]
+    #[cfg(feature = "timestamps")]
+    fn timestamps_test() {
+        let (u, v) = Handshake::<usize>::new();
+        assert_eq!((v.pushed_at(), v.age()), (None, None));
+        let before = std::time::Instant::now();
+        let PushOutcome::Delivered(receipt) = u.push(1) else { panic!() };
+        assert!(v.pushed_at().unwrap() >= before && v.age().is_some());
+        let (u, value) = receipt.retract().unwrap();
+        assert_eq!((value, v.pushed_at()), (1, None));
+
+        // still there for watchers once pulled
+        let watcher = v.watcher();
+        drop(u.push(2));
+        assert_eq!(v.pull_blocking(), Ok(2));
+        assert!(watcher.pushed_at().unwrap() >= before)
+    }
+
+    #[test]
+    #[cfg(feature = "history")]
+    fn history_test() {
+        use crate::history::EventKind;
+
+        let (u, v) = Handshake::<usize>::new();
+        let watcher = u.watcher();
+        let pusher = std::thread::spawn(move || { u.try_push(1).unwrap(); std::thread::current().id() });
+        let pusher = pusher.join().unwrap();
+        assert_eq!(v.try_pull(), Ok(1));
+        let history = watcher.history();
+        let kinds: Vec<_> = history.iter().map(|event| event.kind).collect();
+        assert_eq!(kinds, [EventKind::Created, EventKind::Pushed, EventKind::Pulled]);
+        assert_eq!(history[1].thread, pusher);
+        assert_eq!(history[2].thread, std::thread::current().id());
+        assert!(history.windows(2).all(|pair| pair[0].at <= pair[1].at));
+
+        let (u, v) = Handshake::<usize>::new();
+        drop(u);
+        assert_eq!(v.history().last().map(|event| event.kind), Some(EventKind::Canceled));
+    }
+
+    #[test]
+    #[cfg(feature = "leak-check")]
+    fn leak_check_test() {
+        use crate::leak_check::{self, LeakKind};
+
+        let (u, v) = Handshake::<usize>::new();
+        let canceled = u.id();
+        drop(u);
+        let (x, y) = Handshake::<usize>::new();
+        let lost = x.id();
+        x.try_push(1).unwrap();
+        drop(y);
+        drop(v);
+
+        let leaks = leak_check::leaks();
+        // other tests leak too, possibly at the same addresses
+        let ours = |id, kind| leaks.iter()
+            .any(|leak| leak.id == id && leak.kind == kind && leak.backtrace.contains("leak_check_test"));
+        assert!(ours(canceled, LeakKind::Canceled));
+        assert!(ours(lost, LeakKind::Lost));
+    }
+
+    #[test]
+    fn push_err_test() {
+        use crate::Failure;
+
+        let (u, v) = Handshake::<Result<usize, &str>>::new();
+        let receipt = u.push_err("broken").unwrap().unwrap();
+        assert_eq!(v.try_pull_result().unwrap_err(), Failure::PeerError("broken"));
+        assert_eq!(receipt.status(), Delivery::Consumed);
+
+        let (u, v) = Handshake::<Result<usize, &str>>::new();
+        let v = v.try_pull_result().unwrap().unwrap_err();
+        u.try_push(Ok(1)).unwrap();
+        assert_eq!(v.pull_result(), Ok(1));
+
+        let (u, v) = Handshake::<Result<usize, &str>>::new();
+        drop(u);
+        assert_eq!(v.pull_result(), Err(Failure::Canceled))
+    }
+
+    #[test]
+    fn shutdown_test() {
+        use crate::ShutdownHandshake;
+
+        let (u, v) = ShutdownHandshake::<&str>::new();
+        let (u, report) = u.ready_timeout("draining", Duration::from_millis(10)).unwrap().unwrap_err();
+        assert_eq!(report, "draining");
+        let peer = std::thread::spawn(move || v.ready("flushed"));
+        assert_eq!(u.ready("drained"), Ok("flushed"));
+        assert_eq!(peer.join().unwrap(), Ok("drained"));
+
+        let (u, v) = ShutdownHandshake::<&str>::new();
+        drop(v);
+        assert!(matches!(u.ready_timeout("done", Duration::from_secs(5)), Err(Canceled)))
+    }
+
+    #[test]
+    fn meet_timeout_test() {
+        let (u, v) = Handshake::<usize>::new();
+        let (u, value) = u.meet_timeout(1, Duration::from_millis(10)).unwrap().unwrap_err();
+        assert_eq!(value, 1);
+        let peer = std::thread::spawn(move || v.meet(2));
+        assert_eq!(u.meet_timeout(3, Duration::from_secs(5)), Ok(Ok(2)));
+        assert_eq!(peer.join().unwrap(), Ok(3));
+
+        spinning(|| {
+            let (u, v) = Handshake::new();
+            let other = std::thread::spawn(move || u.meet_timeout(1, Duration::from_secs(60)));
+            while !v.is_set() { std::thread::yield_now() }
+            let peek = v.peek().unwrap();
+            std::thread::sleep(Duration::from_millis(10));
+            drop(peek);
+            assert_eq!(v.meet(2), Ok(1));
+            assert_eq!(other.join().unwrap(), Ok(Ok(2)));
+        })
+    }
+
+    #[test]
+    fn lend_between_test() {
+        let mut buffer = vec![0u8; 8];
+        let (front, back) = buffer.split_at_mut(4);
+        // each side fills the half lent by the other
+        let (a, b) = lend_between(
+            |u: Handshake<&mut [u8]>| { u.meet(front).unwrap().fill(1); 1 },
+            |v| { v.meet(back).unwrap().fill(2); 2 },
+        );
+        assert_eq!((a, b), (1, 2));
+        assert_eq!(buffer, [2, 2, 2, 2, 1, 1, 1, 1])
+    }
+
+    #[test]
+    fn spawn_joined_test() {
+        let greeting = spawn_joined(|| "Handle", || "Communication", |x, y| format!("{x} {y}"));
+        assert!(greeting == "Handle Communication" || greeting == "Communication Handle");
+    }
+
+    #[test]
+    fn rendezvous_test() {
+        use crate::Rendezvous;
+
+        fn exchange(u: &mut dyn Rendezvous<usize>, v: &mut dyn Rendezvous<usize>) {
+            assert_eq!(v.try_pull(), Ok(None));
+            assert_eq!(u.push(1), Ok(()));
+            assert_eq!(u.state(), None);
+            assert_eq!(v.state(), Some(State::Set));
+            assert_eq!(v.push(2), Err(2));
+            assert_eq!(v.try_pull(), Ok(Some(1)));
+            assert_eq!(v.try_pull(), Err(Canceled));
+        }
+
+        let (u, v) = Handshake::<usize>::new();
+        exchange(&mut Some(u), &mut Some(v));
+        let (u, v) = LocalHandshake::<usize>::new();
+        exchange(&mut Some(u), &mut Some(v));
+
+        let (u, v) = Handshake::<usize>::new();
+        let (mut u, mut v) = (Some(u), Some(v));
+        u.cancel();
+        assert_eq!(v.state(), Some(State::PeerGone));
+        assert_eq!(v.push(3), Err(3));
+        assert_eq!(v.state(), None)
+    }
+
+    #[test]
+    #[cfg(feature = "chaos")]
+    fn chaos_test() {
+        use crate::chaos::{self, Fault, Gate};
+
+        chaos::inject(Fault::Cancel);
+        let (u, v) = Handshake::<usize>::new();
+        assert_eq!(u.try_push(1).unwrap_err(), PushError::PeerGone(1));
+        assert_eq!(v.try_pull(), Err(PullError::PeerGone));
+
+        let gate = Gate::new();
+        let (u, v) = Handshake::<usize>::new();
+        let pusher = std::thread::spawn({
+            let gate = gate.clone();
+            move || {
+                chaos::inject(Fault::Wait(gate));
+                u.try_push(2).unwrap().wait()
+            }
+        });
+        // held up until the gate opens
+        let v = v.try_pull().unwrap_err().into_handshake().unwrap();
+        gate.open();
+        assert_eq!(v.pull_blocking(), Ok(2));
+        assert_eq!(pusher.join().unwrap(), Ok(()));
+
+        chaos::inject(Fault::Delay(Duration::from_millis(1)));
+        chaos::inject(Fault::Cancel);
+        chaos::clear();
+        let (u, v) = Handshake::<usize>::new();
+        u.try_push(3).unwrap();
+        assert_eq!(v.try_pull(), Ok(3))
+    }
+
+    #[test]
+    fn join_all_test() {
+        let (ours, theirs) = Handshake::<usize>::pairs(4);
+        let mut theirs: Vec<_> = theirs.into_iter().enumerate().collect();
+        // peer goes the other way round, one arrives before us, one never
+        let (i, early) = theirs.remove(1);
+        assert_eq!(early.join(10 * i, |x, y| x + y), Ok(None));
+        let (_, gone) = theirs.remove(2);
+        drop(gone);
+        let peer = std::thread::spawn(move || join_all(theirs.into_iter().rev().map(|(i, end)| (end, 10 * i)), |x, y| x + y));
+        let joined = join_all(ours.into_iter().enumerate().map(|(i, end)| (end, i)), |x, y| x + y);
+        let theirs = peer.join().unwrap();
+        assert_eq!(joined.canceled, [3]);
+        assert!(theirs.canceled.is_empty());
+        assert!(joined.combined.contains(&11));
+        assert_eq!(joined.combined.len() + theirs.combined.len(), 3);
+
+        let (ours, theirs) = Handshake::<usize>::pairs(2);
+        theirs.into_iter().for_each(|end| { end.join(1, |x, y| x + y).unwrap(); });
+        let joined = block_on(join_all_async(ours.into_iter().map(|end| (end, 2)), |x, y| x * 10 + y));
+        assert_eq!(joined, crate::Joined { combined: vec![12, 12], canceled: vec![] })
+    }
+
+    #[test]
+    fn notify_on_complete_test() {
+        use std::sync::{Arc, atomic::{AtomicUsize, Ordering}};
+
+        struct Count(AtomicUsize);
+        impl std::task::Wake for Count {
+            fn wake(self: Arc<Self>) { self.0.fetch_add(1, Ordering::Relaxed); }
+        }
+
+        let (u, v) = Handshake::<usize>::new();
+        let count = Arc::new(Count(AtomicUsize::new(0)));
+        v.register_waker(count.clone().into());
+        let states = Arc::new(std::sync::Mutex::new(Vec::new()));
+        v.notify_on_complete({ let states = states.clone(); move |state| states.lock().unwrap().push(state) });
+        assert_eq!(count.0.load(Ordering::Relaxed), 0);
+        let receipt = u.try_push(1).unwrap();
+        v.common().wake();
+        assert_eq!(v.try_pull(), Ok(1));
+        assert_eq!(receipt.status(), Delivery::Consumed);
+        assert_eq!(count.0.load(Ordering::Relaxed), 1);
+        assert_eq!(*states.lock().unwrap(), [State::Set]);
+
+        let (u, v) = Handshake::<usize>::new();
+        drop(u);
+        let count = Arc::new(Count(AtomicUsize::new(0)));
+        v.register_waker(count.clone().into());
+        assert_eq!(count.0.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    fn outcome_test() {
+        use crate::{PullOutcome, PushOutcome};
+
+        let (u, v) = Handshake::<usize>::new();
+        let PullOutcome::Pending(v) = v.pull_now() else { panic!("nothing pushed") };
+        let PushOutcome::Delivered(receipt) = u.push(1) else { panic!("peer waiting") };
+        assert_eq!(v.pull_now(), PullOutcome::Delivered(1));
+        assert_eq!(receipt.status(), Delivery::Consumed);
+
+        let (u, v) = Handshake::<usize>::new();
+        assert!(matches!(v.push(2), PushOutcome::Delivered(_)));
+        let PushOutcome::Occupied(u, 3) = u.push(3) else { panic!("peer pushed") };
+        drop(u.pull_now());
+
+        let (u, v) = Handshake::<usize>::new();
+        drop(u);
+        assert!(matches!(Handshake::new().0.push(4), PushOutcome::Delivered(_)));
+        assert_eq!(v.pull_now(), PullOutcome::PeerGone)
+    }
+
+    #[test]
+    fn barrier_test() {
+        use crate::Barrier2;
+
+        let (u, v) = Barrier2::new();
+        let u = u.wait_timeout(Duration::from_millis(10)).unwrap().unwrap_err();
+        assert!(!v.peer_arrived());
+        let peer = std::thread::spawn(move || u.wait());
+        assert_eq!(v.wait(), Ok(()));
+        assert_eq!(peer.join().unwrap(), Ok(()));
+
+        let (u, v) = Barrier2::new();
+        assert_eq!(u.arrive(), Ok(false));
+        assert!(v.peer_arrived());
+        assert_eq!(v.arrive(), Ok(true));
+
+        let (u, v) = Barrier2::new();
+        drop(u);
+        assert_eq!(v.wait(), Err(Canceled));
+
+        // one word shared by both sides
+        assert_eq!(size_of::<Barrier2>(), size_of::<usize>());
+        let (u, v) = Barrier2::new();
+        let waiter = std::thread::spawn(move || u.wait());
+        // gone while the other side waits
+        while !v.peer_arrived() { std::thread::yield_now() }
+        drop(v);
+        assert_eq!(waiter.join().unwrap(), Err(Canceled))
+    }
+
+    #[test]
+    #[cfg(feature = "timer")]
+    fn deadline_test() {
+        use std::time::Instant;
+
+        let (u, v) = Handshake::<String>::with_deadline(Instant::now() + Duration::from_millis(20));
+        let watcher = v.watcher();
+        let receipt = u.try_push("late".to_string()).unwrap();
+        assert_eq!(receipt.wait(), Err(Canceled));
+        assert!(v.timed_out() && watcher.timed_out());
+        assert_eq!(v.try_pull(), Err(PullError::PeerGone));
+
+        let (u, v) = Handshake::<usize>::with_deadline(Instant::now() + Duration::from_millis(10));
+        assert_eq!(v.pull_blocking(), Err(Canceled));
+        assert!(u.timed_out());
+
+        let (u, v) = Handshake::<usize>::with_deadline(Instant::now() + Duration::from_millis(10));
+        let watcher = u.watcher();
+        u.try_push(1).unwrap();
+        assert_eq!(v.try_pull(), Ok(1));
+        std::thread::sleep(Duration::from_millis(20));
+        assert!(!watcher.timed_out());
+        assert_eq!(watcher.state(), State::TakenByPeer);
+
+        // let go of once over, long before the deadline
+        let payload = std::sync::Arc::new(());
+        let (u, v) = Handshake::with_deadline(Instant::now() + Duration::from_secs(3600));
+        drop(u.try_push(payload.clone()).unwrap());
+        drop(v);
+        assert_eq!(std::sync::Arc::strong_count(&payload), 1)
+    }
+
+    #[cfg(feature = "timer")]
+    #[test]
+    fn fallback_test() {
+        use std::time::Instant;
+
+        // the fallback let go of once answered, long before the deadline
+        let fallback = std::sync::Arc::new("fallback");
+        let (u, v) = Handshake::with_fallback(Instant::now() + Duration::from_secs(3600), fallback.clone());
+        assert!(matches!(u.push(std::sync::Arc::new("answer")), PushOutcome::Delivered(_)));
+        assert_eq!(*v.pull_blocking().unwrap(), "answer");
+        assert_eq!(std::sync::Arc::strong_count(&fallback), 1);
+
+        let (u, v) = Handshake::with_fallback(Instant::now() + Duration::from_millis(10), "fallback");
+        assert_eq!(v.pull_blocking(), Ok("fallback"));
+        drop(u.push("late"));
+
+        // answered for even once dropped
+        let (u, v) = Handshake::<usize>::with_default_fallback(Instant::now() + Duration::from_millis(10));
+        drop(u);
+        assert_eq!(v.pull_blocking(), Ok(0))
+    }
+
+    #[test]
+    fn builder_test() {
+        use std::sync::{Arc, Mutex};
+
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let record = |tag| { let seen = seen.clone(); move |state| seen.lock().unwrap().push((tag, state)) };
+        let lost = { let seen = seen.clone(); move || seen.lock().unwrap().push(("lost", State::PeerGone)) };
+        let (u, v) = Handshake::builder().on_complete(record("done")).on_lost(lost.clone()).build();
+        drop(u.push(1));
+        assert_eq!(v.pull_blocking(), Ok(1));
+        assert_eq!(*seen.lock().unwrap(), [("done", State::TakenByPeer)]);
+
+        let (u, v) = Handshake::<usize>::builder().on_lost(lost).build();
+        drop((u, v));
+        assert_eq!(seen.lock().unwrap()[1], ("lost", State::PeerGone));
+
+        #[cfg(feature = "timer")]
+        {
+            let (_u, v) = Handshake::builder().ttl(Duration::from_millis(10)).fallback(7).build();
+            assert_eq!(v.pull_blocking(), Ok(7));
+            let (u, v) = Handshake::<usize>::builder().ttl(Duration::from_millis(10)).build();
+            assert_eq!(v.pull_blocking(), Err(Canceled));
+            assert!(u.timed_out());
+        }
+    }
+
+    #[test]
+    fn unzip_test() {
+        let (both, peer) = Handshake::<(usize, &str)>::new();
+        let (a, b) = both.unzip();
+        let b = std::thread::spawn(move || b.pull_blocking());
+        let receipt = peer.try_push((1, "b")).unwrap();
+        assert_eq!(a.try_pull(), Ok(1));
+        assert_eq!(b.join().unwrap(), Ok("b"));
+        assert_eq!(receipt.status(), Delivery::Consumed);
+
+        // one half dropped still delivers the other
+        let (both, peer) = Handshake::<(usize, &str)>::new();
+        let (a, b) = both.unzip();
+        drop(b);
+        assert_eq!(peer.state(), State::Empty);
+        peer.try_push((2, "dropped")).unwrap();
+        assert_eq!(a.try_pull(), Ok(2));
+
+        let (both, peer) = Handshake::<(usize, &str)>::new();
+        let (a, b) = both.unzip();
+        drop(peer);
+        assert_eq!((a.try_pull(), b.try_pull()), (Err(PullError::PeerGone), Err(PullError::PeerGone)));
+
+        let (both, peer) = Handshake::<(usize, &str)>::new();
+        drop(both.unzip());
+        assert_eq!(peer.state(), State::PeerGone)
+    }
+
+    #[test]
+    fn map_test() {
+        let (u, v) = Handshake::<usize>::new();
+        let mapped = u.map(|n| n.to_string());
+        v.try_push(12).unwrap();
+        assert_eq!(mapped.try_pull(), Ok("12".to_string()));
+
+        let (u, v) = Handshake::<usize>::new();
+        let mapped = u.map(|n| n.to_string()).map(|s| s.len());
+        let pusher = std::thread::spawn(move || v.try_push(100).unwrap().wait());
+        assert_eq!(pusher.join().unwrap(), Ok(()));
+        assert_eq!(mapped.try_pull(), Ok(3));
+
+        let (u, v) = Handshake::<usize>::new();
+        let mapped = u.map(|n| n + 1);
+        drop(v);
+        assert_eq!(mapped.try_pull(), Err(PullError::PeerGone));
+
+        let (u, v) = Handshake::<usize>::new();
+        drop(u.map(|n| n + 1));
+        assert_eq!(v.state(), State::PeerGone);
+    }
+
+    #[test]
+    fn cell_test() {
+        let (mut u, mut v) = HandshakeCell::<usize>::new();
+        for round in 0..3 {
+            assert_eq!(u.try_pull(), Ok(None));
+            assert_eq!(u.try_push(round), Ok(Ok(())));
+            assert_eq!(u.try_push(round), Ok(Err(round)));
+            assert_eq!(u.try_pull(), Ok(None));
+            assert_eq!(v.try_push(round), Ok(Err(round)));
+            assert_eq!(v.try_pull(), Ok(Some(round)));
+        }
+        assert_eq!(u.join(1, |x, y| x + y), Ok(None));
+        assert_eq!(v.join(2, |x, y| x * 10 + y), Ok(Some(12)));
+        assert_eq!(u.try_push(3), Ok(Ok(())));
+        drop(u);
+        assert_eq!(v.state(), State::Set);
+        assert_eq!(v.try_push(1), Err(1));
+        assert_eq!(v.try_pull(), Ok(Some(3)));
+        assert_eq!(v.try_pull(), Err(Canceled));
+        assert_eq!(v.try_push(1), Err(1));
+    }
+
+    #[test]
+    fn cell_join_test() {
+        const ROUNDS: usize = 256;
+        let (mut u, mut v) = HandshakeCell::<usize>::new();
+        let left = std::thread::spawn(move || {
+            (0..ROUNDS).filter_map(|n| u.join(n, |x, y| (x, y)).unwrap()).collect::<Vec<_>>()
+        });
+        let right = (0..ROUNDS).filter_map(|n| v.join(n, |x, y| (x, y)).unwrap()).collect::<Vec<_>>();
+        let mut all = left.join().unwrap();
+        all.extend(right);
+        all.sort();
+        assert_eq!(all, (0..ROUNDS).map(|n| (n, n)).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn channel_test() {
+        const N: usize = 64;
+        let channel = RendezvousChannel::<usize>::new();
+        let senders: Vec<_> = (0..4).map(|t| {
+            let channel = channel.clone();
+            std::thread::spawn(move || (0..N).for_each(|n| channel.send(t * N + n).unwrap()))
+        }).collect();
+        let receivers: Vec<_> = (0..4).map(|_| {
+            let channel = channel.clone();
+            std::thread::spawn(move || (0..N).map(|_| channel.recv().unwrap()).collect::<Vec<_>>())
+        }).collect();
+        senders.into_iter().for_each(|t| t.join().unwrap());
+        let mut all: Vec<_> = receivers.into_iter().flat_map(|t| t.join().unwrap()).collect();
+        all.sort();
+        assert_eq!(all, (0..4 * N).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn channel_close_test() {
+        let channel = RendezvousChannel::<usize>::new();
+        let sender = {
+            let channel = channel.clone();
+            std::thread::spawn(move || channel.send(1))
+        };
+        std::thread::sleep(std::time::Duration::from_millis(10));
+        channel.close();
+        assert_eq!(sender.join().unwrap(), Err(1));
+        assert_eq!(channel.recv(), Err(Canceled));
+        assert_eq!(channel.send(2), Err(2));
+
+        let channel = RendezvousChannel::<usize>::new();
+        let receiver = {
+            let channel = channel.clone();
+            std::thread::spawn(move || channel.recv())
+        };
+        std::thread::sleep(std::time::Duration::from_millis(10));
+        channel.close();
+        assert_eq!(receiver.join().unwrap(), Err(Canceled));
+    }
+
+    #[test]
+    fn map_meet_test() {
+        use std::sync::Arc;
+
+        const N: usize = 32;
+        let map = Arc::new(RendezvousMap::<usize, (bool, usize)>::new());
+        let requests: Vec<_> = (0..N).map(|id| {
+            let map = map.clone();
+            std::thread::spawn(move || map.meet(id, (true, id)).unwrap())
+        }).collect();
+        let responses: Vec<_> = (0..N).rev().map(|id| {
+            let map = map.clone();
+            std::thread::spawn(move || map.meet(id, (false, id * 10)).unwrap())
+        }).collect();
+        let requests: Vec<_> = requests.into_iter().map(|t| t.join().unwrap()).collect();
+        let responses: Vec<_> = responses.into_iter().map(|t| t.join().unwrap()).collect();
+        assert_eq!(requests, (0..N).map(|id| (false, id * 10)).collect::<Vec<_>>());
+        assert_eq!(responses, (0..N).rev().map(|id| (true, id)).collect::<Vec<_>>());
+        assert_eq!(map.pending(), 0);
+    }
+
+    #[test]
+    fn atomic_test() {
+        use crate::atomic::AtomicHandshake;
+
+        let (u, v) = AtomicHandshake::<i16>::new();
+        let v = v.try_pull().unwrap().unwrap_err();
+        assert!(u.try_push(-3).unwrap().is_ok());
+        assert_eq!(v.state(), State::Set);
+        assert_eq!(v.try_pull().unwrap().unwrap(), -3);
+
+        let (u, v) = AtomicHandshake::<char>::new();
+        assert!(v.try_push('a').unwrap().is_ok());
+        let (u, value) = u.try_push('b').unwrap().unwrap_err();
+        assert_eq!((u.try_pull().unwrap().unwrap(), value), ('a', 'b'));
+
+        let (u, v) = AtomicHandshake::<f64>::new();
+        drop(u);
+        assert_eq!(v.state(), State::PeerGone);
+        assert_eq!(v.try_push(1.5).unwrap_err(), 1.5);
+
+        let (u, v) = AtomicHandshake::<usize>::new();
+        let pusher = std::thread::spawn(move || u.try_push(usize::MAX).unwrap().unwrap());
+        let mut v = v;
+        let value = loop {
+            match v.try_pull().unwrap() {
+                Ok(value) => break value,
+                Err(end) => { v = end; std::thread::yield_now() }
+            }
+        };
+        pusher.join().unwrap();
+        assert_eq!(value, usize::MAX)
+    }
+
+    #[test]
+    fn pairs_macro_test() {
+        crate::pairs! {
+            #[derive(Debug, PartialEq)]
+            struct Startup => StartupEnd {
+                name: String,
+                port: u16,
+            }
+        }
+
+        let (server, client) = Startup::pairs();
+        let pusher = std::thread::spawn(move || {
+            server.push_all(Startup { name: "db".into(), port: 5432 })
+        });
+        assert_eq!(client.pull_all(), Ok(Startup { name: "db".into(), port: 5432 }));
+        assert_eq!(pusher.join().unwrap(), Ok(()));
+
+        let (a, b) = Startup::pairs();
+        let other = std::thread::spawn(move || b.exchange(Startup { name: "b".into(), port: 2 }));
+        assert_eq!(a.exchange(Startup { name: "a".into(), port: 1 }), Ok(Startup { name: "b".into(), port: 2 }));
+        assert_eq!(other.join().unwrap(), Ok(Startup { name: "a".into(), port: 1 }));
+
+        let (a, b) = Startup::pairs();
+        assert!(matches!(a.name.push("early".into()), PushOutcome::Delivered(_)));
+        drop(a.port);
+        assert_eq!(b.pull_all(), Err(Canceled))
+    }
+
+    #[test]
+    #[cfg(feature = "test-util")]
+    fn test_util_test() {
+        use crate::{test_util::{MockEnd, Step}, Rendezvous};
+
+        // code under test, retrying a refused push after pulling
+        fn hand_over(end: &mut dyn Rendezvous<usize>, value: usize) -> Result<Option<usize>, Canceled> {
+            match end.push(value) {
+                Ok(()) => Ok(None),
+                Err(_) => end.try_pull(),
+            }
+        }
+
+        let mut end = MockEnd::scripted([Step::Accept]);
+        assert_eq!(hand_over(&mut end, 1), Ok(None));
+        assert_eq!((end.pushed(), end.remaining()), (&[1][..], 0));
+
+        let mut end = MockEnd::scripted([Step::Occupied, Step::Delay(Duration::from_millis(1)), Step::Deliver(2)]);
+        assert_eq!(hand_over(&mut end, 1), Ok(Some(2)));
+        assert_eq!(end.state(), None);
+
+        let mut end = MockEnd::scripted([Step::PeerGone]);
+        assert_eq!(hand_over(&mut end, 1), Err(Canceled));
+        let mut end = MockEnd::<usize>::scripted([]);
+        end.cancel();
+        assert!(end.is_canceled());
+
+        let (u, v) = Handshake::<usize>::new();
+        let receipt = u.try_push(1).unwrap();
+        assert_eq!(v.try_pull(), Ok(1));
+        crate::assert_completed!(receipt);
+        let (u, v) = Handshake::<usize>::new();
+        drop(v);
+        crate::assert_canceled!(u);
+        let unscripted = std::panic::catch_unwind(|| MockEnd::<usize>::scripted([]).push(1));
+        assert!(unscripted.is_err())
+    }
+
+    #[cfg(feature = "test-util")]
+    #[test]
+    fn sim_test() {
+        use std::{cell::RefCell, collections::HashSet};
+
+        use crate::sim::{yield_now, Simulation};
+
+        let run = |seed| {
+            let log = RefCell::new(Vec::new());
+            let mut sim = Simulation::new(seed);
+            let (u, v) = Handshake::<usize>::new();
+            let log = &log;
+            sim.spawn(async move {
+                for i in 0..3 { log.borrow_mut().push(i); yield_now().await }
+                let PushOutcome::Delivered(receipt) = u.push(7) else { panic!() };
+                assert_eq!(receipt.await, Ok(()));
+            });
+            sim.spawn(async move {
+                log.borrow_mut().push(10);
+                yield_now().await;
+                assert_eq!(v.pull().await, Ok(7));
+                log.borrow_mut().push(11);
+            });
+            let report = sim.run();
+            assert!(report.stuck.is_empty());
+            (report.trace, log.take())
+        };
+        // same seed, same interleaving, other seeds explore others
+        assert_eq!(run(1), run(1));
+        assert!((0..16).map(run).collect::<HashSet<_>>().len() > 1);
+
+        // each waits for the other to go first
+        let ((a, a_peer), (b, b_peer)) = (Handshake::<usize>::new(), Handshake::<usize>::new());
+        let mut sim = Simulation::new(0);
+        sim.spawn(async move { let _ = a.pull().await; drop(b_peer.push(1)) });
+        sim.spawn(async move { let _ = b.pull().await; drop(a_peer.push(1)) });
+        assert_eq!(sim.run().stuck, [0, 1]);
+    }
+
+    #[test]
+    fn connect_test() {
+        let (u, v) = Handshake::new();
+        let peer = std::thread::spawn(move || {
+            let (tx, rx) = v.connect().unwrap();
+            tx.send(rx.recv().unwrap() * 2).unwrap();
+        });
+        let (tx, rx) = u.connect().unwrap();
+        tx.send(21).unwrap();
+        assert_eq!(rx.recv(), Ok(42));
+        peer.join().unwrap();
+
+        let (u, v) = Handshake::<std::sync::mpsc::Sender<usize>>::new();
+        drop(v);
+        assert!(u.connect().is_err())
+    }
+
+    #[test]
+    fn group_test() {
+        let mut group = HandshakeGroup::new();
+        let (a, a_peer) = Handshake::<usize>::new();
+        let (b, b_peer) = Handshake::<usize>::new();
+        group.insert("a", &a);
+        group.insert("b", &b);
+        assert_eq!(group.wait_all(Duration::from_millis(1)), Err(vec![&"a", &"b"]));
+
+        let pusher = std::thread::spawn(move || a_peer.push(1));
+        assert_eq!(a.pull_blocking(), Ok(1));
+        drop(b_peer);
+        pusher.join().unwrap();
+        assert_eq!(group.wait_all(Duration::from_secs(5)), Ok(()));
+        assert_eq!(group.status().collect::<Vec<_>>(), [(&"a", State::TakenByPeer), (&"b", State::PeerGone)]);
+        assert_eq!(group.wait_any(), Some(("a", State::TakenByPeer)));
+        assert_eq!(group.wait_any(), Some(("b", State::PeerGone)));
+        assert_eq!(group.wait_any(), None);
+
+        let (c, c_peer) = Handshake::<usize>::new();
+        group.insert("c", &c);
+        let puller = std::thread::spawn(move || c_peer.pull_blocking());
+        assert!(matches!(c.push(2), PushOutcome::Delivered(_)));
+        assert_eq!(group.wait_any(), Some(("c", State::TakenByPeer)));
+        assert_eq!(puller.join().unwrap(), Ok(2))
+    }
+
+    #[test]
+    fn inflight_test() {
+        use std::{sync::Arc, time::Instant};
+
+        let table = Arc::new(InflightTable::new());
+        let later = Instant::now() + Duration::from_secs(60);
+        let answered = table.insert(1, later);
+        let expired = table.insert(2, Instant::now());
+        drop(table.insert(3, later));
+        assert_eq!(table.resolve(&1, "one"), Ok(()));
+        assert_eq!(answered.pull_blocking(), Ok("one"));
+        assert_eq!(table.resolve(&4, "four"), Err("four"));
+
+        assert_eq!(table.sweep(), [2]);
+        assert_eq!(expired.pull_blocking(), Err(Canceled));
+        assert!(table.is_empty());
+
+        let pending = table.insert(5, Instant::now() + Duration::from_millis(10));
+        let sweeper = table.spawn_sweeper(Duration::from_millis(5));
+        assert_eq!(pending.pull_blocking(), Err(Canceled));
+        drop(table);
+        sweeper.join().unwrap()
+    }
+
+    #[test]
+    fn promise_test() {
+        use std::future::IntoFuture;
+
+        let (promise, resolver) = Promise::new();
+        assert!(!promise.is_resolved());
+        assert_eq!(resolver.resolve(1), Ok(()));
+        assert!(promise.is_resolved());
+        assert_eq!(block_on(promise.into_future()), Ok(1));
+
+        let (a, a_resolver) = Promise::new();
+        let (b, b_resolver) = Promise::new();
+        let (c, c_resolver) = Promise::<usize>::new();
+        let chained = a.map(|x: usize| x + 1).and_then(move |x| c.map(move |y| x * y)).zip(b);
+        assert_eq!(a_resolver.resolve(1), Ok(()));
+        assert_eq!(c_resolver.resolve(3), Ok(()));
+        assert_eq!(b_resolver.resolve("b"), Ok(()));
+        assert_eq!(chained.block_on(), Ok((6, "b")));
+
+        let (promise, resolver) = Promise::<usize>::new();
+        let chained = promise.and_then(|_| unreachable!() as Promise<usize>);
+        drop(resolver);
+        assert_eq!(chained.block_on(), Err(Canceled));
+        let (promise, resolver) = Promise::new();
+        drop(promise);
+        assert!(resolver.is_canceled());
+        assert_eq!(resolver.resolve(1), Err(1))
+    }
+
+    #[test]
+    fn poller_test() {
+        let poller = Poller::new();
+        let mut peers = Vec::new();
+        for token in 0..100 {
+            let (u, v) = Handshake::new();
+            poller.register(token, u).unwrap();
+            peers.push(v);
+        }
+        let (u, _v) = Handshake::new();
+        assert!(poller.register(0, u).is_err());
+        let mut events = Vec::new();
+        assert_eq!(poller.poll(&mut events, Some(Duration::from_millis(1))), 0);
+
+        let pusher = std::thread::spawn(move || {
+            for (i, peer) in peers.into_iter().enumerate() {
+                if i % 2 == 0 { drop(peer.push(i)) }
+            }
+        });
+        while events.len() < 100 { poller.poll(&mut events, None); }
+        pusher.join().unwrap();
+        events.sort_by_key(|&(token, _)| token);
+        assert_eq!(events[..2], [(0, Ok(0)), (1, Err(Canceled))]);
+        assert!(poller.is_empty());
+
+        let (u, v) = Handshake::new();
+        poller.register(7, u).unwrap();
+        assert!(poller.deregister(7).is_some());
+        drop(v.push(1));
+        assert_eq!(poller.poll(&mut events, Some(Duration::from_millis(1))), 0)
+    }
+
+    #[test]
+    fn cell_policy_test() {
+        use crate::{CellPush, OnOccupied};
+
+        let (mut u, mut v) = HandshakeCell::new();
+        assert_eq!(u.push_now(1), Ok(CellPush::Pushed));
+        assert_eq!(u.push_now(2), Ok(CellPush::Occupied(2)));
+        assert_eq!(v.try_pull(), Ok(Some(1)));
+
+        let (mut u, mut v) = HandshakeCell::with_policy(OnOccupied::Replace);
+        assert_eq!(u.push_now(1), Ok(CellPush::Pushed));
+        assert_eq!(u.push_now(2), Ok(CellPush::Replaced(1)));
+        // the peer's value is never replaced
+        assert_eq!(v.push_now(3), Ok(CellPush::Occupied(3)));
+        assert_eq!(v.try_pull(), Ok(Some(2)));
+
+        let (mut u, mut v) = HandshakeCell::with_policy(OnOccupied::Keep);
+        assert_eq!(u.push_now(1), Ok(CellPush::Pushed));
+        assert_eq!(u.push_now(2), Ok(CellPush::Kept(2)));
+        assert_eq!(v.try_pull(), Ok(Some(1)));
+        drop(v);
+        assert_eq!(u.push_now(3), Err(3))
+    }
+
+    #[test]
+    fn exchange_iters_test() {
+        let sums: Vec<_> = exchange_iters(0..5, (0..5).map(|x| x * 10), |a, b| a + b).collect();
+        assert_eq!(sums, [0, 11, 22, 33, 44]);
+        // stops with the shorter one, either side
+        assert_eq!(exchange_iters(0..100, 0..3, |a, b| (a, b)).count(), 3);
+        assert_eq!(exchange_iters(0..3, 0.., |a, b| (a, b)).last(), Some((2, 2)));
+
+        let (mut u, mut v) = HandshakeCell::new();
+        let puller = std::thread::spawn(move || (v.pull(), v.pull()));
+        assert_eq!(u.push(1), Ok(()));
+        assert_eq!(u.push(2), Ok(()));
+        assert_eq!(puller.join().unwrap(), (Ok(1), Ok(2)));
+        assert_eq!(u.push(3), Err(3));
+        assert_eq!(u.pull(), Err(Canceled))
+    }
+
+    #[test]
+    fn queue_test() {
+        use std::sync::Arc;
+
+        let queue = Arc::new(HandshakeQueue::<usize>::new());
+        let first = std::thread::spawn({ let queue = queue.clone(); move || queue.enqueue(1) });
+        while queue.pending() == 0 { std::thread::yield_now() }
+        assert_eq!(queue.enqueue(2), Ok(1));
+        assert_eq!(first.join().unwrap(), Ok(2));
+
+        const N: usize = 32;
+        let participants: Vec<_> = (0..N).map(|id| {
+            let queue = queue.clone();
+            std::thread::spawn(move || queue.enqueue(id).unwrap())
+        }).collect();
+        let partners: Vec<_> = participants.into_iter().map(|t| t.join().unwrap()).collect();
+        // paired up, never with themselves
+        assert!((0..N).all(|id| partners[id] != id && partners[partners[id]] == id));
+        assert_eq!(queue.pending(), 0);
+    }
+
+    #[test]
+    #[cfg(all(feature = "shm", target_os = "linux"))]
+    fn shm_test() {
+        use crate::shm::ShmHandshake;
+
+        let (u, fd) = ShmHandshake::<[u64; 4]>::create().unwrap();
+        let v = ShmHandshake::<[u64; 4]>::open(fd).unwrap();
+        let v = v.try_pull().unwrap().unwrap_err();
+        u.try_push([1, 2, 3, 4]).unwrap().unwrap();
+        assert_eq!(v.state(), State::Set);
+        assert_eq!(v.try_pull().unwrap().unwrap(), [1, 2, 3, 4]);
+
+        let (u, fd) = ShmHandshake::<u64>::create().unwrap();
+        drop(ShmHandshake::<u64>::open(fd).unwrap());
+        assert_eq!(u.try_push(1).unwrap_err(), 1);
+
+        // never opened
+        let (u, fd) = ShmHandshake::<u64>::create().unwrap();
+        assert_eq!(u.state(), State::Empty);
+        drop(fd);
+        assert_eq!(u.state(), State::PeerGone);
+    }
+
+    #[test]
+    #[cfg(all(feature = "shm", target_os = "linux"))]
+    fn fd_test() {
+        use std::{io::{Read, Write}, os::unix::net::UnixStream};
+
+        use crate::shm::FdHandshake;
+
+        let (u, v) = FdHandshake::pair().unwrap();
+        let (mut ours, theirs) = UnixStream::pair().unwrap();
+        let peer = std::thread::spawn(move || {
+            let mut stream = UnixStream::from(v.pull().unwrap());
+            stream.write_all(b"hi").unwrap();
+        });
+        u.push(theirs.into()).unwrap();
+        let mut buf = [0; 2];
+        ours.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"hi");
+        peer.join().unwrap();
+
+        let (u, v) = FdHandshake::pair().unwrap();
+        let (a, b) = UnixStream::pair().unwrap();
+        let peer = std::thread::spawn(move || v.meet(b.into()).unwrap());
+        let (mut a, mut b) = (UnixStream::from(u.meet(a.into()).unwrap()), UnixStream::from(peer.join().unwrap()));
+        a.write_all(b"x").unwrap();
+        b.read_exact(&mut buf[..1]).unwrap();
+        assert_eq!(buf[0], b'x');
+
+        let (u, v) = FdHandshake::pair().unwrap();
+        drop(v);
+        assert_eq!(u.pull().unwrap_err().kind(), std::io::ErrorKind::UnexpectedEof);
+
+        // a byte with nothing riding on it
+        let (mut a, b) = UnixStream::pair().unwrap();
+        a.write_all(b"x").unwrap();
+        assert_eq!(FdHandshake::from_stream(b).pull().unwrap_err().kind(), std::io::ErrorKind::InvalidData)
+    }
+
+    #[test]
+    #[cfg(all(feature = "shm", target_os = "linux"))]
+    fn shm_peer_death_test() {
+        use crate::shm::ShmHandshake;
+
+        let (u, fd) = ShmHandshake::<u64>::create().unwrap();
+        let pid = unsafe { libc::fork() };
+        if pid == 0 {
+            // crash without dropping the end
+            std::mem::forget(ShmHandshake::<u64>::open(fd));
+            unsafe { libc::_exit(0) }
+        }
+        // only the child's copy left
+        drop(fd);
+        assert_eq!(unsafe { libc::waitpid(pid, std::ptr::null_mut(), 0) }, pid);
+        assert_eq!(u.state(), State::PeerGone);
+        assert_eq!(u.try_pull().unwrap_err(), Canceled);
+    }
+
+    #[test]
+    #[cfg(feature = "remote")]
+    fn remote_test() {
+        use std::{io::Write, net::{TcpListener, TcpStream}};
+
+        use crate::remote::{serve, RemoteHandshake};
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let connect = || RemoteHandshake::<String, _>::connect(TcpStream::connect(addr).unwrap());
+        let accept = || serve::<String, _>(listener.accept().unwrap().0);
+
+        let remote = connect();
+        let local = accept();
+        let remote = remote.try_pull().unwrap().unwrap_err();
+        local.try_push("from local".into()).unwrap();
+        assert_eq!(remote.try_pull().unwrap().unwrap(), "from local");
+
+        let remote = connect();
+        let local = accept();
+        assert!(matches!(remote.try_push("from remote".into()), Ok(Ok(()))));
+        assert_eq!(local.try_pull().unwrap(), "from remote");
+
+        let remote = connect();
+        let local = accept();
+        assert_eq!(local.join("local".into(), |x, y| x + &y), Ok(None));
+        assert_eq!(remote.join(" remote".into(), |x, y| x + &y), Ok(Some("local remote".into())));
+
+        let remote = connect();
+        drop(accept());
+        assert_eq!(remote.try_pull().unwrap_err(), Canceled);
+
+        let remote = connect();
+        let local = accept();
+        drop(remote);
+        while local.state() == State::Empty { std::thread::yield_now() }
+        assert_eq!(local.state(), State::PeerGone);
+
+        // a frame claiming 4 GiB, refused before making room for it
+        let header = &mut &[0, 0xff, 0xff, 0xff, 0xff][..];
+        assert_eq!(crate::remote::read_frame(header, 1 << 20).unwrap_err().kind(), std::io::ErrorKind::InvalidData);
+        let mut stream = TcpStream::connect(addr).unwrap();
+        let local = accept();
+        stream.write_all(&[0, 0xff, 0xff, 0xff, 0xff]).unwrap();
+        while local.state() == State::Empty { std::thread::yield_now() }
+        assert_eq!(local.state(), State::PeerGone);
+
+        let remote = connect().with_max_frame(4);
+        let local = accept();
+        local.try_push("longer than four".into()).unwrap();
+        assert_eq!(remote.try_pull().unwrap_err(), Canceled);
+    }
+
+    #[test]
+    fn raw_test() {
+        let (u, v) = Handshake::<usize>::new();
+        let raw = u.into_raw();
+        let u = unsafe { Handshake::<usize>::from_raw(raw) };
+        assert!(u.same_channel(&v));
+        u.try_push(1).unwrap();
+        assert_eq!(v.try_pull(), Ok(1));
+    }
+
+    #[test]
+    #[cfg(feature = "ffi")]
+    fn ffi_test() {
+        use std::ffi::c_void;
+
+        use crate::ffi::*;
+
+        let mut payload = 7usize;
+        let (u, v) = Handshake::<FfiValue>::new();
+        let raw = v.into_raw();
+        let mut pulled = std::ptr::null_mut();
+        assert_eq!(unsafe { handshake_pull(raw, &mut pulled) }, HANDSHAKE_PENDING);
+        u.try_push(FfiValue((&mut payload as *mut usize).cast())).unwrap();
+        assert_eq!(unsafe { handshake_push(raw, std::ptr::null_mut()) }, HANDSHAKE_OCCUPIED);
+        assert_eq!(unsafe { handshake_pull(raw, &mut pulled) }, HANDSHAKE_OK);
+        assert_eq!(unsafe { *pulled.cast::<usize>() }, 7);
+
+        let (u, v) = Handshake::<FfiValue>::new();
+        unsafe { handshake_cancel(v.into_raw()) };
+        assert_eq!(u.try_pull(), Err(PullError::PeerGone));
+
+        let (u, v) = Handshake::<FfiValue>::new();
+        drop(u);
+        assert_eq!(unsafe { handshake_push(v.into_raw(), std::ptr::null_mut::<c_void>()) }, HANDSHAKE_CANCELED);
+    }
+
+    #[test]
+    fn receipt_future_test() {
+        let (u, v) = Handshake::<usize>::new();
+        let receipt = u.try_push(1).unwrap();
+        let puller = std::thread::spawn(move || v.try_pull().unwrap());
+        assert_eq!(block_on(receipt), Ok(()));
+        assert_eq!(puller.join().unwrap(), 1)
+    }
+
+    #[test]
+    fn pull_future_test() {
+        let (u, v) = Handshake::<usize>::new();
+        // stands in for a driver completing from another context
+        let pusher = std::thread::spawn(move || { u.try_push(1).unwrap(); });
+        assert_eq!(block_on(v.pull()), Ok(1));
+        pusher.join().unwrap();
+        let (u, v) = Handshake::<usize>::new();
+        drop(u);
+        assert_eq!(block_on(v.pull()), Err(Canceled))
+    }
+
+    #[test]
+    fn cancel_safe_test() {
+        let waker = common::thread_waker();
+        let mut cx = Context::from_waker(&waker);
+
+        let (mut u, v) = Handshake::<usize>::new();
+        assert!(pin!(u.pull_ref()).poll(&mut cx).is_pending());
+        assert_eq!(v.state(), State::Empty);
+        assert!(matches!(v.push(1), PushOutcome::Delivered(_)));
+        assert_eq!(block_on(u.pull_ref()), Ok(1));
+        assert_eq!(block_on(u.pull_ref()), Err(Canceled));
+
+        let (u, v) = Handshake::<usize>::new();
+        let mut pull = u.pull();
+        assert!(Pin::new(&mut pull).poll(&mut cx).is_pending());
+        let u = pull.into_handshake().unwrap();
+        assert_eq!(v.state(), State::Empty);
+        assert!(matches!(v.push(2), PushOutcome::Delivered(_)));
+        assert_eq!(block_on(u.pull()), Ok(2));
+
+        let (u, v) = Handshake::<usize>::new();
+        let mut join = u.join_async(3, |a, b| a + b);
+        assert!(Pin::new(&mut join).poll(&mut cx).is_pending());
+        assert_eq!(v.state(), State::Set);
+        let (u, value) = join.abort().unwrap();
+        assert_eq!((v.state(), value), (State::Empty, 3));
+        assert_eq!(v.join(4, |a, b| a + b), Ok(None));
+        assert_eq!(block_on(u.join_async(3, |a, b| a * 10 + b)), Ok(Some(43)));
+
+        // dropped after depositing, the value taken back
+        let (mut u, v) = Handshake::new();
+        assert!(pin!(u.join_ref(5, |a, b| a + b)).poll(&mut cx).is_pending());
+        assert_eq!(v.state(), State::Empty);
+        assert_eq!(v.join(6, |a, b| a + b), Ok(None));
+        assert_eq!(block_on(u.join_ref(5, |a, b| a * 10 + b)), Ok(Some(65)));
+        assert_eq!(block_on(u.join_ref(5, |a, b| a + b)), Err(Canceled));
+
+        // dropped after the peer combined
+        let (mut u, v) = Handshake::new();
+        let mut join = u.join_ref(7, |a, b| a + b);
+        assert!(Pin::new(&mut join).poll(&mut cx).is_pending());
+        assert_eq!(v.join(8, |a, b| a * 10 + b), Ok(Some(78)));
+        drop(join);
+        assert_eq!(u.state(), State::TakenByPeer)
+    }
+
+    #[test]
+    fn wait_on_word_test() {
+        use crate::sync::{self, AtomicU32, Ordering};
+
+        let word = AtomicU32::new(0);
+        // moved on already
+        sync::wait(&word, 1, None);
+        sync::wait(&word, 0, Some(Duration::from_millis(1)));
+        std::thread::scope(|s| {
+            s.spawn(|| while word.load(Ordering::Acquire) == 0 { sync::wait(&word, 0, None) });
+            word.store(1, Ordering::Release);
+            sync::wake_all(&word)
+        })
+    }
+
+    #[test]
+    fn thread_waker_test() {
+        // made once per thread
+        assert!(common::thread_waker().will_wake(&common::thread_waker()));
+        let other = std::thread::spawn(common::thread_waker).join().unwrap();
+        assert!(!other.will_wake(&common::thread_waker()))
+    }
+
+    #[test]
+    fn join_async_test() {
+        let (u, v) = Handshake::new();
+        let waker = common::thread_waker();
+        let mut first = pin!(u.join_async(1, |x, y| x * 10 + y));
+        assert!(first.as_mut().poll(&mut Context::from_waker(&waker)).is_pending());
+        assert!(first.as_mut().poll(&mut Context::from_waker(&waker)).is_pending());
+        let second = std::thread::spawn(move || block_on(v.join_async(2, |x, y| x * 10 + y)));
+        assert_eq!(block_on(first), Ok(None));
+        assert_eq!(second.join().unwrap(), Ok(Some(12)));
+
+        let (u, v) = Handshake::<usize>::new();
+        let mut first = pin!(u.join_async(1, |x, y| x + y));
+        assert!(first.as_mut().poll(&mut Context::from_waker(&waker)).is_pending());
+        drop(v);
+        assert_eq!(block_on(first), Err(Canceled));
+
+        // polled while the peer peeks at the value
+        let (u, v) = Handshake::new();
+        let mut first = pin!(u.join_async(1, |x, y| x * 10 + y));
+        assert!(first.as_mut().poll(&mut Context::from_waker(&waker)).is_pending());
+        let peek = v.peek().unwrap();
+        assert!(first.as_mut().poll(&mut Context::from_waker(&waker)).is_pending());
+        drop(peek);
+        assert_eq!(v.join(2, |x, y| x * 10 + y), Ok(Some(12)));
+        assert_eq!(block_on(first), Ok(None))
+    }
+
+    #[test]
+    fn join_retry_test() {
+        use crate::{Backoff, RetryPolicy};
+
+        let (u, v) = Handshake::new();
+        let policy = RetryPolicy::new(Backoff::Exponential { initial: Duration::from_millis(1), max: Duration::from_millis(4) }, 3);
+        let (res, attempts) = u.join_retry(1, |a, b| a + b, policy);
+        assert_eq!(attempts.attempts, 3);
+        let Ok(Err((u, value))) = res else { unreachable!() };
+        assert_eq!(value, 1);
+
+        let joiner = std::thread::spawn(move || {
+            u.join_retry(1, |a, b| a + b, RetryPolicy::new(Backoff::Jittered { initial: Duration::from_millis(1), max: Duration::from_millis(8) }, u32::MAX))
+        });
+        // the first attempt deposits, and with nobody else acting the next change
+        // is it taking the value back once timed out
+        v.common().block_while(|state| state != common::SET);
+        v.common().block_while(|state| state == common::SET);
+        let res = v.join(2, |a, b| a + b);
+        let (joined, attempts) = joiner.join().unwrap();
+        assert!(attempts.attempts > 1);
+        // whoever arrived second combined
+        assert!(matches!((res, joined), (Ok(Some(3)), Ok(Ok(None))) | (Ok(None), Ok(Ok(Some(3))))));
+
+        let (u, v) = Handshake::<usize>::new();
+        drop(v);
+        let (res, attempts) = u.join_retry(1, |a, b| a + b, RetryPolicy::new(Backoff::Fixed(Duration::ZERO), 5));
+        assert!(matches!(res, Err(Canceled)));
+        assert_eq!(attempts.attempts, 1)
+    }
+
+    #[test]
+    fn ring_test() {
+        assert_eq!(ring_exchange(vec![1, 2, 3]), [3, 1, 2]);
+        assert!(ring_exchange::<usize>(Vec::new()).is_empty());
+
+        let stages: Vec<_> = Handshake::ring(4).into_iter().enumerate()
+            .map(|(i, end)| std::thread::spawn(move || end.exchange(i)))
+            .collect();
+        let received: Vec<_> = stages.into_iter().map(|stage| stage.join().unwrap()).collect();
+        assert_eq!(received, [Ok(3), Ok(0), Ok(1), Ok(2)]);
+
+        let mut ends = Handshake::<usize>::ring(2);
+        drop(ends.pop());
+        assert_eq!(ends.pop().unwrap().exchange(1), Err(Canceled))
+    }
+
+    #[test]
+    fn send_sync_test() {
+        fn send_sync<T: Send + Sync>() {}
+        fn send<T: Send>() {}
+        fn future<F: std::future::Future + Send>(_: F) {}
+
+        send_sync::<Handshake<usize>>();
+        send_sync::<crate::Receipt<usize>>();
+        send_sync::<crate::Watcher<usize>>();
+        send_sync::<crate::Peek<'_, usize>>();
+        send_sync::<crate::PullFuture<usize>>();
+        send_sync::<crate::PullRef<'_, usize>>();
+        send_sync::<crate::JoinRef<'_, usize, fn(usize, usize) -> usize>>();
+        send_sync::<crate::JoinFuture<usize, fn(usize, usize) -> usize>>();
+        send_sync::<crate::JoinAll<usize, usize, fn(usize, usize) -> usize>>();
+        send_sync::<crate::Next<'_, usize>>();
+        send_sync::<crate::FulfillGuard<usize>>();
+        send_sync::<crate::WeakHandshake<usize>>();
+        send_sync::<CancelScope>();
+        send_sync::<HandshakeCell<usize>>();
+        send_sync::<HandshakeGroup<usize, usize>>();
+        send_sync::<Promise<usize>>();
+        send_sync::<crate::Resolver<usize>>();
+        // not shared, only moved
+        send::<std::cell::Cell<usize>>();
+        send::<Handshake<std::cell::Cell<usize>>>();
+
+        // a view held across an await point doesn't pin the future to its thread
+        let (u, v) = Handshake::<usize>::new();
+        drop(u.push(1));
+        future(async move {
+            let peek = v.peek();
+            std::future::ready(()).await;
+            drop(peek)
+        });
+    }
+
+    #[test]
+    fn try_pull_for_spins_test() {
+        let (u, v) = Handshake::<usize>::new();
+        let PullOutcome::Pending(v) = v.try_pull_for_spins(100) else { unreachable!() };
+        let pusher = std::thread::spawn(move || drop(u.push(1)));
+        let mut v = v;
+        let value = loop {
+            match v.try_pull_for_spins(1000) {
+                PullOutcome::Delivered(value) => break value,
+                PullOutcome::Pending(retry) => v = retry,
+                PullOutcome::PeerGone => unreachable!(),
+            }
+        };
+        assert_eq!(value, 1);
+        pusher.join().unwrap()
+    }
+
+    #[test]
+    fn cancel_scope_test() {
+        let scope = CancelScope::new();
+        let child = scope.child();
+        let (u, v) = scope.pair::<usize>();
+        let (w, x) = child.pair::<usize>();
+        let waiter = std::thread::spawn(move || x.pull_blocking());
+        scope.cancel();
+        assert!(child.is_canceled());
+        assert_eq!((u.state(), v.state(), w.state()), (State::PeerGone, State::PeerGone, State::PeerGone));
+        assert_eq!(waiter.join().unwrap(), Err(Canceled));
+        // made too late, canceled right away
+        assert_eq!(child.pair::<usize>().0.state(), State::PeerGone);
+
+        let (u, v) = Handshake::<usize>::new();
+        let (w, x) = v.new_child::<usize>();
+        drop(w.push(1));
+        drop(u);
+        assert_eq!(x.pull_blocking(), Err(Canceled));
+
+        // a completed parent leaves its children be
+        let (u, v) = Handshake::<usize>::new();
+        let (w, x) = v.new_child::<usize>();
+        drop(u.push(1));
+        assert_eq!(v.pull_blocking(), Ok(1));
+        drop(w.push(2));
+        assert_eq!(x.pull_blocking(), Ok(2))
+    }
+
+    #[test]
+    fn bulk_test() {
+        let (ours, theirs) = Handshake::<usize>::pairs(3);
+        let mut theirs = theirs.into_iter();
+        drop(theirs.next().unwrap().push(1));
+        drop(theirs.next());
+        let waiting = theirs.next().unwrap();
+        let outcomes = drain_ready(ours);
+        assert!(matches!(outcomes[..], [PullOutcome::Delivered(1), PullOutcome::PeerGone, PullOutcome::Pending(_)]));
+
+        let pending = outcomes.into_iter().filter_map(|outcome| match outcome {
+            PullOutcome::Pending(end) => Some(end),
+            _ => None,
+        });
+        assert_eq!(cancel_all(pending), 1);
+        assert_eq!(waiting.state(), State::PeerGone)
+    }
+
+    #[test]
+    fn debug_test() {
+        let (u, v) = Handshake::<usize>::new();
+        let debug = format!("{u:?}");
+        assert!(debug.starts_with(&format!("Handshake {{ id: {:#x}, state: Empty, peer_alive: true", u.id())));
+        drop(v);
+        assert_eq!(u.to_string(), format!("handshake {:#x} (PeerGone)", u.id()));
+        #[cfg(feature = "timestamps")]
+        assert!(format!("{u:?}").contains("age: Some("))
+    }
+
+    #[test]
+    fn weak_test() {
+        let (u, v) = Handshake::new();
+        let weak = u.downgrade();
+        assert!(weak.is_live());
+        assert!(matches!(weak.upgrade().unwrap().push(1), PushOutcome::Delivered(_)));
+        assert!(weak.upgrade().is_none());
+        assert_eq!(v.pull_blocking(), Ok(1));
+
+        // let go of as soon as the peer is
+        let (u, v) = Handshake::<usize>::new();
+        let weak = u.downgrade();
+        drop(v);
+        assert!(!weak.is_live());
+        assert!(weak.upgrade().is_none());
+
+        let (u, v) = Handshake::<usize>::new();
+        drop(u.downgrade());
+        assert_eq!(v.state(), State::PeerGone)
+    }
+
+    #[test]
+    fn complete_locally_test() {
+        let (u, v) = Handshake::new();
+        assert_eq!(u.complete_locally(v, 1, 2, |a, b| (a, b)), Ok((2, 1)));
+
+        // canceled from elsewhere while both ends are held
+        let scope = CancelScope::new();
+        let (u, v) = scope.pair::<usize>();
+        scope.cancel();
+        assert_eq!(u.complete_locally(v, 1, 2, |a, b| a + b), Err(Canceled));
+
+        let (u, _) = Handshake::<usize>::new();
+        let (_, v) = Handshake::<usize>::new();
+        let mismatched = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| u.complete_locally(v, 1, 2, |a, b| a + b)));
+        assert!(mismatched.is_err())
+    }
+
+    #[test]
+    fn fulfill_guard_test() {
+        let (u, v) = Handshake::new();
+        let worker = std::thread::spawn(move || {
+            let _guard = u.fulfill_guard(0);
+            panic!("worker gave up")
+        });
+        assert_eq!(v.pull_blocking(), Ok(0));
+        assert!(worker.join().is_err());
+
+        let (u, v) = Handshake::<Result<usize, Aborted>>::new();
+        drop(u.abort_guard());
+        assert_eq!(v.pull_blocking(), Ok(Err(Aborted)));
+
+        let (u, v) = Handshake::new();
+        assert!(matches!(u.fulfill_guard(0).push(1), PushOutcome::Delivered(_)));
+        assert_eq!(v.pull_blocking(), Ok(1));
+
+        let (u, v) = Handshake::<usize>::new();
+        drop(u.fulfill_guard(0).disarm());
+        assert_eq!(v.pull_blocking(), Err(Canceled))
+    }
+
+    #[test]
+    fn push_with_uninit_test() {
+        let (u, v) = Handshake::<[u64; 512]>::new();
+        let Ok(receipt) = u.push_with_uninit(|slot| slot.write([7; 512])) else { unreachable!() };
+        assert_eq!(v.pull_blocking().map(|value| value[511]), Ok(7));
+        assert_eq!(receipt.status(), Delivery::Consumed);
+
+        // the slot stays empty when `init` doesn't fill it
+        let (u, v) = Handshake::<usize>::new();
+        let stray = Box::into_raw(Box::new(std::mem::MaybeUninit::uninit()));
+        let cheat = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| drop(u.push_with_uninit(|_| unsafe { (*stray).write(1) }))));
+        assert!(cheat.is_err());
+        drop(unsafe { Box::from_raw(stray) });
+        assert_eq!(v.state(), State::PeerGone);
+
+        let (u, v) = Handshake::<usize>::new();
+        drop(v.push(1));
+        let Err((u, _)) = u.push_with_uninit(|slot| slot.write(2)) else { unreachable!() };
+        assert_eq!(u.state(), State::Set)
+    }
+
+    #[test]
+    fn try_join_with_test() {
+        let parse = |x: &str, y: &str| format!("{x}{y}").parse::<u8>();
+        let (u, v) = Handshake::new();
+        assert_eq!(u.try_join_with("1", parse), Ok(Ok(None)));
+        assert_eq!(v.try_join_with("2", parse), Ok(Ok(Some(12))));
+
+        // the failure reaches the waiting peer
+        let (u, v) = Handshake::new();
+        let waker = common::thread_waker();
+        let mut first = pin!(u.join_async("9", |_, _| unreachable!()));
+        assert!(first.as_mut().poll(&mut Context::from_waker(&waker)).is_pending());
+        assert!(matches!(v.try_join_with("99", parse), Ok(Err(_))));
+        assert_eq!(block_on(first), Err::<Option<u8>, _>(Canceled));
+
+        let (u, v) = Handshake::new();
+        drop(v);
+        assert_eq!(u.try_join_with("1", parse), Err(Canceled))
+    }
+
+    #[test]
+    fn local_test() {
+        use std::rc::Rc;
+        let (u, v) = LocalHandshake::<Rc<str>>::new();
+        assert_eq!(u.join("a".into(), |_, _| unreachable!()), Ok(None));
+        assert_eq!(v.join("b".into(), |a, b| format!("{a}{b}")), Ok(Some("ab".to_string())));
+
+        let (u, v) = LocalHandshake::<Rc<usize>>::new();
+        let waker = common::thread_waker();
+        let mut pull = pin!(v.pull());
+        assert!(pull.as_mut().poll(&mut Context::from_waker(&waker)).is_pending());
+        assert!(u.try_push(Rc::new(1)).unwrap().is_ok());
+        assert_eq!(block_on(pull), Ok(Rc::new(1)));
+
+        let (u, v) = LocalHandshake::<Rc<usize>>::new();
+        drop(u);
+        assert_eq!(v.state(), State::PeerGone);
+        assert_eq!(v.try_pull().unwrap_err(), Canceled)
+    }
+
+    #[test]
+    fn strategy_test() {
+        use std::rc::Rc;
+
+        use crate::strategy::{Local, SendSafe, Strategy};
+
+        fn exchange<S: Strategy>((u, v): (Handshake<Rc<usize>, S>, Handshake<Rc<usize>, S>)) -> Rc<usize> {
+            assert!(matches!(u.push(Rc::new(1)), PushOutcome::Delivered(_)));
+            block_on(v.pull()).unwrap()
+        }
+
+        assert_eq!(exchange::<Local>(Handshake::new_local()), Rc::new(1));
+        assert_eq!(exchange::<SendSafe>(Handshake::new()), Rc::new(1));
+    }
+
+    #[test]
+    fn scoped_test() {
+        let combined = Handshake::<usize>::scoped(|u, v| std::thread::scope(|s| {
+            let a = s.spawn(|| u.join(1, |a, b| a + b).unwrap());
+            let b = s.spawn(|| v.join(2, |a, b| a + b).unwrap());
+            a.join().unwrap().or(b.join().unwrap())
+        }));
+        assert_eq!(combined, Some(3));
+        Handshake::<Vec<usize>>::scoped(|u, v| {
+            assert!(u.try_push(vec![1]).unwrap().is_ok());
+            // left in the slot, dropped along with the frame
+            drop(v)
+        })
+    }
+
+    #[test]
+    #[cfg(feature = "allocator")]
+    fn pool_test() {
+        use crate::HandshakePool;
+
+        let pool = HandshakePool::<String>::new(2);
+        assert_eq!(pool.available(), 2);
+        let (u, v) = pool.pair();
+        let (x, y) = pool.clone().pair();
+        let (w, z) = pool.pair();
+        assert_eq!(pool.available(), 0);
+        u.try_push("pooled".to_string()).unwrap();
+        assert_eq!(v.try_pull().unwrap(), "pooled");
+        assert_eq!(pool.available(), 1);
+        drop((x, y, w, z));
+        // one more than it keeps
+        assert_eq!(pool.available(), 2);
+        drop(pool)
+    }
+
+    #[test]
+    #[cfg(feature = "allocator")]
+    fn new_in_test() {
+        use std::{alloc::Layout, ptr::NonNull, sync::{atomic::{AtomicIsize, Ordering}, Arc}};
+
+        use allocator_api2::alloc::{AllocError, Allocator, Global};
+
+        // counts live allocations
+        struct Counting(Arc<AtomicIsize>);
+
+        unsafe impl Allocator for Counting {
+            fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+                self.0.fetch_add(1, Ordering::Relaxed);
+                Global.allocate(layout)
+            }
+
+            unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+                self.0.fetch_sub(1, Ordering::Relaxed);
+                Global.deallocate(ptr, layout)
+            }
+        }
+
+        let live = Arc::new(AtomicIsize::new(0));
+        let (u, v) = Handshake::<String>::new_in(Counting(live.clone()));
+        assert_eq!(live.load(Ordering::Relaxed), 1);
+        u.try_push("a".into()).unwrap();
+        assert_eq!(v.try_pull().unwrap(), "a");
+        assert_eq!(live.load(Ordering::Relaxed), 0)
+    }
+
+    #[test]
+    #[cfg(all(target_pointer_width = "64", not(any(feature = "allocator", feature = "deadlock-detection", feature = "embassy", feature = "history", feature = "leak-check", feature = "timestamps"))))]
+    fn layout_test() {
+        // as documented on `Handshake`, update both together
+        assert_eq!(size_of::<common::Common<u64>>(), 56)
+    }
+
+    #[test]
+    fn canceled_error_test() {
+        fn pull(v: Handshake<usize>) -> Result<usize, Box<dyn std::error::Error>> {
+            Ok(v.meet(0)?)
+        }
+
+        let (u, v) = Handshake::<usize>::new();
+        drop(u);
+        let err = pull(v).unwrap_err();
+        assert!(err.is::<Canceled>());
+        assert_eq!(err.to_string(), "handshake canceled, the peer went away")
+    }
+
+    #[test]
+    fn relay_panic_test() {
+        let (u, v) = Handshake::<usize>::new();
+        let mapped = v.map(|_| -> usize { panic!("adapter failed") });
+        // the pusher is unaffected, the panicking side cancels
+        assert!(u.try_push(1).is_ok());
+        assert_eq!(mapped.try_pull().unwrap_err(), PullError::PeerGone)
+    }
+
+    #[test]
+    fn pairs_test() {
+        let (left, right) = Handshake::<usize>::pairs(8);
+        assert_eq!((left.len(), right.len()), (8, 8));
+        assert!(left.iter().zip(&right).all(|(u, v)| u.same_channel(v)));
+        assert!(!left[0].same_channel(&right[1]));
+        assert_eq!(left[0].id(), right[0].id());
+        assert_ne!(left[0].id(), left[1].id());
+        let (left, right) = Handshake::<usize>::array::<4>();
+        assert!(left.iter().zip(&right).all(|(u, v)| u.same_channel(v)))
+    }
+
+    #[test]
+    // Ends only ever hold a raw pointer to the slot and give up ownership through
+    // `ManuallyDrop`, so no reference outlives the end it came from; the miri CI
+    // job runs the suite to keep it that way.
+    fn collision_check() {
+        use rand::prelude::*;
+        const N: usize = 64;
+
+        let mut left: Vec<Handshake<usize>> = vec![];
+        let mut right: Vec<Handshake<usize>> = vec![];
+        for _ in 0..N {
+            let (u, v) = Handshake::<usize>::new();
+            left.push(u);
+            right.push(v)
+        }
+        let mut rng = rand::thread_rng();
+        left.shuffle(&mut rng);
+        right.shuffle(&mut rng);
+        let left_thread = std::thread::spawn(|| left
+            .into_iter()
+            .enumerate()
+            .filter_map(|(n, u)| {u.join(n, |x, y| (x, y)).unwrap()})
+            .collect::<Vec<(usize, usize)>>()
+        );
+        let right_thread = std::thread::spawn(|| right
+            .into_iter()
+            .enumerate()
+            .filter_map(|(n, v)| {v.join(n, |x, y| (x, y)).unwrap()})
+            .collect::<Vec<(usize, usize)>>()
+        );
+        let total = left_thread.join().unwrap().len() + right_thread.join().unwrap().len();
+        assert_eq!(total, N)
+    }
+}
+// run with `RUSTFLAGS="--cfg loom" cargo test --release --lib loom`
+#[cfg(all(test, loom))]
+mod loom_test {
+    use loom::thread;
+
+    use crate::{Handshake, PushError};
+
+    // other blocking paths are left out, loom mistakes unparks racing a join for the join completing
+
+    #[test]
+    fn loom_push_pull() {
+        loom::model(|| {
+            let (u, v) = Handshake::<usize>::new();
+            let pusher = thread::spawn(move || { u.try_push(1).unwrap(); });
+            // pulled now, or the end comes back to try again once pushed
+            let res = match v.try_pull() {
+                Ok(value) => value,
+                Err(err) => { pusher.join().unwrap(); return assert_eq!(err.into_handshake().unwrap().try_pull(), Ok(1)) }
+            };
+            pusher.join().unwrap();
+            assert_eq!(res, 1)
+        })
+    }
+
+    #[test]
+    fn loom_push_pull_blocking() {
+        loom::model(|| {
+            let (u, v) = Handshake::<usize>::new();
+            let pusher = thread::spawn(move || drop(u.try_push(1)));
+            // the push can't slip in between the puller's last look and its wait
+            assert_eq!(v.pull_blocking(), Ok(1));
+            pusher.join().unwrap()
+        })
+    }
+
+    #[test]
+    fn loom_push_cancel() {
+        loom::model(|| {
+            let (u, v) = Handshake::<Box<usize>>::new();
+            let canceler = thread::spawn(move || drop(v));
+            // either deposited and dropped with the peer, or handed back
+            match u.try_push(Box::new(1)) {
+                Ok(receipt) => drop(receipt),
+                Err(PushError::PeerGone(value)) => assert_eq!(*value, 1),
+                Err(err) => panic!("{err}"),
+            }
+            canceler.join().unwrap()
+        })
+    }
+
+    #[test]
+    fn loom_join() {
+        loom::model(|| {
+            let (u, v) = Handshake::<usize>::new();
+            let other = thread::spawn(move || u.join(1, |a, b| a + b));
+            let res = v.join(2, |a, b| a + b).unwrap();
+            let other = other.join().unwrap().unwrap();
+            // exactly one side combines
+            assert!(res.is_none() != other.is_none());
+            assert_eq!(res.or(other), Some(3))
+        })
+    }
+
+    #[test]
+    fn loom_peek_copied_put() {
+        loom::model(|| {
+            let (u, v) = Handshake::<[usize; 2]>::new();
+            let watcher = v.watcher();
+            let pusher = thread::spawn(move || drop(u.try_push([1, 1])));
+            // nothing yet or the whole value, never half of it
+            assert!(matches!(watcher.peek_copied(), None | Some([1, 1])));
+            pusher.join().unwrap();
+            assert_eq!(watcher.peek_copied(), Some([1, 1]));
+            drop(v)
+        })
+    }
+
+    #[test]
+    fn loom_retract_pull() {
+        loom::model(|| {
+            let (u, v) = Handshake::<usize>::new();
+            let receipt = u.try_push(1).unwrap();
+            let puller = thread::spawn(move || v.try_pull().ok());
+            // the value ends up on exactly one side
+            let retracted = receipt.retract().map(|(_, value)| value).ok();
+            let pulled = puller.join().unwrap();
+            match retracted {
+                Some(value) => { assert_eq!(value, 1); assert_eq!(pulled, None) }
+                None => assert_eq!(pulled, Some(1)),
+            }
+        })
+    }
+}
@@ -0,0 +1,89 @@
+use std::{
+    fmt::Debug,
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use crate::{strategy::Local, Canceled, Handshake, PullFuture, PullOutcome, PushOutcome, State};
+
+/// One end of a handshake confined to a single thread, for `!Send` payloads.
+///
+/// Works like [`Handshake`](crate::Handshake), sharing its implementation
+/// through the [`Local`] strategy, without being sendable to another thread.
+pub struct LocalHandshake<T> {
+    handshake: Handshake<T, Local>,
+}
+
+impl<T> LocalHandshake<T> {
+    pub fn new() -> (LocalHandshake<T>, LocalHandshake<T>) {
+        let (u, v) = Handshake::new_local();
+        (LocalHandshake { handshake: u }, LocalHandshake { handshake: v })
+    }
+
+    /// Deposits `value`, or combines it with the peer's if that is already there.
+    pub fn join<U, F: FnOnce(T, T) -> U>(self, value: T, f: F) -> Result<Option<U>, Canceled> {
+        self.handshake.join(value, f)
+    }
+
+    /// Deposits `value` for the peer.
+    ///
+    /// Gives back the end and value when the peer already pushed (pull instead),
+    /// or just the value when the peer is gone.
+    pub fn try_push(self, value: T) -> Result<Result<(), (Self, T)>, T> {
+        match self.handshake.push(value) {
+            PushOutcome::Delivered(_) => Ok(Ok(())),
+            PushOutcome::Occupied(handshake, value) => Ok(Err((LocalHandshake { handshake }, value))),
+            PushOutcome::PeerGone(value) => Err(value),
+        }
+    }
+
+    /// Takes the value deposited by the peer.
+    ///
+    /// Gives back the end when nothing was pushed yet, or [`Canceled`] when the peer is gone.
+    pub fn try_pull(self) -> Result<Result<T, Self>, Canceled> {
+        match self.handshake.pull_now() {
+            PullOutcome::Delivered(value) => Ok(Ok(value)),
+            PullOutcome::Pending(handshake) => Ok(Err(LocalHandshake { handshake })),
+            PullOutcome::PeerGone => Err(Canceled),
+        }
+    }
+
+    /// Waits for the value deposited by the peer, or [`Canceled`] once the peer is gone.
+    pub fn pull(self) -> LocalPullFuture<T> {
+        LocalPullFuture { future: self.handshake.pull() }
+    }
+
+    pub fn is_set(&self) -> bool {
+        self.handshake.is_set()
+    }
+
+    pub fn state(&self) -> State {
+        self.handshake.state()
+    }
+}
+
+impl<T> Debug for LocalHandshake<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LocalHandshake").field("state", &self.state()).finish()
+    }
+}
+
+/// Future returned by [`LocalHandshake::pull`].
+pub struct LocalPullFuture<T> {
+    future: PullFuture<T, Local>,
+}
+
+impl<T> Future for LocalPullFuture<T> {
+    type Output = Result<T, Canceled>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        Pin::new(&mut self.future).poll(cx)
+    }
+}
+
+impl<T> Debug for LocalPullFuture<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LocalPullFuture").field("future", &self.future).finish()
+    }
+}
@@ -5,14 +5,14 @@ fn main() {
     let combine = |x, y| format!("{} {}!", x, y);
 
     '_task_a: {
-        u.join("Handle Communication".into(), combine)
-            .unwrap()
-            .map(|s| println!("{}", s));
+        if let Some(s) = u.join("Handle Communication".into(), combine).unwrap() {
+            println!("{}", s)
+        }
     }
 
     '_task_b: {
-        v.join("Symmetrically".into(), combine)
-            .unwrap()
-            .map(|s| println!("{}", s));
+        if let Some(s) = v.join("Symmetrically".into(), combine).unwrap() {
+            println!("{}", s)
+        }
     }
 }
\ No newline at end of file
@@ -0,0 +1,50 @@
+use std::{collections::HashMap, fmt::Debug, hash::Hash, sync::{Mutex, PoisonError}};
+
+use crate::{Canceled, Handshake};
+
+/// Pairs up participants arriving with the same key, each receiving the other's value.
+pub struct RendezvousMap<K, T> {
+    // ends whose peer is blocked waiting for a partner
+    waiting: Mutex<HashMap<K, Handshake<T>>>,
+}
+
+impl<K: Eq + Hash, T> RendezvousMap<K, T> {
+    pub fn new() -> RendezvousMap<K, T> {
+        RendezvousMap { waiting: Mutex::new(HashMap::new()) }
+    }
+
+    /// Blocks until another participant meets on `key`, returning its value.
+    ///
+    /// Only fails if the partner went away (e.g. panicked) mid-exchange.
+    pub fn meet(&self, key: K, value: T) -> Result<T, Canceled> {
+        let mut waiting = self.waiting.lock().unwrap_or_else(PoisonError::into_inner);
+        let handshake = match waiting.remove(&key) {
+            Some(partner) => partner,
+            None => {
+                let (ours, theirs) = Handshake::new();
+                waiting.insert(key, theirs);
+                ours
+            }
+        };
+        drop(waiting);
+        handshake.meet(value)
+    }
+
+    /// Number of keys with a participant waiting for a partner.
+    pub fn pending(&self) -> usize {
+        self.waiting.lock().unwrap_or_else(PoisonError::into_inner).len()
+    }
+}
+
+impl<K: Eq + Hash, T> Default for RendezvousMap<K, T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K, T> Debug for RendezvousMap<K, T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let pending = self.waiting.lock().unwrap_or_else(PoisonError::into_inner).len();
+        f.debug_struct("RendezvousMap").field("pending", &pending).finish()
+    }
+}
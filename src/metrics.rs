@@ -0,0 +1,41 @@
+//! Process-wide totals of what became of handshakes, e.g. to chart rendezvous failure rates.
+
+use std::sync::atomic::Ordering;
+
+#[cfg(feature = "portable-atomic")]
+use portable_atomic::AtomicU64;
+#[cfg(not(feature = "portable-atomic"))]
+use std::sync::atomic::AtomicU64;
+
+pub(crate) static CREATED: AtomicU64 = AtomicU64::new(0);
+pub(crate) static COMPLETED: AtomicU64 = AtomicU64::new(0);
+pub(crate) static CANCELED: AtomicU64 = AtomicU64::new(0);
+pub(crate) static LOST: AtomicU64 = AtomicU64::new(0);
+
+/// Totals since the process started, see [`snapshot`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub struct Snapshot {
+    /// Exchanges set up.
+    pub created: u64,
+    /// Values collected by the peer.
+    pub completed: u64,
+    /// Exchanges canceled before anything was deposited.
+    pub canceled: u64,
+    /// Values dropped because the peer went away without them.
+    pub lost: u64,
+}
+
+/// Reads the current totals, each counter on its own so they may be a little apart.
+pub fn snapshot() -> Snapshot {
+    Snapshot {
+        created: CREATED.load(Ordering::Relaxed),
+        completed: COMPLETED.load(Ordering::Relaxed),
+        canceled: CANCELED.load(Ordering::Relaxed),
+        lost: LOST.load(Ordering::Relaxed),
+    }
+}
+
+pub(crate) fn count(counter: &AtomicU64) {
+    counter.fetch_add(1, Ordering::Relaxed);
+}
@@ -0,0 +1,76 @@
+//! Adapters between handshake ends and `futures` oneshot channels.
+//!
+//! Like the combinators, forwarding happens inline on whichever thread completes
+//! the source, and canceling either side cancels the other.
+
+use std::{future::Future, pin::Pin, task::Poll};
+
+use futures_channel::oneshot::{self, Receiver, Sender};
+
+use crate::{combinators::Pulling, relay, Handshake, State};
+
+impl<T: Send + 'static> Handshake<T> {
+    /// Turns this end into a oneshot receiver of the value the peer pushes.
+    pub fn into_oneshot_receiver(self) -> Receiver<T> {
+        let (tx, rx) = oneshot::channel();
+        forward_to(self, tx);
+        rx
+    }
+
+    /// Turns this end into a oneshot sender whose value is pushed to the peer.
+    pub fn into_oneshot_sender(self) -> Sender<T> {
+        let (tx, rx) = oneshot::channel();
+        forward_from(rx, self);
+        tx
+    }
+}
+
+/// An end receiving whatever is sent on the receiver's sender.
+impl<T: Send + 'static> From<Receiver<T>> for Handshake<T> {
+    fn from(rx: Receiver<T>) -> Handshake<T> {
+        let (out, ours) = Handshake::new();
+        forward_from(rx, ours);
+        out
+    }
+}
+
+/// An end whose pushes are sent on to the sender's receiver.
+impl<T: Send + 'static> From<Sender<T>> for Handshake<T> {
+    fn from(tx: Sender<T>) -> Handshake<T> {
+        let (out, ours) = Handshake::new();
+        forward_to(ours, tx);
+        out
+    }
+}
+
+// sends the value pulled from `input` on `tx`
+fn forward_to<T: Send + 'static>(input: Handshake<T>, tx: Sender<T>) {
+    let mut input = Pulling::Waiting(input);
+    let mut tx = Some(tx);
+    relay::spawn(move |cx| {
+        let Some(sender) = tx.as_mut() else { return Poll::Ready(()) };
+        // nobody left to deliver to, cancel the input
+        if sender.poll_canceled(cx).is_ready() || !input.poll(cx) { return Poll::Ready(()); }
+        let Some(value) = input.take() else { return Poll::Pending };
+        let _ = tx.take().unwrap().send(value);
+        Poll::Ready(())
+    });
+}
+
+// pushes the value received on `rx` through `output`
+fn forward_from<T: Send + 'static>(rx: Receiver<T>, output: Handshake<T>) {
+    let mut rx = rx;
+    let mut output = Some(output);
+    relay::spawn(move |cx| {
+        let Some(out) = output.as_ref() else { return Poll::Ready(()) };
+        match Pin::new(&mut rx).poll(cx) {
+            Poll::Ready(Ok(value)) => { let _ = output.take().unwrap().push(value); return Poll::Ready(()) }
+            // sender dropped, cancel the output
+            Poll::Ready(Err(_)) => return Poll::Ready(()),
+            Poll::Pending => {}
+        }
+        out.common().register(cx);
+        // nobody left to deliver to, dropping the receiver cancels the sender
+        if out.state() == State::PeerGone { Poll::Ready(()) } else { Poll::Pending }
+    });
+}
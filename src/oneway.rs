@@ -0,0 +1,67 @@
+use std::{fmt::Debug, sync::mpsc::{RecvError, SendError, TryRecvError}};
+
+use crate::{Canceled, Handshake, PullOutcome, PushOutcome};
+
+/// The sending half of a one-way handshake, see [`Handshake::into_sender_receiver`].
+pub struct Pusher<T> {
+    end: Option<Handshake<T>>,
+}
+
+/// The receiving half of a one-way handshake, see [`Handshake::into_sender_receiver`].
+pub struct Puller<T> {
+    end: Option<Handshake<T>>,
+}
+
+impl<T> Handshake<T> {
+    /// Splits a pair into halves with `std::sync::mpsc`-like names and errors,
+    /// this end sending one value and `peer` receiving it.
+    ///
+    /// # Panics
+    /// If `peer` isn't the other end of this exchange.
+    pub fn into_sender_receiver(self, peer: Handshake<T>) -> (Pusher<T>, Puller<T>) {
+        assert!(self.same_channel(&peer), "ends belong to different exchanges");
+        (Pusher { end: Some(self) }, Puller { end: Some(peer) })
+    }
+}
+
+impl<T> Pusher<T> {
+    /// Sends `value`, failing once a value was sent or the receiver is gone.
+    pub fn send(&mut self, value: T) -> Result<(), SendError<T>> {
+        let Some(end) = self.end.take() else { return Err(SendError(value)) };
+        match end.push(value) {
+            PushOutcome::Delivered(_) => Ok(()),
+            // the receiver never pushes, nothing else refuses a value
+            PushOutcome::Occupied(_, value) | PushOutcome::PeerGone(value) => Err(SendError(value)),
+        }
+    }
+}
+
+impl<T> Puller<T> {
+    /// Blocks until the value arrives, failing once it was received or the sender is gone.
+    pub fn recv(&mut self) -> Result<T, RecvError> {
+        let end = self.end.take().ok_or(RecvError)?;
+        end.pull_blocking().map_err(|Canceled| RecvError)
+    }
+
+    /// Receives the value if it already arrived.
+    pub fn try_recv(&mut self) -> Result<T, TryRecvError> {
+        let end = self.end.take().ok_or(TryRecvError::Disconnected)?;
+        match end.pull_now() {
+            PullOutcome::Delivered(value) => Ok(value),
+            PullOutcome::Pending(end) => { self.end = Some(end); Err(TryRecvError::Empty) }
+            PullOutcome::PeerGone => Err(TryRecvError::Disconnected),
+        }
+    }
+}
+
+impl<T> Debug for Pusher<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Pusher").field("end", &self.end).finish()
+    }
+}
+
+impl<T> Debug for Puller<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Puller").field("end", &self.end).finish()
+    }
+}
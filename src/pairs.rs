@@ -0,0 +1,66 @@
+/// Declares a struct of values exchanged together, along with an end struct
+/// holding one [`Handshake`](crate::Handshake) per field, e.g. to wire up a
+/// multi-value startup handshake between two subsystems.
+///
+/// `pairs! { pub struct Startup => StartupEnd { pub config: Config, pub port: u16 } }`
+/// declares `Startup` as written, plus `StartupEnd` whose fields are ends of
+/// the same names, paired up by `Startup::pairs()`. Fields can be pushed
+/// individually, or all at once with `push_all`, `pull_all` and `exchange`.
+#[macro_export]
+macro_rules! pairs {
+    ($(#[$attr:meta])* $vis:vis struct $name:ident => $end:ident {
+        $($(#[$field_attr:meta])* $field_vis:vis $field:ident: $ty:ty),* $(,)?
+    }) => {
+        $(#[$attr])*
+        $vis struct $name {
+            $($(#[$field_attr])* $field_vis $field: $ty,)*
+        }
+
+        #[doc = concat!("The ends exchanging a [`", stringify!($name), "`], one handshake per field.")]
+        #[derive(Debug)]
+        $vis struct $end {
+            $($field_vis $field: $crate::Handshake<$ty>,)*
+        }
+
+        impl $name {
+            #[doc = concat!("Creates both ends exchanging a [`", stringify!($name), "`].")]
+            $vis fn pairs() -> ($end, $end) {
+                $(let $field = $crate::Handshake::new();)*
+                ($end { $($field: $field.0,)* }, $end { $($field: $field.1,)* })
+            }
+        }
+
+        #[allow(dead_code)]
+        impl $end {
+            /// Deposits every field for the peer, [`Canceled`]($crate::Canceled)
+            /// if any was refused, the rest still deposited.
+            $vis fn push_all(self, values: $name) -> Result<(), $crate::Canceled> {
+                let mut res = Ok(());
+                $(if !matches!(self.$field.push(values.$field), $crate::PushOutcome::Delivered(_)) {
+                    res = Err($crate::Canceled)
+                })*
+                res
+            }
+
+            /// Blocks until the peer pushed every field, or [`Canceled`]($crate::Canceled)
+            /// once it went away without one.
+            $vis fn pull_all(self) -> Result<$name, $crate::Canceled> {
+                Ok($name { $($field: {
+                    let mut end = self.$field;
+                    loop {
+                        match end.pull_now() {
+                            $crate::PullOutcome::Delivered(value) => break value,
+                            $crate::PullOutcome::Pending(pending) => { pending.wait(); end = pending }
+                            $crate::PullOutcome::PeerGone => return Err($crate::Canceled),
+                        }
+                    }
+                },)* })
+            }
+
+            /// Swaps every field with the peer's, blocking until it exchanges them too.
+            $vis fn exchange(self, values: $name) -> Result<$name, $crate::Canceled> {
+                Ok($name { $($field: self.$field.meet(values.$field)?,)* })
+            }
+        }
+    };
+}
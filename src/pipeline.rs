@@ -0,0 +1,55 @@
+use std::fmt::Debug;
+
+use crate::{Canceled, Handshake};
+
+// one round's value, the leading side handing over the end for the next round
+struct Round<T> {
+    value: T,
+    next: Option<Handshake<Round<T>>>,
+}
+
+/// One side of a sequence of exchanges in lockstep, each round setting up the next.
+pub struct Pipeline<T> {
+    end: Option<Handshake<Round<T>>>,
+    // whether this side sets up the next round
+    leads: bool,
+    rounds: u64,
+}
+
+impl<T> Pipeline<T> {
+    pub fn new() -> (Pipeline<T>, Pipeline<T>) {
+        let (a, b) = Handshake::new();
+        (Pipeline { end: Some(a), leads: true, rounds: 0 }, Pipeline { end: Some(b), leads: false, rounds: 0 })
+    }
+
+    /// Swaps `value` for the peer's in the current round, blocking until it arrives,
+    /// then moves on to the next round.
+    ///
+    /// Once the peer is gone every round is [`Canceled`].
+    pub fn exchange(&mut self, value: T) -> Result<T, Canceled> {
+        let end = self.end.take().ok_or(Canceled)?;
+        let round = if self.leads {
+            let (ours, theirs) = Handshake::new();
+            let round = end.meet(Round { value, next: Some(theirs) })?;
+            self.end = Some(ours);
+            round
+        } else {
+            let mut round = end.meet(Round { value, next: None })?;
+            self.end = round.next.take();
+            round
+        };
+        self.rounds += 1;
+        Ok(round.value)
+    }
+
+    /// Number of rounds completed so far.
+    pub fn rounds(&self) -> u64 {
+        self.rounds
+    }
+}
+
+impl<T> Debug for Pipeline<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Pipeline").field("rounds", &self.rounds).finish_non_exhaustive()
+    }
+}
@@ -0,0 +1,133 @@
+use std::{
+    collections::HashMap,
+    fmt::Debug,
+    sync::{Arc, Condvar, Mutex, PoisonError},
+    task::{Context, Wake, Waker},
+    time::{Duration, Instant},
+};
+
+use crate::{Canceled, Handshake, PullOutcome};
+
+/// Pulls from many registered ends on one dispatcher thread, like mio for
+/// handshakes: each is registered with a token, and [`poll`](Self::poll) hands out
+/// the tokens of those the peer pushed to or went away from, with the outcome.
+///
+/// Readiness is tracked by a waker per registration pushing its token onto a
+/// queue, no thread or future is involved, so pending ends cost nothing.
+pub struct Poller<T> {
+    pending: Mutex<HashMap<usize, (Handshake<T>, Waker)>>,
+    ready: Arc<Ready>,
+}
+
+// tokens of the ends woken since the last poll
+struct Ready {
+    tokens: Mutex<Vec<usize>>,
+    woken: Condvar,
+}
+
+struct TokenWaker {
+    token: usize,
+    ready: Arc<Ready>,
+}
+
+impl Wake for TokenWaker {
+    fn wake(self: Arc<Self>) {
+        self.wake_by_ref()
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        self.ready.tokens.lock().unwrap_or_else(PoisonError::into_inner).push(self.token);
+        self.ready.woken.notify_one();
+    }
+}
+
+impl<T> Poller<T> {
+    pub fn new() -> Poller<T> {
+        let ready = Arc::new(Ready { tokens: Mutex::new(Vec::new()), woken: Condvar::new() });
+        Poller { pending: Mutex::new(HashMap::new()), ready }
+    }
+
+    /// Starts pulling from `handshake` under `token`, giving it back if the token is in use.
+    pub fn register(&self, token: usize, handshake: Handshake<T>) -> Result<(), Handshake<T>> {
+        let mut pending = self.pending.lock().unwrap_or_else(PoisonError::into_inner);
+        if pending.contains_key(&token) { return Err(handshake) }
+        let waker = Waker::from(Arc::new(TokenWaker { token, ready: self.ready.clone() }));
+        arm(&handshake, &waker);
+        pending.insert(token, (handshake, waker));
+        Ok(())
+    }
+
+    /// Stops pulling under `token`, giving back its end.
+    pub fn deregister(&self, token: usize) -> Option<Handshake<T>> {
+        let (handshake, waker) = self.pending.lock().unwrap_or_else(PoisonError::into_inner).remove(&token)?;
+        handshake.common().unregister(&waker);
+        Some(handshake)
+    }
+
+    /// Waits up to `timeout`, or for good with `None`, for registered ends to be
+    /// ready, pulling from them and appending their tokens and outcomes to `events`.
+    ///
+    /// Those ends are deregistered. Returns how many were appended, none on timing out.
+    pub fn poll(&self, events: &mut Vec<(usize, Result<T, Canceled>)>, timeout: Option<Duration>) -> usize {
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+        let len = events.len();
+        loop {
+            let tokens = {
+                let mut tokens = self.ready.tokens.lock().unwrap_or_else(PoisonError::into_inner);
+                while tokens.is_empty() {
+                    match deadline {
+                        None => tokens = self.ready.woken.wait(tokens).unwrap_or_else(PoisonError::into_inner),
+                        Some(deadline) => {
+                            let Some(left) = deadline.checked_duration_since(Instant::now()) else { return 0 };
+                            tokens = self.ready.woken.wait_timeout(tokens, left).unwrap_or_else(PoisonError::into_inner).0;
+                        }
+                    }
+                }
+                std::mem::take(&mut *tokens)
+            };
+            let mut pending = self.pending.lock().unwrap_or_else(PoisonError::into_inner);
+            for token in tokens {
+                // deregistered or already handed out
+                let Some((handshake, waker)) = pending.remove(&token) else { continue };
+                match handshake.pull_now() {
+                    PullOutcome::Delivered(value) => events.push((token, Ok(value))),
+                    PullOutcome::PeerGone => events.push((token, Err(Canceled))),
+                    // woken by something else, wait for the next change
+                    PullOutcome::Pending(handshake) => {
+                        arm(&handshake, &waker);
+                        pending.insert(token, (handshake, waker));
+                    }
+                }
+            }
+            if events.len() > len { return events.len() - len }
+        }
+    }
+
+    /// Number of registered ends.
+    pub fn len(&self) -> usize {
+        self.pending.lock().unwrap_or_else(PoisonError::into_inner).len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+// registers `waker` for the next change, waking it right away if that already came
+fn arm<T>(handshake: &Handshake<T>, waker: &Waker) {
+    handshake.common().register(&Context::from_waker(waker));
+    // re-check after registering so a wake-up can't slip through
+    if handshake.is_set() || handshake.is_canceled() { waker.wake_by_ref() }
+}
+
+impl<T> Default for Poller<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Debug for Poller<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Poller").field("registered", &self.len()).finish()
+    }
+}
@@ -0,0 +1,87 @@
+use std::{alloc::Layout, fmt::Debug, marker::PhantomData, ptr::NonNull, sync::{Arc, Mutex, PoisonError}};
+
+use allocator_api2::alloc::{AllocError, Allocator, Global};
+
+use crate::{common, Handshake};
+
+/// Hands out handshakes whose slots are recycled once both ends and any receipts
+/// or watchers are gone, sparing the allocator under heavy churn.
+pub struct HandshakePool<T> {
+    alloc: PoolAlloc,
+    _value: PhantomData<fn(T) -> T>,
+}
+
+// the allocator every slot of a pool keeps, recycling blocks of one layout
+#[derive(Clone)]
+struct PoolAlloc(Arc<Blocks>);
+
+struct Blocks {
+    layout: Layout,
+    // at most `capacity` freed blocks waiting to be reused
+    free: Mutex<Vec<NonNull<u8>>>,
+    capacity: usize,
+}
+
+impl<T> HandshakePool<T> {
+    /// Makes a pool with `capacity` slots allocated up front, keeping at most that many for reuse.
+    pub fn new(capacity: usize) -> HandshakePool<T> {
+        let layout = common::layout_in::<T, PoolAlloc>();
+        let free = (0..capacity).map(|_| Global.allocate(layout).map(NonNull::cast))
+            .collect::<Result<_, _>>()
+            .unwrap_or_else(|_| std::alloc::handle_alloc_error(layout));
+        HandshakePool { alloc: PoolAlloc(Arc::new(Blocks { layout, free: Mutex::new(free), capacity })), _value: PhantomData }
+    }
+
+    /// Creates a handshake in a recycled slot, allocating one if none is free.
+    pub fn pair(&self) -> (Handshake<T>, Handshake<T>) {
+        Handshake::new_in(self.alloc.clone())
+    }
+
+    /// Number of slots waiting to be reused.
+    pub fn available(&self) -> usize {
+        self.alloc.0.free.lock().unwrap_or_else(PoisonError::into_inner).len()
+    }
+}
+
+impl<T> Clone for HandshakePool<T> {
+    fn clone(&self) -> Self {
+        HandshakePool { alloc: self.alloc.clone(), _value: PhantomData }
+    }
+}
+
+impl<T> Debug for HandshakePool<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HandshakePool").field("available", &self.available()).finish()
+    }
+}
+
+unsafe impl Allocator for PoolAlloc {
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        if layout != self.0.layout { return Global.allocate(layout) }
+        match self.0.free.lock().unwrap_or_else(PoisonError::into_inner).pop() {
+            Some(block) => Ok(NonNull::slice_from_raw_parts(block, layout.size())),
+            None => Global.allocate(layout),
+        }
+    }
+
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        if layout == self.0.layout {
+            let mut free = self.0.free.lock().unwrap_or_else(PoisonError::into_inner);
+            if free.len() < self.0.capacity { return free.push(ptr) }
+        }
+        Global.deallocate(ptr, layout)
+    }
+}
+
+impl Drop for Blocks {
+    fn drop(&mut self) {
+        let free = self.free.get_mut().unwrap_or_else(PoisonError::into_inner);
+        // blocks came from `Global` with the pool's layout
+        free.drain(..).for_each(|block| unsafe { Global.deallocate(block, self.layout) });
+    }
+}
+
+// blocks are plain memory, owned by whoever popped them
+unsafe impl Send for Blocks {}
+
+unsafe impl Sync for Blocks {}
@@ -0,0 +1,115 @@
+use std::{fmt::Debug, future::IntoFuture, task::Poll};
+
+use crate::{combinators::{zip, Pulling}, relay, Canceled, Handshake, PullFuture, PushOutcome, State};
+
+/// A value to come from a [`Resolver`], for those thinking in promises.
+///
+/// Awaiting it or calling [`block_on`](Self::block_on) gives the value, or
+/// [`Canceled`] once the resolver is dropped without resolving.
+pub struct Promise<T> {
+    handshake: Handshake<T>,
+}
+
+/// Resolves the [`Promise`] it was made with, see [`Promise::new`].
+pub struct Resolver<T> {
+    handshake: Handshake<T>,
+}
+
+impl<T> Promise<T> {
+    pub fn new() -> (Promise<T>, Resolver<T>) {
+        let (a, b) = Handshake::new();
+        (Promise { handshake: a }, Resolver { handshake: b })
+    }
+
+    /// Blocks until resolved.
+    pub fn block_on(self) -> Result<T, Canceled> {
+        self.handshake.pull_blocking()
+    }
+
+    /// Whether the value is there to take.
+    pub fn is_resolved(&self) -> bool {
+        self.handshake.is_set()
+    }
+
+    pub fn state(&self) -> State {
+        self.handshake.state()
+    }
+}
+
+impl<T: Send + 'static> Promise<T> {
+    /// Adapts the value once resolved, see [`Handshake::map`].
+    pub fn map<U, F>(self, f: F) -> Promise<U>
+    where U: Send + 'static, F: FnOnce(T) -> U + Send + 'static {
+        Promise { handshake: self.handshake.map(f) }
+    }
+
+    /// Chains the promise `f` makes of the value once resolved, resolving with its value.
+    ///
+    /// Like the combinators, `f` runs on the thread resolving this promise.
+    pub fn and_then<U, F>(self, f: F) -> Promise<U>
+    where U: Send + 'static, F: FnOnce(T) -> Promise<U> + Send + 'static {
+        let (out, ours) = Handshake::new();
+        let mut outer = Pulling::Waiting(self.handshake.map(move |value| (f)(value).handshake));
+        let mut inner = None;
+        let mut ours = Some(ours);
+        relay::spawn(move |cx| {
+            let Some(out) = ours.as_ref() else { return Poll::Ready(()) };
+            out.common().register(cx);
+            // nobody left to deliver to, cancel both
+            if out.state() == State::PeerGone { return Poll::Ready(()); }
+            if inner.is_none() {
+                if !outer.poll(cx) { return Poll::Ready(()); }
+                let Some(handshake) = outer.take() else { return Poll::Pending };
+                inner = Some(Pulling::Waiting(handshake));
+            }
+            let inner = inner.as_mut().unwrap();
+            if !inner.poll(cx) { return Poll::Ready(()); }
+            let Some(value) = inner.take() else { return Poll::Pending };
+            let _ = ours.take().unwrap().push(value);
+            Poll::Ready(())
+        });
+        Promise { handshake: out }
+    }
+
+    /// Resolves with both values once both are resolved, see [`zip`].
+    pub fn zip<U: Send + 'static>(self, other: Promise<U>) -> Promise<(T, U)> {
+        Promise { handshake: zip(self.handshake, other.handshake) }
+    }
+}
+
+impl<T> IntoFuture for Promise<T> {
+    type Output = Result<T, Canceled>;
+    type IntoFuture = PullFuture<T>;
+
+    fn into_future(self) -> PullFuture<T> {
+        self.handshake.pull()
+    }
+}
+
+impl<T> Resolver<T> {
+    /// Resolves the promise with `value`, giving it back if the promise is gone.
+    pub fn resolve(self, value: T) -> Result<(), T> {
+        match self.handshake.push(value) {
+            PushOutcome::Delivered(_) => Ok(()),
+            // the promise never pushes, nothing else refuses a value
+            PushOutcome::Occupied(_, value) | PushOutcome::PeerGone(value) => Err(value),
+        }
+    }
+
+    /// Whether the promise was dropped, e.g. to skip making a value nobody awaits.
+    pub fn is_canceled(&self) -> bool {
+        self.handshake.is_canceled()
+    }
+}
+
+impl<T> Debug for Promise<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Promise").field("state", &self.state()).finish()
+    }
+}
+
+impl<T> Debug for Resolver<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Resolver").field("state", &self.handshake.state()).finish()
+    }
+}
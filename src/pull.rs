@@ -0,0 +1,86 @@
+use std::{fmt::Debug, future::Future, pin::Pin, task::{Context, Poll}};
+
+use crate::{common, strategy::SendSafe, Canceled, Handshake};
+
+/// Future returned by [`Handshake::pull`].
+///
+/// Works with any executor; with the `embassy` feature the peer may complete
+/// it from an interrupt handler.
+///
+/// Dropping it cancels the exchange along with the end it owns, recover the
+/// end with [`into_handshake`](Self::into_handshake), or pull with
+/// [`pull_ref`](Handshake::pull_ref) to keep it e.g. across `select!` branches.
+pub struct PullFuture<T, S = SendSafe> {
+    handshake: Option<Handshake<T, S>>,
+}
+
+/// Future returned by [`Handshake::pull_ref`], leaving the end in place when dropped.
+pub struct PullRef<'a, T, S = SendSafe> {
+    handshake: &'a mut Handshake<T, S>,
+}
+
+impl<T, S> Handshake<T, S> {
+    /// Waits for the value deposited by the peer, or [`Canceled`] once the peer is gone.
+    pub fn pull(self) -> PullFuture<T, S> {
+        PullFuture { handshake: Some(self) }
+    }
+
+    /// Like [`pull`](Self::pull), but borrows the end, so dropping the future
+    /// before it completes leaves the exchange untouched to pull again later.
+    ///
+    /// Once the value was pulled the end is spent, pulling from it again sees [`Canceled`].
+    pub fn pull_ref(&mut self) -> PullRef<'_, T, S> {
+        PullRef { handshake: self }
+    }
+}
+
+impl<T, S> PullFuture<T, S> {
+    /// Gives back the end being pulled, `None` once the future completed.
+    pub fn into_handshake(mut self) -> Option<Handshake<T, S>> {
+        self.handshake.take()
+    }
+}
+
+impl<T, S> Future for PullFuture<T, S> {
+    type Output = Result<T, Canceled>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let handshake = self.handshake.take().expect("pull future polled after completion");
+        match handshake.poll_pull(cx) {
+            Ok(Ok(value)) => Poll::Ready(Ok(value)),
+            Ok(Err(handshake)) => { self.handshake = Some(handshake); Poll::Pending }
+            Err(canceled) => Poll::Ready(Err(canceled)),
+        }
+    }
+}
+
+impl<T, S> Future for PullRef<'_, T, S> {
+    type Output = Result<T, Canceled>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let common = self.handshake.common();
+        let mut res = common.take();
+        if matches!(res, Err(common::EMPTY)) {
+            common.register(cx);
+            // re-check after registering so a wake-up can't slip through
+            res = common.take();
+        }
+        match res {
+            Ok(value) => Poll::Ready(Ok(value)),
+            Err(common::EMPTY) => Poll::Pending,
+            Err(_) => Poll::Ready(Err(Canceled)),
+        }
+    }
+}
+
+impl<T, S> Debug for PullFuture<T, S> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PullFuture").field("handshake", &self.handshake).finish()
+    }
+}
+
+impl<T, S> Debug for PullRef<'_, T, S> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PullRef").field("handshake", &self.handshake).finish()
+    }
+}
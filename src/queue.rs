@@ -0,0 +1,55 @@
+use std::{collections::VecDeque, fmt::Debug, sync::{Mutex, PoisonError}};
+
+use crate::{Canceled, Handshake};
+
+/// Pairs up participants first come, first served, each receiving the other's value.
+///
+/// Like a [`RendezvousMap`](crate::RendezvousMap) where everyone shares the same key.
+pub struct HandshakeQueue<T> {
+    // ends whose peer is blocked waiting for a partner, oldest first
+    waiting: Mutex<VecDeque<Handshake<T>>>,
+}
+
+impl<T> HandshakeQueue<T> {
+    pub fn new() -> HandshakeQueue<T> {
+        HandshakeQueue { waiting: Mutex::new(VecDeque::new()) }
+    }
+
+    /// Blocks until paired with the longest waiting participant, or the next
+    /// to arrive if there is none, returning its value.
+    ///
+    /// Only fails if the partner went away (e.g. panicked) mid-exchange.
+    pub fn enqueue(&self, value: T) -> Result<T, Canceled> {
+        let mut waiting = self.waiting.lock().unwrap_or_else(PoisonError::into_inner);
+        // skipping participants that went away while waiting
+        let partner = std::iter::from_fn(|| waiting.pop_front()).find(|partner| !partner.is_canceled());
+        let handshake = match partner {
+            Some(partner) => partner,
+            None => {
+                let (ours, theirs) = Handshake::new();
+                waiting.push_back(theirs);
+                ours
+            }
+        };
+        drop(waiting);
+        handshake.meet(value)
+    }
+
+    /// Number of participants waiting for a partner.
+    pub fn pending(&self) -> usize {
+        let waiting = self.waiting.lock().unwrap_or_else(PoisonError::into_inner);
+        waiting.iter().filter(|partner| !partner.is_canceled()).count()
+    }
+}
+
+impl<T> Default for HandshakeQueue<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Debug for HandshakeQueue<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HandshakeQueue").field("pending", &self.pending()).finish()
+    }
+}
@@ -0,0 +1,37 @@
+//! Exchanges between tasks of a rayon scope.
+
+use std::sync::{Arc, Mutex, PoisonError};
+
+use rayon::Scope;
+
+use crate::Handshake;
+
+/// Spawns `a` and `b` as tasks of `scope`, handing both results to `then` on
+/// whichever task finishes last.
+///
+/// Neither task ever holds up a worker waiting for the other, so it doesn't matter
+/// which one runs first, gets stolen or lands on the same worker. Should either
+/// panic, `then` never runs.
+pub fn exchange<'scope, T, A, B, F>(scope: &Scope<'scope>, a: A, b: B, then: F)
+where
+    T: Send + 'scope,
+    A: FnOnce() -> T + Send + 'scope,
+    B: FnOnce() -> T + Send + 'scope,
+    F: FnOnce(T, T) + Send + 'scope,
+{
+    // values are tagged with whether they came from `a`, to hand them over in order
+    let (left, right) = Handshake::<(bool, T)>::new();
+    let then = Arc::new(Mutex::new(Some(then)));
+    let other = then.clone();
+    scope.spawn(move |_| finish(left, (true, a()), &then));
+    scope.spawn(move |_| finish(right, (false, b()), &other));
+}
+
+fn finish<T, F: FnOnce(T, T)>(end: Handshake<(bool, T)>, value: (bool, T), then: &Mutex<Option<F>>) {
+    // first to finish deposits, the other combines, a gone peer panicked
+    let _ = end.join(value, |peer, ours| {
+        let ((_, a), (_, b)) = if peer.0 { (peer, ours) } else { (ours, peer) };
+        let then = then.lock().unwrap_or_else(PoisonError::into_inner).take();
+        if let Some(then) = then { then(a, b) }
+    });
+}
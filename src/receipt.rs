@@ -0,0 +1,161 @@
+use std::{fmt::Debug, future::Future, mem::ManuallyDrop, pin::Pin, ptr::NonNull, task::{Context, Poll}};
+
+use crate::{common::{self, Common}, relay, Canceled, Handshake, Peek, State};
+
+/// What became of a pushed value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Delivery {
+    /// The peer has not collected the value yet.
+    Pending,
+    /// The peer collected the value.
+    Consumed,
+    /// The peer went away without collecting the value.
+    Lost,
+}
+
+/// Returned by a successful push to observe whether the peer collected the value.
+///
+/// Awaiting a receipt resolves once the value is consumed, or with [`Canceled`] if it was lost.
+pub struct Receipt<T> {
+    common: NonNull<Common<T>>,
+}
+
+impl<T> Receipt<T> {
+    pub(crate) fn new(common: NonNull<Common<T>>) -> Receipt<T> {
+        // may move to another thread, whatever end it came from
+        #[cfg(feature = "deadlock-detection")]
+        unsafe { common.as_ref() }.set_owner(None);
+        Receipt { common }
+    }
+
+    pub(crate) fn common(&self) -> &Common<T> {
+        // shared state outlives the receipt
+        unsafe { self.common.as_ref() }
+    }
+
+    pub fn status(&self) -> Delivery {
+        match self.common().state() {
+            common::TAKEN | common::SHARED => Delivery::Consumed,
+            common::LOST | common::ORPHANED | common::TIMED_OUT => Delivery::Lost,
+            _ => Delivery::Pending,
+        }
+    }
+
+    pub fn state(&self) -> State {
+        State::of(self.common().state())
+    }
+
+    /// Identifies the exchange, see [`Handshake::id`].
+    pub fn id(&self) -> u64 {
+        common::id(self.common)
+    }
+
+    /// Reads the pushed value while the peer hasn't collected it, see [`Handshake::peek`].
+    pub fn peek(&self) -> Option<Peek<'_, T>> {
+        self.common().peek()
+    }
+
+    /// Copies the pushed value while the peer hasn't collected it, see [`Handshake::peek_copied`].
+    pub fn peek_copied(&self) -> Option<T> where T: Copy {
+        self.common().read()
+    }
+
+    /// Takes the value back if the peer hasn't collected it yet, restoring the pushing end.
+    ///
+    /// Otherwise returns what became of the value.
+    pub fn retract(self) -> Result<(Handshake<T>, T), Delivery> {
+        match self.common().retract() {
+            Ok(value) => {
+                // reference moves to the end
+                let common = ManuallyDrop::new(self).common;
+                Ok((Handshake::from_common(common), value))
+            }
+            Err(_) => Err(self.status()),
+        }
+    }
+
+    /// Resets the exchange for another one once the peer collected the value,
+    /// returning a fresh pair backed by the same allocation.
+    ///
+    /// The new pair's ids differ from the old one's, so stale ids kept around
+    /// don't match it. Gives back the receipt while the value is pending, was
+    /// lost, or the peer still holds on to the slot.
+    pub fn recycle(self) -> Result<(Handshake<T>, Handshake<T>), Self> {
+        if !self.common().recycle() { return Err(self) }
+        // reference moves to the first end, the second is new
+        let common = ManuallyDrop::new(self).common;
+        Ok((Handshake::from_common(common), Handshake::from_common(common)))
+    }
+
+    /// Hands the value to `callback` should the peer go away without collecting it,
+    /// e.g. to return a pooled buffer, on the thread dropping the peer.
+    ///
+    /// Without a callback such a value is dropped along with the last of the
+    /// receipt and peer.
+    pub fn on_lost<F>(self, callback: F)
+    where T: Send + 'static, F: FnOnce(T) + Send + 'static {
+        let mut callback = Some(callback);
+        relay::spawn(move |cx| loop {
+            let common = self.common();
+            if common.state() == common::SET {
+                common.register(cx);
+                // re-check after registering so a wake-up can't slip through
+                if common.state() == common::SET { return Poll::Pending }
+            }
+            match common.reclaim() {
+                Ok(value) => {
+                    if let Some(callback) = callback.take() { (callback)(value) }
+                    return Poll::Ready(())
+                }
+                // peeked in the meantime
+                Err(common::SET) => {}
+                // collected, or retracted through another receipt
+                Err(_) => return Poll::Ready(()),
+            }
+        });
+    }
+
+    /// Blocks until the value was either consumed or lost.
+    pub fn wait(self) -> Result<(), Canceled> {
+        match self.common().block_while(|state| !matches!(state, common::TAKEN | common::SHARED | common::LOST | common::ORPHANED | common::TIMED_OUT)) {
+            common::TAKEN | common::SHARED => Ok(()),
+            _ => Err(Canceled),
+        }
+    }
+
+    fn resolved(&self) -> Option<Result<(), Canceled>> {
+        match self.status() {
+            Delivery::Pending => None,
+            Delivery::Consumed => Some(Ok(())),
+            Delivery::Lost => Some(Err(Canceled)),
+        }
+    }
+}
+
+impl<T> Future for Receipt<T> {
+    type Output = Result<(), Canceled>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        if let Some(res) = self.resolved() { return Poll::Ready(res); }
+        self.common().register(cx);
+        // re-check after registering so a wake-up can't slip through
+        self.resolved().map_or(Poll::Pending, Poll::Ready)
+    }
+}
+
+impl<T> Drop for Receipt<T> {
+    fn drop(&mut self) {
+        // receipt owns a reference
+        unsafe { Common::release(self.common) }
+    }
+}
+
+unsafe impl<T: Send> Sync for Receipt<T> {}
+
+unsafe impl<T: Send> Send for Receipt<T> {}
+
+impl<T> Debug for Receipt<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Receipt").field("status", &self.status()).finish()
+    }
+}
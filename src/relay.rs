@@ -0,0 +1,58 @@
+use std::{
+    panic::{self, AssertUnwindSafe},
+    sync::{Arc, Mutex, TryLockError},
+    task::{Context, Poll, Wake, Waker},
+};
+
+use crate::sync::{AtomicBool, Ordering};
+
+/// Drives `step` inline on whichever thread wakes it, until it completes.
+///
+/// The relay is kept alive by the wakers it leaves registered, so `step` must
+/// register with everything it is waiting on before returning pending.
+///
+/// A panicking `step` counts as completed, the panic staying in the relay rather
+/// than unwinding into whichever peer happened to wake it.
+pub(crate) fn spawn<F>(step: F)
+where F: FnMut(&mut Context<'_>) -> Poll<()> + Send + 'static {
+    Arc::new(Relay { step: Mutex::new(Some(step)), notified: AtomicBool::new(true) }).run()
+}
+
+struct Relay<F> {
+    step: Mutex<Option<F>>,
+    notified: AtomicBool,
+}
+
+impl<F> Relay<F>
+where F: FnMut(&mut Context<'_>) -> Poll<()> + Send + 'static {
+    fn run(self: Arc<Self>) {
+        loop {
+            let mut step = match self.step.try_lock() {
+                Ok(step) => step,
+                Err(TryLockError::Poisoned(poisoned)) => poisoned.into_inner(),
+                // whoever holds it sees `notified`
+                Err(TryLockError::WouldBlock) => return,
+            };
+            let waker = Waker::from(self.clone());
+            let mut cx = Context::from_waker(&waker);
+            while self.notified.swap(false, Ordering::AcqRel) {
+                let done = step.as_mut().is_some_and(|f| {
+                    panic::catch_unwind(AssertUnwindSafe(|| f(&mut cx))).map_or(true, |poll| poll.is_ready())
+                });
+                // wake-ups from dropping the step land on the flag
+                if done { *step = None }
+            }
+            drop(step);
+            // a wake-up may have landed between the last check and unlocking
+            if !self.notified.load(Ordering::Acquire) { return; }
+        }
+    }
+}
+
+impl<F> Wake for Relay<F>
+where F: FnMut(&mut Context<'_>) -> Poll<()> + Send + 'static {
+    fn wake(self: Arc<Self>) {
+        self.notified.store(true, Ordering::Release);
+        self.run()
+    }
+}
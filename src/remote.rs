@@ -0,0 +1,173 @@
+//! Handshakes between services over a byte stream (TCP or Unix socket).
+//!
+//! One side [serves](serve) the stream, keeping an ordinary [`Handshake`] end
+//! locally while a background thread answers for the other. The other side uses
+//! a [`RemoteHandshake`] with the same push, pull and join operations, payloads
+//! being encoded as JSON. Losing the connection counts as canceling, as does a
+//! frame longer than the side reading it accepts, [`DEFAULT_MAX_FRAME`] unless set.
+
+use std::{
+    fmt::Debug,
+    io::{self, Read, Write},
+    marker::PhantomData,
+};
+
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{Canceled, Handshake, PullOutcome, PushOutcome};
+
+// requests
+const PUSH: u8 = 0;
+const PULL: u8 = 1;
+const JOIN: u8 = 2;
+// replies
+const DEPOSITED: u8 = 0;
+const OCCUPIED: u8 = 1;
+const GONE: u8 = 2;
+const PENDING: u8 = 3;
+const VALUE: u8 = 4;
+
+fn write_frame(stream: &mut impl Write, tag: u8, payload: &[u8]) -> io::Result<()> {
+    let len = u32::try_from(payload.len()).map_err(io::Error::other)?;
+    stream.write_all(&[tag])?;
+    stream.write_all(&len.to_be_bytes())?;
+    stream.write_all(payload)?;
+    stream.flush()
+}
+
+/// The longest payload either side accepts in a frame unless told otherwise, 1 MiB.
+pub const DEFAULT_MAX_FRAME: usize = 1 << 20;
+
+// refuses frames longer than `max` before making room for them, the length coming from the peer
+pub(crate) fn read_frame(stream: &mut impl Read, max: usize) -> io::Result<(u8, Vec<u8>)> {
+    let mut header = [0; 5];
+    stream.read_exact(&mut header)?;
+    let len = u32::from_be_bytes(header[1..].try_into().unwrap()) as usize;
+    if len > max {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, format!("frame of {len} bytes, at most {max} accepted")));
+    }
+    let mut payload = vec![0; len];
+    stream.read_exact(&mut payload)?;
+    Ok((header[0], payload))
+}
+
+/// Serves the remote end of a handshake over `stream`, returning the local end.
+///
+/// The serving thread exits once the remote end hangs up.
+pub fn serve<T, S>(stream: S) -> Handshake<T>
+where T: Serialize + DeserializeOwned + Send + 'static, S: Read + Write + Send + 'static {
+    serve_with_max_frame(stream, DEFAULT_MAX_FRAME)
+}
+
+/// Like [`serve`], but accepting payloads of up to `max_frame` bytes.
+pub fn serve_with_max_frame<T, S>(stream: S, max_frame: usize) -> Handshake<T>
+where T: Serialize + DeserializeOwned + Send + 'static, S: Read + Write + Send + 'static {
+    let (local, served) = Handshake::new();
+    std::thread::spawn(move || {
+        let mut stream = stream;
+        let mut served = Some(served);
+        // any io error means the remote end is gone, canceling the served end
+        while let Ok((tag, payload)) = read_frame(&mut stream, max_frame) {
+            let reply = match (served.take(), tag) {
+                // exchange already over
+                (None, _) => (GONE, Vec::new()),
+                (Some(handshake), PUSH) => {
+                    let Ok(value) = serde_json::from_slice(&payload) else { break };
+                    match handshake.push(value) {
+                        PushOutcome::Delivered(_) => (DEPOSITED, Vec::new()),
+                        PushOutcome::Occupied(handshake, _) => { served = Some(handshake); (OCCUPIED, Vec::new()) }
+                        PushOutcome::PeerGone(_) => (GONE, Vec::new()),
+                    }
+                }
+                (Some(handshake), PULL) => match handshake.pull_now() {
+                    PullOutcome::Delivered(value) => {
+                        let Ok(value) = serde_json::to_vec(&value) else { break };
+                        (VALUE, value)
+                    }
+                    PullOutcome::Pending(handshake) => { served = Some(handshake); (PENDING, Vec::new()) }
+                    PullOutcome::PeerGone => (GONE, Vec::new()),
+                },
+                (Some(handshake), JOIN) => {
+                    let Ok(value) = serde_json::from_slice(&payload) else { break };
+                    // the remote end combines, hand it the local value
+                    match handshake.join(value, |other, _| other) {
+                        Ok(Some(other)) => {
+                            let Ok(other) = serde_json::to_vec(&other) else { break };
+                            (VALUE, other)
+                        }
+                        Ok(None) => (DEPOSITED, Vec::new()),
+                        Err(_) => (GONE, Vec::new()),
+                    }
+                }
+                _ => break,
+            };
+            if write_frame(&mut stream, reply.0, &reply.1).is_err() { break; }
+        }
+    });
+    local
+}
+
+/// The end of a handshake whose peer is [served](serve) on the other side of a stream.
+pub struct RemoteHandshake<T, S> {
+    stream: S,
+    max_frame: usize,
+    _value: PhantomData<fn(T) -> T>,
+}
+
+impl<T: Serialize + DeserializeOwned, S: Read + Write> RemoteHandshake<T, S> {
+    pub fn connect(stream: S) -> RemoteHandshake<T, S> {
+        RemoteHandshake { stream, max_frame: DEFAULT_MAX_FRAME, _value: PhantomData }
+    }
+
+    /// Accepts replies with payloads of up to `max_frame` bytes, instead of [`DEFAULT_MAX_FRAME`].
+    pub fn with_max_frame(self, max_frame: usize) -> RemoteHandshake<T, S> {
+        RemoteHandshake { max_frame, ..self }
+    }
+
+    fn request(&mut self, tag: u8, payload: &[u8]) -> io::Result<(u8, Vec<u8>)> {
+        write_frame(&mut self.stream, tag, payload)?;
+        read_frame(&mut self.stream, self.max_frame)
+    }
+
+    /// Deposits `value` for the peer.
+    ///
+    /// Gives back the end and value when the peer already pushed (pull instead),
+    /// or just the value when the peer or connection is gone.
+    pub fn try_push(mut self, value: T) -> Result<Result<(), (Self, T)>, T> {
+        let Ok(payload) = serde_json::to_vec(&value) else { return Err(value) };
+        match self.request(PUSH, &payload) {
+            Ok((DEPOSITED, _)) => Ok(Ok(())),
+            Ok((OCCUPIED, _)) => Ok(Err((self, value))),
+            _ => Err(value),
+        }
+    }
+
+    /// Takes the value deposited by the peer.
+    ///
+    /// Gives back the end when nothing was pushed yet, or [`Canceled`] when the peer or connection is gone.
+    pub fn try_pull(mut self) -> Result<Result<T, Self>, Canceled> {
+        match self.request(PULL, &[]) {
+            Ok((VALUE, value)) => serde_json::from_slice(&value).map(Ok).map_err(|_| Canceled),
+            Ok((PENDING, _)) => Ok(Err(self)),
+            _ => Err(Canceled),
+        }
+    }
+
+    pub fn join<U, F: FnOnce(T, T) -> U>(mut self, value: T, f: F) -> Result<Option<U>, Canceled> {
+        let payload = serde_json::to_vec(&value).map_err(|_| Canceled)?;
+        match self.request(JOIN, &payload) {
+            Ok((VALUE, other)) => {
+                let other = serde_json::from_slice(&other).map_err(|_| Canceled)?;
+                Ok(Some((f)(other, value)))
+            }
+            Ok((DEPOSITED, _)) => Ok(None),
+            _ => Err(Canceled),
+        }
+    }
+}
+
+impl<T, S> Debug for RemoteHandshake<T, S> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RemoteHandshake").finish_non_exhaustive()
+    }
+}
@@ -0,0 +1,106 @@
+use crate::{Canceled, Handshake, LocalHandshake, PullError, PushError, State};
+
+/// The operations shared by the handshake variants, for code generic over
+/// them or working with `dyn Rendezvous<T>`, and for mocking the primitive.
+///
+/// Implemented for an `Option` of each end, `None` standing for an end that
+/// was used up by a push or pull, or canceled.
+pub trait Rendezvous<T> {
+    /// Deposits `value` for the peer, using up the end.
+    ///
+    /// Gives back the value when the peer already pushed (the end stays, pull
+    /// instead), when the peer is gone, or when the end was used up.
+    fn push(&mut self, value: T) -> Result<(), T>;
+
+    /// Takes the value deposited by the peer if there is one, using up the end.
+    ///
+    /// Fails with [`Canceled`] when the peer is gone or the end was used up.
+    fn try_pull(&mut self) -> Result<Option<T>, Canceled>;
+
+    /// Gives up the end, canceling the exchange unless it completed.
+    fn cancel(&mut self);
+
+    /// Where the exchange stands, `None` once the end is used up or if it can't
+    /// tell without talking to the peer.
+    fn state(&self) -> Option<State>;
+}
+
+// the other variants share the shape of `try_push` and `try_pull`
+macro_rules! rendezvous {
+    ($([$($generics:tt)*] $end:ty, |$this:ident| $state:expr;)*) => {$(
+        impl<$($generics)*> Rendezvous<T> for Option<$end> {
+            fn push(&mut self, value: T) -> Result<(), T> {
+                let Some(end) = self.take() else { return Err(value) };
+                match end.try_push(value)? {
+                    Ok(_) => Ok(()),
+                    Err((end, value)) => { *self = Some(end); Err(value) }
+                }
+            }
+
+            fn try_pull(&mut self) -> Result<Option<T>, Canceled> {
+                match self.take().ok_or(Canceled)?.try_pull()? {
+                    Ok(value) => Ok(Some(value)),
+                    Err(end) => { *self = Some(end); Ok(None) }
+                }
+            }
+
+            fn cancel(&mut self) {
+                drop(self.take())
+            }
+
+            fn state(&self) -> Option<State> {
+                let $this = self.as_ref()?;
+                $state
+            }
+        }
+    )*};
+}
+
+// the errors spelled out on `Handshake` alone
+impl<T> Rendezvous<T> for Option<Handshake<T>> {
+    fn push(&mut self, value: T) -> Result<(), T> {
+        let Some(end) = self.take() else { return Err(value) };
+        match end.try_push(value) {
+            Ok(_) => Ok(()),
+            Err(PushError::AlreadySet(end, value)) => { *self = Some(end); Err(value) }
+            Err(PushError::PeerGone(value)) => Err(value),
+        }
+    }
+
+    fn try_pull(&mut self) -> Result<Option<T>, Canceled> {
+        match self.take().ok_or(Canceled)?.try_pull() {
+            Ok(value) => Ok(Some(value)),
+            Err(PullError::Empty(end)) => { *self = Some(end); Ok(None) }
+            Err(PullError::PeerGone) => Err(Canceled),
+        }
+    }
+
+    fn cancel(&mut self) {
+        drop(self.take())
+    }
+
+    fn state(&self) -> Option<State> {
+        Some(self.as_ref()?.state())
+    }
+}
+
+rendezvous! {
+    [T] LocalHandshake<T>, |end| Some(end.state());
+}
+
+#[cfg(any(target_has_atomic = "64", feature = "portable-atomic"))]
+rendezvous! {
+    [T: crate::atomic::Inline] crate::atomic::AtomicHandshake<T>, |end| Some(end.state());
+}
+
+#[cfg(all(feature = "shm", target_os = "linux"))]
+rendezvous! {
+    [T: crate::shm::Pod] crate::shm::ShmHandshake<T>, |end| Some(end.state());
+}
+
+#[cfg(feature = "remote")]
+rendezvous! {
+    // only the served side knows
+    [T: serde::Serialize + serde::de::DeserializeOwned, S: std::io::Read + std::io::Write]
+        crate::remote::RemoteHandshake<T, S>, |_end| None;
+}
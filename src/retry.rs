@@ -0,0 +1,84 @@
+use std::{
+    hash::{BuildHasher, Hasher},
+    time::{Duration, Instant},
+};
+
+use crate::{Canceled, Handshake};
+
+/// How long each attempt of [`Handshake::join_retry`] waits for the peer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Backoff {
+    /// The same wait every time.
+    Fixed(Duration),
+    /// Doubling from `initial` with every attempt, up to `max`.
+    Exponential { initial: Duration, max: Duration },
+    /// Like `Exponential`, but each wait cut down to somewhere between half and
+    /// all of it, so peers retrying alike drift apart.
+    Jittered { initial: Duration, max: Duration },
+}
+
+impl Backoff {
+    // the wait of the attempt after `attempts` failed ones
+    fn delay(self, attempts: u32) -> Duration {
+        let exponential = |initial: Duration, max: Duration| {
+            initial.saturating_mul(1 << attempts.min(31)).min(max)
+        };
+        match self {
+            Backoff::Fixed(delay) => delay,
+            Backoff::Exponential { initial, max } => exponential(initial, max),
+            Backoff::Jittered { initial, max } => {
+                let delay = exponential(initial, max);
+                // std seeds every `RandomState` afresh, good enough to spread retries
+                let random = std::collections::hash_map::RandomState::new().build_hasher().finish();
+                delay / 2 + delay.mul_f64((random >> 11) as f64 / (1u64 << 53) as f64) / 2
+            }
+        }
+    }
+}
+
+/// When [`Handshake::join_retry`] gives up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RetryPolicy {
+    pub backoff: Backoff,
+    /// How many attempts to make, at least one.
+    pub max_attempts: u32,
+}
+
+impl RetryPolicy {
+    pub fn new(backoff: Backoff, max_attempts: u32) -> RetryPolicy {
+        RetryPolicy { backoff, max_attempts }
+    }
+}
+
+/// What it took [`Handshake::join_retry`] to get to its outcome.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Attempts {
+    /// Attempts made, including the one that succeeded.
+    pub attempts: u32,
+    pub elapsed: Duration,
+}
+
+// what a retried join comes to, the shape of `join_timeout`
+type Retried<T, U> = Result<Result<Option<U>, (Handshake<T>, T)>, Canceled>;
+
+impl<T> Handshake<T> {
+    /// Like [`join_timeout`](Self::join_timeout), retried with each attempt waiting
+    /// as long as `policy` says, until joined, canceled or out of attempts.
+    ///
+    /// Between attempts the value is taken back, so the peer never combines with
+    /// it after this gave up.
+    pub fn join_retry<U, F>(self, value: T, f: F, policy: RetryPolicy) -> (Retried<T, U>, Attempts)
+    where F: FnOnce(T, T) -> U {
+        let start = Instant::now();
+        let (mut end, mut value, mut f) = (self, value, Some(f));
+        let mut attempts = 0;
+        loop {
+            let delay = policy.backoff.delay(attempts);
+            attempts += 1;
+            match end.join_timeout(value, |a, b| (f.take().expect("joined once"))(a, b), delay) {
+                Ok(Err((retry, rejected))) if attempts < policy.max_attempts => (end, value) = (retry, rejected),
+                res => return (res, Attempts { attempts, elapsed: start.elapsed() }),
+            }
+        }
+    }
+}
@@ -0,0 +1,52 @@
+use std::fmt::Debug;
+
+use crate::{Canceled, Handshake, PushOutcome};
+
+/// A participant in a ring of exchanges, pushing to its successor and pulling
+/// from its predecessor, see [`Handshake::ring`].
+pub struct RingEnd<T> {
+    next: Handshake<T>,
+    prev: Handshake<T>,
+}
+
+impl<T> Handshake<T> {
+    /// Wires `n` participants in a ring, the one at each index passing its value
+    /// to the next and the last one's going to the first, using `n` exchanges.
+    pub fn ring(n: usize) -> Vec<RingEnd<T>> {
+        let (next, mut prev) = Handshake::pairs(n);
+        if n > 0 { prev.rotate_right(1) }
+        next.into_iter().zip(prev).map(|(next, prev)| RingEnd { next, prev }).collect()
+    }
+}
+
+impl<T> RingEnd<T> {
+    /// Hands `value` to the successor, then blocks until the predecessor's arrives.
+    ///
+    /// [`Canceled`] once either neighbour went away without its part.
+    pub fn exchange(self, value: T) -> Result<T, Canceled> {
+        match self.next.push(value) {
+            PushOutcome::Delivered(_) => self.prev.pull_blocking(),
+            // the successor never pushes, nothing else refuses a value
+            PushOutcome::Occupied(..) | PushOutcome::PeerGone(_) => Err(Canceled),
+        }
+    }
+}
+
+/// Passes every value on to the next index in one round, the last one's going to the first.
+pub fn ring_exchange<T>(values: Vec<T>) -> Vec<T> {
+    let ends = Handshake::ring(values.len());
+    // all pushes land before any pull, so nothing blocks
+    let mut pulls = Vec::with_capacity(ends.len());
+    for (end, value) in ends.into_iter().zip(values) {
+        let RingEnd { next, prev } = end;
+        let _ = next.push(value);
+        pulls.push(prev);
+    }
+    pulls.into_iter().map(|prev| prev.pull_blocking().expect("every value pushed")).collect()
+}
+
+impl<T> Debug for RingEnd<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RingEnd").field("next", &self.next).field("prev", &self.prev).finish()
+    }
+}
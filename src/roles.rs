@@ -0,0 +1,77 @@
+use std::fmt::Debug;
+
+use crate::{Canceled, Handshake, PullFuture, PushOutcome, Receipt, State};
+
+/// The end of a handshake that can only push, see [`Handshake::into_roles`].
+pub struct PushEnd<T> {
+    handshake: Handshake<T>,
+}
+
+/// The end of a handshake that can only pull, see [`Handshake::into_roles`].
+pub struct PullEnd<T> {
+    handshake: Handshake<T>,
+}
+
+impl<T> Handshake<T> {
+    /// Fixes the direction of an exchange at compile time, this end pushing and `peer` pulling.
+    ///
+    /// Neither can do the other's part, and pushing consumes the end, so a second
+    /// push or a pull after pushing doesn't compile.
+    ///
+    /// # Panics
+    /// If `peer` isn't the other end of this exchange.
+    pub fn into_roles(self, peer: Handshake<T>) -> (PushEnd<T>, PullEnd<T>) {
+        assert!(self.same_channel(&peer), "ends belong to different exchanges");
+        (PushEnd { handshake: self }, PullEnd { handshake: peer })
+    }
+}
+
+impl<T> PushEnd<T> {
+    /// Deposits `value` for the puller, or gives it back if the puller is gone.
+    pub fn push(self, value: T) -> Result<Receipt<T>, T> {
+        match self.handshake.push(value) {
+            PushOutcome::Delivered(receipt) => Ok(receipt),
+            // the puller never pushes, nothing else refuses a value
+            PushOutcome::Occupied(_, value) | PushOutcome::PeerGone(value) => Err(value),
+        }
+    }
+
+    pub fn state(&self) -> State {
+        self.handshake.state()
+    }
+}
+
+impl<T> PullEnd<T> {
+    /// Takes the pushed value.
+    ///
+    /// Gives back the end when nothing was pushed yet, or [`Canceled`] when the pusher is gone.
+    pub fn try_pull(self) -> Result<Result<T, Self>, Canceled> {
+        Ok(self.handshake.pull_now().into_result()?.map_err(|handshake| PullEnd { handshake }))
+    }
+
+    /// Blocks until the value was pushed, or [`Canceled`] once the pusher is gone.
+    pub fn recv(self) -> Result<T, Canceled> {
+        self.handshake.pull_blocking()
+    }
+
+    /// Waits for the pushed value, see [`Handshake::pull`].
+    pub fn pull(self) -> PullFuture<T> {
+        self.handshake.pull()
+    }
+
+    pub fn state(&self) -> State {
+        self.handshake.state()
+    }
+}
+
+impl<T> Debug for PushEnd<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PushEnd").field("state", &self.state()).finish()
+    }
+}
+
+impl<T> Debug for PullEnd<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PullEnd").field("state", &self.state()).finish()
+    }
+}
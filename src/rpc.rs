@@ -0,0 +1,91 @@
+use std::fmt::Debug;
+
+use crate::{Canceled, Handshake, PullFuture, PushOutcome};
+
+// a request travels along with the end to answer it on
+type Request<Req, Resp> = (Req, Reply<Resp>);
+
+/// Creates a linked caller and callee for a single request and its answer.
+pub fn rpc<Req, Resp>() -> (Caller<Req, Resp>, Callee<Req, Resp>) {
+    let (caller, callee) = Handshake::new();
+    (Caller { request: caller }, Callee { request: callee })
+}
+
+/// The side of an [`rpc`] sending the request.
+pub struct Caller<Req, Resp> {
+    request: Handshake<Request<Req, Resp>>,
+}
+
+impl<Req, Resp> Caller<Req, Resp> {
+    /// Sends `request`, blocking until the callee answers.
+    pub fn call(self, request: Req) -> Result<Resp, Canceled> {
+        self.send(request).map_err(|_| Canceled)?.into_handshake().ok_or(Canceled)?.pull_blocking()
+    }
+
+    /// Sends `request`, returning the answer to await.
+    ///
+    /// Gives the request back when the callee is gone.
+    pub fn send(self, request: Req) -> Result<PullFuture<Resp>, Req> {
+        let (reply, answer) = Handshake::new();
+        match self.request.push((request, Reply { reply })) {
+            PushOutcome::Delivered(_) => Ok(answer.pull()),
+            // the callee never pushes on the request, so only a gone callee refuses it
+            PushOutcome::Occupied(_, (request, _)) | PushOutcome::PeerGone((request, _)) => Err(request),
+        }
+    }
+}
+
+/// The side of an [`rpc`] answering the request.
+pub struct Callee<Req, Resp> {
+    request: Handshake<Request<Req, Resp>>,
+}
+
+impl<Req, Resp> Callee<Req, Resp> {
+    /// Blocks until the request arrives, handing it over along with the end to answer on.
+    pub fn accept(self) -> Result<(Req, Reply<Resp>), Canceled> {
+        self.request.pull_blocking()
+    }
+
+    /// Takes the request if it already arrived, otherwise gives the callee back.
+    pub fn try_accept(self) -> Result<Result<(Req, Reply<Resp>), Self>, Canceled> {
+        Ok(self.request.pull_now().into_result()?.map_err(|request| Callee { request }))
+    }
+
+    /// Waits for the request, see [`accept`](Self::accept).
+    pub fn accept_async(self) -> PullFuture<(Req, Reply<Resp>)> {
+        self.request.pull()
+    }
+}
+
+/// Answers a request taken by a [`Callee`], dropping it cancels the caller.
+pub struct Reply<Resp> {
+    reply: Handshake<Resp>,
+}
+
+impl<Resp> Reply<Resp> {
+    /// Hands `response` to the caller, or gives it back when the caller is gone.
+    pub fn respond(self, response: Resp) -> Result<(), Resp> {
+        match self.reply.push(response) {
+            PushOutcome::Delivered(_) => Ok(()),
+            PushOutcome::Occupied(_, response) | PushOutcome::PeerGone(response) => Err(response),
+        }
+    }
+}
+
+impl<Req, Resp> Debug for Caller<Req, Resp> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Caller").field("request", &self.request).finish()
+    }
+}
+
+impl<Req, Resp> Debug for Callee<Req, Resp> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Callee").field("request", &self.request).finish()
+    }
+}
+
+impl<Resp> Debug for Reply<Resp> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Reply").field("reply", &self.reply).finish()
+    }
+}
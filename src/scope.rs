@@ -0,0 +1,134 @@
+use std::{
+    fmt::Debug,
+    sync::{Arc, Mutex, MutexGuard, PoisonError, Weak},
+    task::Poll,
+};
+
+use crate::{relay, Handshake, Watcher};
+
+/// Cancels every exchange made in it and in its child scopes at once, e.g. to
+/// tear down a tree of dependent exchanges.
+///
+/// Clones share the scope, dropping them cancels nothing.
+#[derive(Clone, Default)]
+pub struct CancelScope {
+    inner: Arc<Inner>,
+}
+
+#[derive(Default)]
+struct Inner {
+    // `None` once canceled
+    links: Mutex<Option<Vec<Box<dyn Link>>>>,
+}
+
+// something canceled along with a scope
+trait Link: Send {
+    fn cancel(&self);
+    // nothing left to cancel, to be dropped from the scope
+    fn is_finished(&self) -> bool;
+}
+
+impl<T: Send> Link for Watcher<T> {
+    fn cancel(&self) {
+        self.common().cancel()
+    }
+
+    fn is_finished(&self) -> bool {
+        Watcher::is_finished(self)
+    }
+}
+
+impl Link for Weak<Inner> {
+    fn cancel(&self) {
+        if let Some(inner) = self.upgrade() { inner.cancel() }
+    }
+
+    fn is_finished(&self) -> bool {
+        self.upgrade().is_none_or(|inner| inner.is_canceled())
+    }
+}
+
+impl Inner {
+    fn lock(&self) -> MutexGuard<'_, Option<Vec<Box<dyn Link>>>> {
+        self.links.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn is_canceled(&self) -> bool {
+        self.lock().is_none()
+    }
+
+    // cancels `link` right away should the scope be canceled already
+    fn link(&self, link: Box<dyn Link>) {
+        let mut links = self.lock();
+        let Some(linked) = links.as_mut() else {
+            drop(links);
+            return link.cancel()
+        };
+        linked.retain(|link| !link.is_finished());
+        linked.push(link)
+    }
+
+    fn cancel(&self) {
+        let Some(links) = self.lock().take() else { return };
+        // outside the lock, the waiters woken may link more
+        for link in links { link.cancel() }
+    }
+}
+
+impl CancelScope {
+    pub fn new() -> CancelScope {
+        CancelScope::default()
+    }
+
+    /// Creates a pair canceled along with this scope, as if the peer went away,
+    /// a value waiting to be pulled lost then.
+    pub fn pair<T: Send + 'static>(&self) -> (Handshake<T>, Handshake<T>) {
+        let (u, v) = Handshake::new();
+        self.inner.link(Box::new(u.watcher()));
+        (u, v)
+    }
+
+    /// Creates a scope canceled along with this one, but not the other way around.
+    pub fn child(&self) -> CancelScope {
+        let child = CancelScope::new();
+        self.inner.link(Box::new(Arc::downgrade(&child.inner)));
+        child
+    }
+
+    /// Cancels every exchange made in this scope and its children that is still
+    /// going, waking their waiters with [`Canceled`](crate::Canceled).
+    pub fn cancel(&self) {
+        self.inner.cancel()
+    }
+
+    pub fn is_canceled(&self) -> bool {
+        self.inner.is_canceled()
+    }
+}
+
+impl<T: Send + 'static> Handshake<T> {
+    /// Creates a pair canceled once this exchange is, as if the peer went away,
+    /// a value waiting to be pulled lost then.
+    ///
+    /// The child carries on should this exchange complete instead.
+    pub fn new_child<U: Send + 'static>(&self) -> (Handshake<U>, Handshake<U>) {
+        let (u, v) = Handshake::new();
+        let (parent, child) = (self.watcher(), u.watcher());
+        relay::spawn(move |cx| {
+            if parent.is_canceled() { child.common().cancel() }
+            if parent.is_finished() || child.is_finished() { return Poll::Ready(()) }
+            parent.common().register(cx);
+            child.common().register(cx);
+            // re-check after registering so a wake-up can't slip through
+            if parent.is_canceled() { child.common().cancel() }
+            if parent.is_finished() || child.is_finished() { Poll::Ready(()) } else { Poll::Pending }
+        });
+        (u, v)
+    }
+}
+
+impl Debug for CancelScope {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CancelScope").field("canceled", &self.is_canceled()).finish_non_exhaustive()
+    }
+}
@@ -0,0 +1,125 @@
+use std::{fmt::Debug, marker::PhantomData, panic, ptr::NonNull, thread::Scope};
+
+use crate::{common::Common, Canceled, Handshake, PushOutcome, State};
+
+/// One end of a handshake whose slot lives on the stack, see [`Handshake::scoped`].
+pub struct ScopedHandshake<'s, T> {
+    handshake: Handshake<T>,
+    // borrows the slot in `scoped`'s frame
+    _slot: PhantomData<&'s ()>,
+}
+
+impl<T> Handshake<T> {
+    /// Runs `f` with both ends of a handshake whose slot lives on the stack,
+    /// sparing the allocation for exchanges that never outlive the call.
+    pub fn scoped<R>(f: impl for<'s> FnOnce(ScopedHandshake<'s, T>, ScopedHandshake<'s, T>) -> R) -> R {
+        // one reference more than the ends hold, so releasing never frees the slot
+        let common = Common::new(3);
+        let ptr = NonNull::from(&common);
+        let end = || ScopedHandshake { handshake: Handshake::from_common(ptr), _slot: PhantomData };
+        // both ends are gone by the time `f` returns, the slot drops with the frame
+        f(end(), end())
+    }
+}
+
+/// Runs `f` and `g` on threads of `scope`, each with one end of a fresh handshake,
+/// and returns both results once both are done.
+///
+/// A panic on either thread is resumed on the caller after both finished,
+/// the other having seen its end canceled.
+pub fn exchange_between<'scope, T, A, B, F, G>(scope: &'scope Scope<'scope, '_>, f: F, g: G) -> (A, B)
+where
+    T: Send + 'scope,
+    A: Send + 'scope,
+    B: Send + 'scope,
+    F: FnOnce(Handshake<T>) -> A + Send + 'scope,
+    G: FnOnce(Handshake<T>) -> B + Send + 'scope,
+{
+    let (u, v) = Handshake::new();
+    let (a, b) = (scope.spawn(move || f(u)), scope.spawn(move || g(v)));
+    let (a, b) = (a.join(), b.join());
+    (a.unwrap_or_else(|e| panic::resume_unwind(e)), b.unwrap_or_else(|e| panic::resume_unwind(e)))
+}
+
+/// Runs `f` and `g` on threads of their own, each with one end of a handshake
+/// that may carry borrows of the caller's data, e.g. `&mut` buffers lent to the
+/// other side instead of copied.
+///
+/// Both threads are joined before this returns, so nothing exchanged outlives
+/// the borrows it holds. Panics are resumed like with [`exchange_between`].
+pub fn lend_between<'a, T, A, B, F, G>(f: F, g: G) -> (A, B)
+where
+    T: Send + 'a,
+    A: Send,
+    B: Send,
+    F: FnOnce(Handshake<T>) -> A + Send,
+    G: FnOnce(Handshake<T>) -> B + Send,
+{
+    std::thread::scope(|s| exchange_between(s, f, g))
+}
+
+/// Computes a value with each of `f` and `g` on a thread of its own, joins
+/// them, and returns what `combine` made of the two.
+///
+/// `combine` runs on whichever thread arrives second, with the other's value first.
+pub fn spawn_joined<T, U, F, G, C>(f: F, g: G, combine: C) -> U
+where
+    T: Send,
+    U: Send,
+    F: FnOnce() -> T + Send,
+    G: FnOnce() -> T + Send,
+    C: Fn(T, T) -> U + Sync,
+{
+    let combine = &combine;
+    let (a, b) = std::thread::scope(|s| {
+        exchange_between(s, move |u| u.join(f(), combine), move |v| v.join(g(), combine))
+    });
+    // neither cancels without panicking, which was resumed
+    match (a, b) {
+        (Ok(Some(combined)), _) | (_, Ok(Some(combined))) => combined,
+        _ => unreachable!("one side combines"),
+    }
+}
+
+impl<'s, T> ScopedHandshake<'s, T> {
+    fn wrap(handshake: Handshake<T>) -> ScopedHandshake<'s, T> {
+        ScopedHandshake { handshake, _slot: PhantomData }
+    }
+
+    pub fn join<U, F: FnOnce(T, T) -> U>(self, value: T, f: F) -> Result<Option<U>, Canceled> {
+        self.handshake.join(value, f)
+    }
+
+    /// Deposits `value` for the peer.
+    ///
+    /// Gives back the end and value when the peer already pushed (pull instead),
+    /// or just the value when the peer is gone.
+    pub fn try_push(self, value: T) -> Result<Result<(), (Self, T)>, T> {
+        match self.handshake.push(value) {
+            PushOutcome::Delivered(_) => Ok(Ok(())),
+            PushOutcome::Occupied(handshake, value) => Ok(Err((ScopedHandshake::wrap(handshake), value))),
+            PushOutcome::PeerGone(value) => Err(value),
+        }
+    }
+
+    /// Takes the value deposited by the peer.
+    ///
+    /// Gives back the end when nothing was pushed yet, or [`Canceled`] when the peer is gone.
+    pub fn try_pull(self) -> Result<Result<T, Self>, Canceled> {
+        Ok(self.handshake.pull_now().into_result()?.map_err(ScopedHandshake::wrap))
+    }
+
+    pub fn is_set(&self) -> bool {
+        self.handshake.is_set()
+    }
+
+    pub fn state(&self) -> State {
+        self.handshake.state()
+    }
+}
+
+impl<T> Debug for ScopedHandshake<'_, T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ScopedHandshake").field("state", &self.state()).finish()
+    }
+}
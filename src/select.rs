@@ -0,0 +1,113 @@
+use std::{cmp::Reverse, future::Future, pin::Pin, task::{Context, Poll}};
+
+use crate::{common, sync, Canceled, Handshake, PullOutcome};
+
+/// Blocks until any of `handshakes` is pushed to or canceled, removing it and
+/// returning its index along with the outcome of pulling from it.
+///
+/// Parks between checks rather than spinning, so pending handshakes cost nothing while waiting.
+///
+/// # Panics
+/// If `handshakes` is empty, as nothing could ever complete.
+pub fn select_pull<T>(handshakes: &mut Vec<Handshake<T>>) -> (usize, Result<T, Canceled>) {
+    select_pull_by_key(handshakes, |_, _| ())
+}
+
+/// Like [`select_pull`], picking the handshake with the greatest `key` when several
+/// are ready at once, and the first of those on ties.
+///
+/// `key` is handed each ready handshake along with its current index, e.g. to look up
+/// the priority of the task waiting on it.
+///
+/// # Panics
+/// If `handshakes` is empty, as nothing could ever complete.
+pub fn select_pull_by_key<T, K: Ord>(
+    handshakes: &mut Vec<Handshake<T>>, mut key: impl FnMut(usize, &Handshake<T>) -> K,
+) -> (usize, Result<T, Canceled>) {
+    assert!(!handshakes.is_empty(), "select over no handshakes");
+    let waker = common::thread_waker();
+    let cx = Context::from_waker(&waker);
+    let res = loop {
+        let ready = handshakes.iter().enumerate().filter(|(_, h)| h.is_set());
+        if let Some((i, _)) = ready.max_by_key(|&(i, h)| (key(i, h), Reverse(i))) {
+            match handshakes.remove(i).pull_now() {
+                PullOutcome::Delivered(value) => break (i, Ok(value)),
+                // retracted in the meantime
+                PullOutcome::Pending(handshake) => handshakes.insert(i, handshake),
+                PullOutcome::PeerGone => break (i, Err(Canceled)),
+            }
+            continue;
+        }
+        handshakes.iter().for_each(|h| h.common().register(&cx));
+        // re-check after registering so a wake-up can't slip through
+        if !handshakes.iter().any(Handshake::is_set) { sync::park() }
+    };
+    handshakes.iter().for_each(|h| h.common().unregister(&waker));
+    res
+}
+
+/// Pulls from many handshakes at once, yielding `(index, outcome)` pairs in completion order.
+///
+/// Indices refer to the position in the collection the select was built from.
+#[derive(Debug)]
+pub struct Select<T> {
+    handshakes: Vec<Option<Handshake<T>>>,
+    remaining: usize,
+}
+
+impl<T> Select<T> {
+    pub fn new(handshakes: impl IntoIterator<Item = Handshake<T>>) -> Select<T> {
+        let handshakes: Vec<_> = handshakes.into_iter().map(Some).collect();
+        Select { remaining: handshakes.len(), handshakes }
+    }
+
+    /// Number of handshakes still pending.
+    pub fn len(&self) -> usize {
+        self.remaining
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.remaining == 0
+    }
+
+    /// Resolves with the next completion, or `None` once every handshake completed.
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> Next<'_, T> {
+        Next { select: self }
+    }
+
+    pub fn poll_next(&mut self, cx: &mut Context<'_>) -> Poll<Option<(usize, Result<T, Canceled>)>> {
+        if let Some(res) = self.ready() { return Poll::Ready(Some(res)); }
+        if self.remaining == 0 { return Poll::Ready(None); }
+        self.handshakes.iter().flatten().for_each(|h| h.common().register(cx));
+        // re-check after registering so a wake-up can't slip through
+        self.ready().map_or(Poll::Pending, |res| Poll::Ready(Some(res)))
+    }
+
+    fn ready(&mut self) -> Option<(usize, Result<T, Canceled>)> {
+        for (i, entry) in self.handshakes.iter_mut().enumerate() {
+            if !entry.as_ref().is_some_and(Handshake::is_set) { continue; }
+            match entry.take().unwrap().pull_now() {
+                PullOutcome::Delivered(value) => { self.remaining -= 1; return Some((i, Ok(value))) }
+                // retracted in the meantime
+                PullOutcome::Pending(handshake) => *entry = Some(handshake),
+                PullOutcome::PeerGone => { self.remaining -= 1; return Some((i, Err(Canceled))) }
+            }
+        }
+        None
+    }
+}
+
+/// Future returned by [`Select::next`].
+#[derive(Debug)]
+pub struct Next<'a, T> {
+    select: &'a mut Select<T>,
+}
+
+impl<T> Future for Next<'_, T> {
+    type Output = Option<(usize, Result<T, Canceled>)>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.select.poll_next(cx)
+    }
+}
@@ -0,0 +1,34 @@
+use std::sync::Arc;
+
+use crate::{common, Canceled, Handshake, Receipt, Watcher};
+
+impl<T> Handshake<Arc<T>> {
+    /// Collects the peer's value without taking it, leaving it in place for the
+    /// peer's receipt and any watchers to clone as well, e.g. for a publication
+    /// both parties keep.
+    ///
+    /// Counts as the peer's value being consumed, and can be repeated for more
+    /// clones. Pulling afterwards sees the exchange as over. Gives `Ok(None)`
+    /// while nothing was pushed yet, or [`Canceled`] when the peer is gone.
+    pub fn pull_shared(&self) -> Result<Option<Arc<T>>, Canceled> {
+        match self.common().share() {
+            Ok(value) => Ok(Some(Arc::clone(value))),
+            Err(common::EMPTY) => Ok(None),
+            Err(_) => Err(Canceled),
+        }
+    }
+}
+
+impl<T> Receipt<Arc<T>> {
+    /// The pushed value once the peer collected it with [`Handshake::pull_shared`].
+    pub fn shared(&self) -> Option<Arc<T>> {
+        self.common().shared().map(Arc::clone)
+    }
+}
+
+impl<T> Watcher<Arc<T>> {
+    /// The value once collected with [`Handshake::pull_shared`].
+    pub fn shared(&self) -> Option<Arc<T>> {
+        self.common().shared().map(Arc::clone)
+    }
+}
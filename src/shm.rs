@@ -0,0 +1,335 @@
+//! Handshakes between processes, with the slot placed in a shared memory segment.
+//!
+//! One process [creates](ShmHandshake::create) the segment and hands the returned
+//! descriptor to the other (by inheritance or over a socket), which
+//! [opens](ShmHandshake::open) it. Payloads are copied byte for byte, so `T` must
+//! be [`Pod`]: plain data that any bytes the peer wrote make a valid value of.
+//!
+//! Descriptors themselves, sockets, pipes or such a segment, go through an
+//! [`FdHandshake`] instead, passed over a Unix socket with `SCM_RIGHTS`.
+
+use std::{
+    cell::UnsafeCell,
+    fmt::Debug,
+    io,
+    marker::PhantomData,
+    mem::MaybeUninit,
+    os::{fd::{AsRawFd, FromRawFd, OwnedFd}, unix::net::UnixStream},
+    ptr::NonNull,
+    sync::atomic::{AtomicU8, Ordering},
+};
+
+use crate::{common::{BUSY, CANCELED, EMPTY, SET, TAKEN}, Canceled, State};
+
+/// Plain data, valid whatever its bytes are, so another process can write it.
+///
+/// # Safety
+///
+/// Every bit pattern of `size_of::<Self>()` bytes must be a valid `Self`: no
+/// padding, references, pointers, `bool`s, `char`s or enums, nothing owning a resource.
+pub unsafe trait Pod: Copy {}
+
+macro_rules! pod {
+    ($($ty:ty),*) => {$(
+        unsafe impl Pod for $ty {}
+    )*};
+}
+
+pod!(u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize, f32, f64);
+
+unsafe impl<T: Pod, const N: usize> Pod for [T; N] {}
+
+#[repr(C)]
+struct Region<T> {
+    state: AtomicU8,
+    value: UnsafeCell<MaybeUninit<T>>,
+}
+
+/// One end of a handshake shared with another process.
+///
+/// A peer process dying without dropping its end counts as canceling the
+/// handshake: both ends hold a socket to each other, which the kernel closes
+/// with the process.
+pub struct ShmHandshake<T: Pod> {
+    region: NonNull<Region<T>>,
+    // hung up once the peer end is gone, wherever that process lives
+    peer: UnixStream,
+    _value: PhantomData<T>,
+}
+
+impl<T: Pod> ShmHandshake<T> {
+    /// Creates a segment, returning this process's end and the descriptor for the peer to open.
+    ///
+    /// The peer counts as gone once every copy of the descriptor was closed without opening it.
+    pub fn create() -> io::Result<(ShmHandshake<T>, OwnedFd)> {
+        let fd = unsafe { libc::memfd_create(c"handshake".as_ptr(), libc::MFD_CLOEXEC) };
+        if fd < 0 { return Err(io::Error::last_os_error()); }
+        // owned from here on, closed on error
+        let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+        if unsafe { libc::ftruncate(fd.as_raw_fd(), size_of::<Region<T>>() as libc::off_t) } < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        // the segment waits in the socket for the peer to open it
+        let (ours, theirs) = UnixStream::pair()?;
+        send_fd(&ours, &fd)?;
+        // a fresh segment is zeroed, which reads as empty
+        Ok((ShmHandshake::map(&fd, ours)?, theirs.into()))
+    }
+
+    /// Opens the peer end from the descriptor [`create`](Self::create) returned.
+    pub fn open(fd: OwnedFd) -> io::Result<ShmHandshake<T>> {
+        let peer = UnixStream::from(fd);
+        let segment = recv_fd(&peer)?;
+        ShmHandshake::map(&segment, peer)
+    }
+
+    fn map(fd: &OwnedFd, peer: UnixStream) -> io::Result<ShmHandshake<T>> {
+        let ptr = unsafe { libc::mmap(
+            std::ptr::null_mut(),
+            size_of::<Region<T>>(),
+            libc::PROT_READ | libc::PROT_WRITE,
+            libc::MAP_SHARED,
+            fd.as_raw_fd(),
+            0,
+        )};
+        if ptr == libc::MAP_FAILED { return Err(io::Error::last_os_error()); }
+        // mmap never succeeds with null
+        let region = unsafe { NonNull::new_unchecked(ptr.cast::<Region<T>>()) };
+        Ok(ShmHandshake { region, peer, _value: PhantomData })
+    }
+
+    fn region(&self) -> &Region<T> {
+        // mapping outlives the end
+        unsafe { self.region.as_ref() }
+    }
+
+    fn peer_alive(&self) -> bool {
+        // the peer never writes to the socket, so reading anything but "would block" means it hung up
+        let mut byte = 0u8;
+        let read = unsafe {
+            libc::recv(self.peer.as_raw_fd(), (&mut byte as *mut u8).cast(), 1, libc::MSG_PEEK | libc::MSG_DONTWAIT)
+        };
+        read < 0 && io::Error::last_os_error().kind() == io::ErrorKind::WouldBlock
+    }
+
+    // moves from `from` to `to`, waiting out any `BUSY` period,
+    // otherwise returns the state that was found instead
+    fn transition(&self, from: u8, to: u8) -> Result<(), u8> {
+        let state = &self.region().state;
+        loop {
+            match state.compare_exchange_weak(from, to, Ordering::AcqRel, Ordering::Acquire) {
+                Ok(_) => return Ok(()),
+                Err(BUSY) if self.peer_alive() => std::thread::yield_now(),
+                // peer died mid-move, nothing sane left in the slot
+                Err(BUSY) => return Err(CANCELED),
+                Err(state) if state != from => return Err(state),
+                Err(_) => {} // spurious failure
+            }
+        }
+    }
+
+    fn settle(&self, state: u8) {
+        self.region().state.store(state, Ordering::Release)
+    }
+
+    /// Deposits `value` for the peer.
+    ///
+    /// Gives back the end and value when the peer already pushed (pull instead),
+    /// or just the value when the peer is gone.
+    pub fn try_push(self, value: T) -> Result<Result<(), (Self, T)>, T> {
+        if !self.peer_alive() { return Err(value); }
+        match self.transition(EMPTY, BUSY) {
+            Ok(()) => {
+                // unique access while busy
+                unsafe { (*self.region().value.get()).write(value) };
+                self.settle(SET);
+                self.unmap();
+                Ok(Ok(()))
+            }
+            Err(SET) => Ok(Err((self, value))),
+            Err(_) => Err(value),
+        }
+    }
+
+    /// Takes the value deposited by the peer.
+    ///
+    /// Gives back the end when nothing was pushed yet, or [`Canceled`] when the peer is gone.
+    pub fn try_pull(self) -> Result<Result<T, Self>, Canceled> {
+        match self.transition(SET, TAKEN) {
+            Ok(()) => {
+                // taken is final, nobody else touches the value
+                let value = unsafe { (*self.region().value.get()).assume_init_read() };
+                self.unmap();
+                Ok(Ok(value))
+            }
+            Err(EMPTY) if self.peer_alive() => Ok(Err(self)),
+            Err(_) => Err(Canceled),
+        }
+    }
+
+    pub fn is_set(&self) -> bool {
+        self.region().state.load(Ordering::Acquire) != EMPTY || !self.peer_alive()
+    }
+
+    pub fn state(&self) -> State {
+        match self.region().state.load(Ordering::Acquire) {
+            SET => State::Set,
+            TAKEN => State::TakenByPeer,
+            CANCELED => State::PeerGone,
+            _ if !self.peer_alive() => State::PeerGone,
+            _ => State::Empty,
+        }
+    }
+
+    // consumes `self` without cancelling
+    fn unmap(self) {
+        let this = std::mem::ManuallyDrop::new(self);
+        unsafe {
+            libc::munmap(this.region.as_ptr().cast(), size_of::<Region<T>>());
+            // hangs up on the peer
+            drop(std::ptr::read(&this.peer));
+        }
+    }
+}
+
+impl<T: Pod> Drop for ShmHandshake<T> {
+    fn drop(&mut self) {
+        let _ = self.transition(EMPTY, CANCELED);
+        unsafe { libc::munmap(self.region.as_ptr().cast(), size_of::<Region<T>>()) };
+    }
+}
+
+unsafe impl<T: Pod + Send> Sync for ShmHandshake<T> {}
+
+unsafe impl<T: Pod + Send> Send for ShmHandshake<T> {}
+
+impl<T: Pod> Debug for ShmHandshake<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ShmHandshake").field("state", &self.state()).finish()
+    }
+}
+
+/// One end of a handshake passing a file descriptor to another process over a
+/// Unix socket, e.g. one half of a [`pair`](Self::pair) inherited by a child.
+///
+/// The descriptor is duplicated into the receiving process. A peer hanging up
+/// without pushing counts as canceling, reported as [`io::ErrorKind::UnexpectedEof`].
+#[derive(Debug)]
+pub struct FdHandshake {
+    stream: UnixStream,
+}
+
+impl FdHandshake {
+    pub fn pair() -> io::Result<(FdHandshake, FdHandshake)> {
+        let (a, b) = UnixStream::pair()?;
+        Ok((FdHandshake { stream: a }, FdHandshake { stream: b }))
+    }
+
+    /// Uses a connected socket shared with the peer, e.g. accepted on a listener.
+    pub fn from_stream(stream: UnixStream) -> FdHandshake {
+        FdHandshake { stream }
+    }
+
+    /// Passes `fd` to the peer, which need not be pulling yet.
+    pub fn push(self, fd: OwnedFd) -> io::Result<()> {
+        self.send(&fd)
+    }
+
+    /// Blocks until the peer passed a descriptor and takes it.
+    pub fn pull(self) -> io::Result<OwnedFd> {
+        self.recv()
+    }
+
+    /// Swaps `fd` for the peer's, blocking until the peer meets too.
+    pub fn meet(self, fd: OwnedFd) -> io::Result<OwnedFd> {
+        // the socket buffers ours, neither side waits on the other to send
+        self.send(&fd)?;
+        self.recv()
+    }
+
+    fn send(&self, fd: &OwnedFd) -> io::Result<()> {
+        send_fd(&self.stream, fd)
+    }
+
+    fn recv(&self) -> io::Result<OwnedFd> {
+        recv_fd(&self.stream)
+    }
+}
+
+// passes a duplicate of `fd` over `stream`, with a byte for it to ride on
+fn send_fd(stream: &UnixStream, fd: &OwnedFd) -> io::Result<()> {
+    let mut byte = [0u8];
+    let mut iov = libc::iovec { iov_base: byte.as_mut_ptr().cast(), iov_len: 1 };
+    let mut control = Control::new();
+    let mut msg = control.header(&mut iov);
+    unsafe {
+        // room for one descriptor was made above
+        let cmsg = libc::CMSG_FIRSTHDR(&msg);
+        (*cmsg).cmsg_level = libc::SOL_SOCKET;
+        (*cmsg).cmsg_type = libc::SCM_RIGHTS;
+        (*cmsg).cmsg_len = libc::CMSG_LEN(size_of::<libc::c_int>() as u32) as _;
+        libc::CMSG_DATA(cmsg).cast::<libc::c_int>().write_unaligned(fd.as_raw_fd());
+        msg.msg_controllen = libc::CMSG_SPACE(size_of::<libc::c_int>() as u32) as _;
+    }
+    if unsafe { libc::sendmsg(stream.as_raw_fd(), &msg, libc::MSG_NOSIGNAL) } < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+// blocks until a descriptor arrives over `stream` and takes it
+fn recv_fd(stream: &UnixStream) -> io::Result<OwnedFd> {
+    let mut byte = [0u8];
+    let mut iov = libc::iovec { iov_base: byte.as_mut_ptr().cast(), iov_len: 1 };
+    let mut control = Control::new();
+    let mut msg = control.header(&mut iov);
+    let received = unsafe { libc::recvmsg(stream.as_raw_fd(), &mut msg, libc::MSG_CMSG_CLOEXEC) };
+    if received < 0 { return Err(io::Error::last_os_error()); }
+    if received == 0 { return Err(io::ErrorKind::UnexpectedEof.into()); }
+    // every descriptor that came along is ours to close, even when refusing the message
+    let mut fds = Vec::new();
+    unsafe {
+        let mut cmsg = libc::CMSG_FIRSTHDR(&msg);
+        while !cmsg.is_null() {
+            let len = ((*cmsg).cmsg_len as usize).saturating_sub(libc::CMSG_LEN(0) as usize);
+            if (*cmsg).cmsg_level == libc::SOL_SOCKET && (*cmsg).cmsg_type == libc::SCM_RIGHTS {
+                let data = libc::CMSG_DATA(cmsg).cast::<libc::c_int>();
+                // the kernel installed them in this process, owned from here on
+                fds.extend((0..len / size_of::<libc::c_int>()).map(|i| OwnedFd::from_raw_fd(data.add(i).read_unaligned())));
+            }
+            cmsg = libc::CMSG_NXTHDR(&msg, cmsg);
+        }
+    }
+    if msg.msg_flags & libc::MSG_CTRUNC != 0 {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "descriptors cut off"));
+    }
+    match <[OwnedFd; 1]>::try_from(fds) {
+        Ok([fd]) => Ok(fd),
+        Err(fds) if fds.is_empty() => Err(io::Error::new(io::ErrorKind::InvalidData, "no descriptor passed")),
+        Err(_) => Err(io::Error::new(io::ErrorKind::InvalidData, "more than one descriptor passed")),
+    }
+}
+
+// ancillary data carrying a single descriptor, aligned for the headers in it
+#[repr(C)]
+union Control {
+    _header: libc::cmsghdr,
+    // generous, `CMSG_SPACE` isn't const on every target
+    bytes: [u8; 64],
+}
+
+impl Control {
+    fn new() -> Control {
+        Control { bytes: [0; 64] }
+    }
+
+    fn header(&mut self, iov: &mut libc::iovec) -> libc::msghdr {
+        // zeroed is a valid empty header
+        let mut msg: libc::msghdr = unsafe { std::mem::zeroed() };
+        msg.msg_iov = iov;
+        msg.msg_iovlen = 1;
+        msg.msg_control = (self as *mut Control).cast();
+        msg.msg_controllen = size_of::<Control>() as _;
+        msg
+    }
+}
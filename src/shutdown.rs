@@ -0,0 +1,42 @@
+use std::{fmt::Debug, time::Duration};
+
+use crate::{Canceled, Handshake};
+
+/// One side of two services confirming to each other that they are ready to shut down.
+///
+/// Each side calls [`ready`](Self::ready) with a report, e.g. what it still had
+/// queued, and gets the other side's report once both are ready. A side going
+/// away without reporting cancels the other.
+pub struct ShutdownHandshake<R> {
+    handshake: Handshake<R>,
+}
+
+impl<R> ShutdownHandshake<R> {
+    pub fn new() -> (ShutdownHandshake<R>, ShutdownHandshake<R>) {
+        let (u, v) = Handshake::new();
+        (ShutdownHandshake { handshake: u }, ShutdownHandshake { handshake: v })
+    }
+
+    /// Blocks until the other side is ready too, returning its report.
+    pub fn ready(self, report: R) -> Result<R, Canceled> {
+        self.handshake.meet(report)
+    }
+
+    /// Like [`ready`](Self::ready), but gives back this side and its report if
+    /// the other one isn't ready within `timeout`, to retry or shut down regardless.
+    pub fn ready_timeout(self, report: R, timeout: Duration) -> Result<Result<R, (Self, R)>, Canceled> {
+        Ok(self.handshake.meet_timeout(report, timeout)?
+            .map_err(|(handshake, report)| (ShutdownHandshake { handshake }, report)))
+    }
+
+    /// Whether the other side is already waiting with its report.
+    pub fn is_peer_ready(&self) -> bool {
+        self.handshake.is_set()
+    }
+}
+
+impl<R> Debug for ShutdownHandshake<R> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ShutdownHandshake").field("state", &self.handshake.state()).finish()
+    }
+}
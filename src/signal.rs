@@ -0,0 +1,72 @@
+use std::{cell::UnsafeCell, fmt::Debug, mem::MaybeUninit};
+
+use crate::{common::{BUSY, EMPTY, SET}, sync::{AtomicU8, Ordering}};
+
+/// A reusable slot for handing values out of a Unix signal handler or a real-time
+/// thread, placed in a `static` or shared by reference.
+///
+/// Pushing and pulling are wait-free and async-signal-safe: a compare-and-swap and
+/// a store, no locks, no allocation, no syscalls and no waiting, so either simply
+/// fails while the slot is in use. There are no wakers either, the receiving
+/// thread polls (e.g. once per audio buffer, or after a self-pipe write). With the
+/// `portable-atomic` feature, only on targets with native atomics.
+pub struct SignalHandshake<T> {
+    state: AtomicU8,
+    value: UnsafeCell<MaybeUninit<T>>,
+}
+
+impl<T> SignalHandshake<T> {
+    pub const fn new() -> SignalHandshake<T> {
+        SignalHandshake { state: AtomicU8::new(EMPTY), value: UnsafeCell::new(MaybeUninit::uninit()) }
+    }
+
+    /// Deposits `value`, giving it back if a value is still waiting or being pulled.
+    pub fn try_push(&self, value: T) -> Result<(), T> {
+        // never waits, the pull it would wait for may be the code this handler interrupted
+        if self.state.compare_exchange(EMPTY, BUSY, Ordering::Acquire, Ordering::Relaxed).is_err() {
+            return Err(value);
+        }
+        // unique access while busy
+        unsafe { (*self.value.get()).write(value) };
+        self.state.store(SET, Ordering::Release);
+        Ok(())
+    }
+
+    /// Takes the waiting value, if any, leaving the slot empty for the next push.
+    pub fn try_pull(&self) -> Option<T> {
+        self.state.compare_exchange(SET, BUSY, Ordering::Acquire, Ordering::Relaxed).ok()?;
+        // unique access while busy
+        let value = unsafe { (*self.value.get()).assume_init_read() };
+        self.state.store(EMPTY, Ordering::Release);
+        Some(value)
+    }
+
+    pub fn is_set(&self) -> bool {
+        self.state.load(Ordering::Acquire) == SET
+    }
+}
+
+impl<T> Default for SignalHandshake<T> {
+    fn default() -> Self {
+        SignalHandshake::new()
+    }
+}
+
+impl<T> Drop for SignalHandshake<T> {
+    fn drop(&mut self) {
+        if self.state.load(Ordering::Acquire) == SET {
+            // value never collected
+            unsafe { self.value.get_mut().assume_init_drop() };
+        }
+    }
+}
+
+unsafe impl<T: Send> Sync for SignalHandshake<T> {}
+
+unsafe impl<T: Send> Send for SignalHandshake<T> {}
+
+impl<T> Debug for SignalHandshake<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SignalHandshake").field("set", &self.is_set()).finish()
+    }
+}
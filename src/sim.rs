@@ -0,0 +1,129 @@
+//! A seeded, single-threaded scheduler interleaving many logical threads
+//! deterministically, to reproduce ordering-dependent bugs.
+//!
+//! Logical threads are futures driven by a [`Simulation`], which picks the next
+//! one to poll among those woken with a generator seeded by the caller, so the
+//! same seed always replays the same interleaving and other seeds explore others.
+//! They must stick to the async side of handshakes: a blocking call parks the
+//! only thread there is.
+
+use std::{
+    collections::BTreeSet,
+    fmt::Debug,
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Mutex, PoisonError},
+    task::{Context, Poll, Wake, Waker},
+};
+
+type Task<'a> = Pin<Box<dyn Future<Output = ()> + 'a>>;
+
+/// Drives logical threads in an order picked by a seeded generator, see the [module docs](self).
+pub struct Simulation<'a> {
+    state: u64,
+    tasks: Vec<Option<Task<'a>>>,
+    // woken tasks, ordered so picking among them only depends on the seed
+    ready: Arc<Mutex<BTreeSet<usize>>>,
+}
+
+/// What a [`Simulation::run`] did.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Report {
+    /// The logical threads in the order they were polled, to compare runs.
+    pub trace: Vec<usize>,
+    /// Logical threads left waiting with nothing to wake them, e.g. deadlocked.
+    pub stuck: Vec<usize>,
+}
+
+struct TaskWaker {
+    id: usize,
+    ready: Arc<Mutex<BTreeSet<usize>>>,
+}
+
+impl Wake for TaskWaker {
+    fn wake(self: Arc<Self>) {
+        self.wake_by_ref()
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        self.ready.lock().unwrap_or_else(PoisonError::into_inner).insert(self.id);
+    }
+}
+
+impl<'a> Simulation<'a> {
+    pub fn new(seed: u64) -> Simulation<'a> {
+        Simulation { state: seed, tasks: Vec::new(), ready: Arc::new(Mutex::new(BTreeSet::new())) }
+    }
+
+    /// Adds a logical thread, returning its index in the [`Report`].
+    pub fn spawn(&mut self, task: impl Future<Output = ()> + 'a) -> usize {
+        let id = self.tasks.len();
+        self.tasks.push(Some(Box::pin(task)));
+        self.ready.lock().unwrap_or_else(PoisonError::into_inner).insert(id);
+        id
+    }
+
+    // splitmix64, small and good enough to spread the picks
+    fn random(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Polls woken logical threads one at a time until none is left to poll.
+    ///
+    /// A panic on a logical thread propagates to the caller.
+    pub fn run(&mut self) -> Report {
+        let mut trace = Vec::new();
+        loop {
+            let random = self.random();
+            let id = {
+                let mut ready = self.ready.lock().unwrap_or_else(PoisonError::into_inner);
+                if ready.is_empty() { break }
+                let id = *ready.iter().nth((random % ready.len() as u64) as usize).unwrap();
+                ready.remove(&id);
+                id
+            };
+            let Some(task) = self.tasks[id].as_mut() else { continue };
+            trace.push(id);
+            let waker = Waker::from(Arc::new(TaskWaker { id, ready: self.ready.clone() }));
+            if task.as_mut().poll(&mut Context::from_waker(&waker)).is_ready() {
+                self.tasks[id] = None;
+            }
+        }
+        let stuck = (0..self.tasks.len()).filter(|&id| self.tasks[id].is_some()).collect();
+        Report { trace, stuck }
+    }
+}
+
+impl Debug for Simulation<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let pending = self.tasks.iter().flatten().count();
+        f.debug_struct("Simulation").field("pending", &pending).finish()
+    }
+}
+
+/// Lets the simulation switch to another logical thread before going on.
+pub fn yield_now() -> YieldNow {
+    YieldNow { yielded: false }
+}
+
+/// Future returned by [`yield_now`].
+#[derive(Debug)]
+pub struct YieldNow {
+    yielded: bool,
+}
+
+impl Future for YieldNow {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if self.yielded { return Poll::Ready(()) }
+        self.yielded = true;
+        cx.waker().wake_by_ref();
+        Poll::Pending
+    }
+}
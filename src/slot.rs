@@ -0,0 +1,150 @@
+//! Handshakes over storage supplied by the user, e.g. static memory or an instrumented slot.
+//!
+//! A [`RawSlot`] implements the exchange itself, a [`SlotHandshake`] the two ends
+//! sharing it, pushing or pulling at most once and canceling when dropped unused.
+//! The crate's own [`DefaultSlot`] is also a building block for protocols of one's
+//! own, e.g. three-party exchanges, with who may do what up to its owner.
+
+use std::{fmt::Debug, marker::PhantomData, mem::ManuallyDrop, sync::Arc, task::Context};
+
+use crate::{common::Common, Canceled, State};
+
+/// Storage for a single exchange, shared by both ends.
+pub trait RawSlot<T> {
+    /// Deposits `value` if nothing is there yet, otherwise hands it back with the state found.
+    fn try_set(&self, value: T) -> Result<(), (State, T)>;
+    /// Takes a deposited value, otherwise returns the state found.
+    fn try_take(&self) -> Result<T, State>;
+    /// Records that an end went away.
+    fn cancel(&self);
+    fn state(&self) -> State;
+}
+
+/// The slot behind [`Handshake`](crate::Handshake), for wrapping, as a default or on its own.
+///
+/// Nothing ties a caller to a side: whoever owns it decides who sets, takes,
+/// retracts or cancels.
+pub struct DefaultSlot<T> {
+    common: Common<T>,
+}
+
+impl<T> DefaultSlot<T> {
+    pub fn new() -> DefaultSlot<T> {
+        // references are counted by the owner
+        DefaultSlot { common: Common::new(0) }
+    }
+
+    /// Takes a deposited value back, leaving the slot empty, otherwise returns the state found.
+    pub fn retract(&self) -> Result<T, State> {
+        self.common.retract().map_err(State::of)
+    }
+
+    /// Registers the waker of `cx` to be woken on the next change of state.
+    pub fn register(&self, cx: &Context<'_>) {
+        self.common.register(cx)
+    }
+
+    /// Blocks the current thread for as long as `pending` holds for the state, returning the state then.
+    pub fn wait_while(&self, pending: impl Fn(State) -> bool) -> State {
+        State::of(self.common.block_while(|state| pending(State::of(state))))
+    }
+}
+
+impl<T> Default for DefaultSlot<T> {
+    fn default() -> Self {
+        DefaultSlot::new()
+    }
+}
+
+impl<T> RawSlot<T> for DefaultSlot<T> {
+    fn try_set(&self, value: T) -> Result<(), (State, T)> {
+        self.common.put(value).map_err(|(state, value)| (State::of(state), value))
+    }
+
+    fn try_take(&self) -> Result<T, State> {
+        self.common.take().map_err(State::of)
+    }
+
+    fn cancel(&self) {
+        self.common.cancel()
+    }
+
+    fn state(&self) -> State {
+        State::of(self.common.state())
+    }
+}
+
+unsafe impl<T: Send> Sync for DefaultSlot<T> {}
+
+unsafe impl<T: Send> Send for DefaultSlot<T> {}
+
+impl<T> Debug for DefaultSlot<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DefaultSlot").field("state", &self.state()).finish()
+    }
+}
+
+/// One end of a handshake over a [`RawSlot`].
+pub struct SlotHandshake<T, S: RawSlot<T>> {
+    slot: Arc<S>,
+    _value: PhantomData<fn(T) -> T>,
+}
+
+impl<T, S: RawSlot<T>> SlotHandshake<T, S> {
+    pub fn new(slot: S) -> (SlotHandshake<T, S>, SlotHandshake<T, S>) {
+        let slot = Arc::new(slot);
+        (SlotHandshake { slot: slot.clone(), _value: PhantomData }, SlotHandshake { slot, _value: PhantomData })
+    }
+
+    // consumes `self` without cancelling
+    fn finish(self) {
+        let end = ManuallyDrop::new(self);
+        // moved out once, never dropped in place
+        drop(unsafe { std::ptr::read(&end.slot) })
+    }
+
+    /// Deposits `value` for the peer.
+    ///
+    /// Gives back the end and value when the peer already pushed (pull instead),
+    /// or just the value when the peer is gone.
+    pub fn try_push(self, value: T) -> Result<Result<(), (Self, T)>, T> {
+        match self.slot.try_set(value) {
+            Ok(()) => { self.finish(); Ok(Ok(())) }
+            // value present, pull instead
+            Err((State::Set, value)) => Ok(Err((self, value))),
+            Err((_, value)) => { self.finish(); Err(value) }
+        }
+    }
+
+    /// Takes the value deposited by the peer.
+    ///
+    /// Gives back the end when nothing was pushed yet, or [`Canceled`] when the peer is gone.
+    pub fn try_pull(self) -> Result<Result<T, Self>, Canceled> {
+        match self.slot.try_take() {
+            Ok(value) => { self.finish(); Ok(Ok(value)) }
+            Err(State::Empty) => Ok(Err(self)),
+            Err(_) => { self.finish(); Err(Canceled) }
+        }
+    }
+
+    pub fn state(&self) -> State {
+        self.slot.state()
+    }
+
+    /// The slot, e.g. to read instrumentation from it.
+    pub fn slot(&self) -> &S {
+        &self.slot
+    }
+}
+
+impl<T, S: RawSlot<T>> Drop for SlotHandshake<T, S> {
+    fn drop(&mut self) {
+        self.slot.cancel()
+    }
+}
+
+impl<T, S: RawSlot<T>> Debug for SlotHandshake<T, S> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SlotHandshake").field("state", &self.state()).finish()
+    }
+}
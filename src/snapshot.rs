@@ -0,0 +1,53 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{common, sync, Handshake};
+
+/// The state of an in-flight exchange, to checkpoint it and [restore](Snapshot::restore) it later.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Snapshot<T> {
+    /// Nothing has been pushed yet.
+    Empty,
+    /// A value is waiting to be pulled.
+    Set(T),
+    /// An end went away, or the exchange is already over.
+    Canceled,
+}
+
+impl<T: Clone> Handshake<T> {
+    /// Captures where the exchange stands, cloning any value waiting in it.
+    pub fn snapshot(&self) -> Snapshot<T> {
+        let mut spins = 0;
+        loop {
+            match self.common().state() {
+                common::EMPTY => return Snapshot::Empty,
+                common::SET => if let Some(value) = self.peek() { return Snapshot::Set(T::clone(&value)) },
+                // a value moving in or being peeked
+                common::BUSY => {}
+                _ => return Snapshot::Canceled,
+            }
+            sync::backoff(&mut spins)
+        }
+    }
+}
+
+impl<T> Snapshot<T> {
+    /// Rebuilds a linked pair in the captured state, returning the end that has
+    /// yet to push, if any, and the end that pulls.
+    ///
+    /// A restored value is deposited as if pushed by the first end, whose receipt is dropped.
+    pub fn restore(self) -> (Option<Handshake<T>>, Handshake<T>) {
+        let (pusher, puller) = Handshake::new();
+        match self {
+            Snapshot::Empty => (Some(pusher), puller),
+            Snapshot::Set(value) => {
+                // fresh pair, the push can't fail
+                drop(pusher.push(value));
+                (None, puller)
+            }
+            Snapshot::Canceled => {
+                drop(pusher);
+                (None, puller)
+            }
+        }
+    }
+}
@@ -0,0 +1,45 @@
+use std::{fmt::Debug, ptr::NonNull};
+
+use crate::{common::{self, Common}, sync::{AtomicBool, Ordering}, Handshake, State};
+
+/// A handshake whose slot lives in a `static`, for exchanges that can't allocate.
+///
+/// Good for a single exchange: the ends are handed out once, and a value left
+/// behind when they are gone is never dropped.
+pub struct StaticHandshake<T> {
+    common: Common<T>,
+    split: AtomicBool,
+}
+
+impl<T> StaticHandshake<T> {
+    pub const fn new() -> StaticHandshake<T> {
+        // one reference more than the ends hold, so releasing never frees the slot
+        StaticHandshake { common: Common::new_uncounted(3), split: AtomicBool::new(false) }
+    }
+
+    /// Hands out both ends, or `None` if they were handed out before.
+    pub fn split(&'static self) -> Option<(Handshake<T>, Handshake<T>)> {
+        if self.split.swap(true, Ordering::AcqRel) { return None }
+        common::tally(common::EMPTY);
+        let common = NonNull::from(&self.common);
+        Some((Handshake::from_common(common), Handshake::from_common(common)))
+    }
+
+    pub fn state(&self) -> State {
+        State::of(self.common.state())
+    }
+}
+
+impl<T> Default for StaticHandshake<T> {
+    fn default() -> Self {
+        StaticHandshake::new()
+    }
+}
+
+unsafe impl<T: Send> Sync for StaticHandshake<T> {}
+
+impl<T> Debug for StaticHandshake<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("StaticHandshake").field("state", &self.state()).finish()
+    }
+}
@@ -0,0 +1,26 @@
+//! Whether ends may move between threads, chosen by the second parameter of
+//! [`Handshake`](crate::Handshake).
+//!
+//! Both strategies share one implementation, differing only in the `Send` and
+//! `Sync` impls they grant, so code generic over the strategy works with either.
+
+mod sealed {
+    pub trait Sealed {}
+}
+
+/// A threading strategy for [`Handshake`](crate::Handshake), either [`SendSafe`] or [`Local`].
+pub trait Strategy: sealed::Sealed + 'static {}
+
+/// Ends may be sent to and shared with other threads whenever the payload may, the default.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum SendSafe {}
+
+/// Ends stay on the thread that made them, even for `Send` payloads.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Local {}
+
+impl sealed::Sealed for SendSafe {}
+impl Strategy for SendSafe {}
+
+impl sealed::Sealed for Local {}
+impl Strategy for Local {}
@@ -0,0 +1,328 @@
+//! Synchronization primitives, swapped for plain cells on targets without threads
+//! (`wasm32` without the `atomics` feature), where nothing can race, and for
+//! loom's model checked ones under `cfg(loom)`.
+//!
+//! With the `portable-atomic` feature the atomics come from that crate, for
+//! targets without compare-and-swap; enable one of its fallbacks, e.g. its
+//! `critical-section` feature, for those.
+
+#[cfg(not(any(loom, all(target_arch = "wasm32", not(target_feature = "atomics")), feature = "portable-atomic")))]
+pub(crate) use std::{sync::atomic::{fence, AtomicBool, AtomicU32, AtomicU8}, thread::{current, Thread}};
+
+#[cfg(all(not(loom), not(all(target_arch = "wasm32", not(target_feature = "atomics"))), feature = "portable-atomic"))]
+pub(crate) use {portable_atomic::{fence, AtomicBool, AtomicU32, AtomicU8}, std::thread::{current, Thread}};
+
+#[cfg(all(not(any(loom, all(target_arch = "wasm32", not(target_feature = "atomics")), feature = "portable-atomic")), target_has_atomic = "64"))]
+pub(crate) use std::sync::atomic::AtomicU64;
+
+#[cfg(all(not(loom), not(all(target_arch = "wasm32", not(target_feature = "atomics"))), feature = "portable-atomic"))]
+pub(crate) use portable_atomic::AtomicU64;
+
+#[cfg(all(not(loom), target_arch = "wasm32", not(target_feature = "atomics")))]
+pub(crate) use single::AtomicU64;
+
+#[cfg(loom)]
+pub(crate) use loom::sync::atomic::AtomicU64;
+
+pub(crate) use std::sync::atomic::Ordering;
+
+#[cfg(all(not(loom), target_arch = "wasm32", not(target_feature = "atomics")))]
+pub(crate) use {single::{fence, AtomicBool, AtomicU32, AtomicU8}, std::thread::{current, Thread}};
+
+#[cfg(loom)]
+pub(crate) use loom::{sync::atomic::{fence, AtomicBool, AtomicU32, AtomicU8}, thread::{current, Thread}};
+
+/// A lock around data touched on every wake-up, held for a handful of
+/// instructions at a time so spinning beats parking and nothing can poison it.
+///
+/// A critical section with the `embassy` feature instead, so interrupt handlers
+/// can complete handshakes without deadlocking against the task they interrupted.
+pub(crate) struct Lock<T> {
+    #[cfg(not(feature = "embassy"))]
+    locked: AtomicBool,
+    #[cfg(not(feature = "embassy"))]
+    value: std::cell::UnsafeCell<T>,
+    #[cfg(feature = "embassy")]
+    inner: critical_section::Mutex<std::cell::RefCell<T>>,
+}
+
+impl<T> Lock<T> {
+    // loom's atomics can't be made in const contexts
+    #[cfg(all(loom, not(feature = "embassy")))]
+    pub(crate) fn new(value: T) -> Lock<T> {
+        Lock { locked: AtomicBool::new(false), value: std::cell::UnsafeCell::new(value) }
+    }
+
+    #[cfg(any(not(loom), feature = "embassy"))]
+    pub(crate) const fn new(value: T) -> Lock<T> {
+        #[cfg(not(feature = "embassy"))]
+        return Lock { locked: AtomicBool::new(false), value: std::cell::UnsafeCell::new(value) };
+        #[cfg(feature = "embassy")]
+        Lock { inner: critical_section::Mutex::new(std::cell::RefCell::new(value)) }
+    }
+
+    /// Runs `f` with exclusive access, which must not re-enter the lock.
+    pub(crate) fn with<R>(&self, f: impl FnOnce(&mut T) -> R) -> R {
+        count_lock();
+        #[cfg(not(feature = "embassy"))]
+        {
+            let mut spins = 0;
+            while self.locked.compare_exchange_weak(false, true, Ordering::Acquire, Ordering::Relaxed).is_err() {
+                backoff(&mut spins)
+            }
+            // unlocks even if `f` unwinds
+            struct Unlock<'a>(&'a AtomicBool);
+            impl Drop for Unlock<'_> {
+                fn drop(&mut self) { self.0.store(false, Ordering::Release) }
+            }
+            let _unlock = Unlock(&self.locked);
+            // exclusive while locked
+            f(unsafe { &mut *self.value.get() })
+        }
+        #[cfg(feature = "embassy")]
+        critical_section::with(|cs| f(&mut self.inner.borrow_ref_mut(cs)))
+    }
+}
+
+#[cfg(not(feature = "embassy"))]
+unsafe impl<T: Send> Sync for Lock<T> {}
+
+// locks taken and OS waits or wakes made by the current thread, for tests
+// asserting a path never makes any
+#[cfg(all(test, not(loom)))]
+thread_local! {
+    static LOCKS: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+}
+
+#[cfg(all(test, not(loom)))]
+pub(crate) fn locks() -> usize {
+    LOCKS.with(std::cell::Cell::get)
+}
+
+fn count_lock() {
+    #[cfg(all(test, not(loom)))]
+    LOCKS.with(|locks| locks.set(locks.get() + 1))
+}
+
+/// Waits a little before retrying something another thread holds up, spinning
+/// at first and yielding once that has gone on for a while.
+pub(crate) fn backoff(spins: &mut u32) {
+    *spins += 1;
+    // loom must see every retry as a yield to keep exploration finite
+    #[cfg(loom)]
+    loom::thread::yield_now();
+    #[cfg(not(loom))]
+    if *spins < 64 { std::hint::spin_loop() } else { std::thread::yield_now() }
+}
+
+/// Parks the current thread until unparked.
+///
+/// # Panics
+/// On targets without threads, as nothing could ever unpark it.
+pub(crate) fn park() {
+    count_lock();
+    #[cfg(loom)]
+    return loom::thread::park();
+    #[cfg(all(not(loom), target_arch = "wasm32", not(target_feature = "atomics")))]
+    panic!("handshake would block forever on a target without threads");
+    #[cfg(not(any(loom, all(target_arch = "wasm32", not(target_feature = "atomics")))))]
+    std::thread::park()
+}
+
+/// Blocks while `word` holds `expected`, until [`wake_all`] on it or `timeout`
+/// passed, possibly waking spuriously.
+///
+/// A futex on Linux and `WaitOnAddress` on Windows, parking the thread in a
+/// table keyed on the address elsewhere.
+pub(crate) fn wait(word: &AtomicU32, expected: u32, timeout: Option<std::time::Duration>) {
+    count_lock();
+    words::wait(word, expected, timeout)
+}
+
+/// Wakes every thread in [`wait`] on `word`.
+pub(crate) fn wake_all(word: &AtomicU32) {
+    count_lock();
+    words::wake_all(word)
+}
+
+#[cfg(all(any(target_os = "linux", target_os = "android"), not(any(loom, feature = "portable-atomic"))))]
+mod words {
+    use std::time::Duration;
+
+    use super::AtomicU32;
+
+    pub(super) fn wait(word: &AtomicU32, expected: u32, timeout: Option<Duration>) {
+        let timeout = timeout.map(|timeout| libc::timespec {
+            tv_sec: timeout.as_secs().try_into().unwrap_or(libc::time_t::MAX),
+            tv_nsec: timeout.subsec_nanos() as _,
+        });
+        let timeout = timeout.as_ref().map_or(std::ptr::null(), |timeout| timeout as *const libc::timespec);
+        // returns early if the word moved on, which the caller re-checks anyway
+        unsafe { libc::syscall(libc::SYS_futex, word.as_ptr(), libc::FUTEX_WAIT | libc::FUTEX_PRIVATE_FLAG, expected, timeout) };
+    }
+
+    pub(super) fn wake_all(word: &AtomicU32) {
+        unsafe { libc::syscall(libc::SYS_futex, word.as_ptr(), libc::FUTEX_WAKE | libc::FUTEX_PRIVATE_FLAG, i32::MAX) };
+    }
+}
+
+#[cfg(all(windows, not(any(loom, feature = "portable-atomic"))))]
+mod words {
+    use std::{ffi::c_void, time::Duration};
+
+    use super::AtomicU32;
+
+    #[link(name = "synchronization")]
+    extern "system" {
+        fn WaitOnAddress(address: *const c_void, compare: *const c_void, size: usize, millis: u32) -> i32;
+        fn WakeByAddressAll(address: *const c_void);
+    }
+
+    pub(super) fn wait(word: &AtomicU32, expected: u32, timeout: Option<Duration>) {
+        // rounded up so short waits don't spin, `u32::MAX` waits forever
+        let millis = timeout.map_or(u32::MAX, |timeout| timeout.as_nanos().div_ceil(1_000_000).try_into().unwrap_or(u32::MAX - 1));
+        unsafe { WaitOnAddress(word.as_ptr().cast(), (&expected as *const u32).cast(), 4, millis) };
+    }
+
+    pub(super) fn wake_all(word: &AtomicU32) {
+        unsafe { WakeByAddressAll(word.as_ptr().cast()) }
+    }
+}
+
+#[cfg(not(all(any(target_os = "linux", target_os = "android", windows), not(any(loom, feature = "portable-atomic")))))]
+mod words {
+    use std::time::Duration;
+
+    use super::{current, AtomicU32, Ordering, Thread};
+
+    #[cfg(not(loom))]
+    type Mutex<T> = std::sync::Mutex<T>;
+    #[cfg(loom)]
+    type Mutex<T> = loom::sync::Mutex<T>;
+
+    // the threads waiting on each address
+    #[cfg(not(loom))]
+    static WAITING: Mutex<Vec<(usize, Thread)>> = Mutex::new(Vec::new());
+    // loom's locks can't be made in const contexts
+    #[cfg(loom)]
+    loom::lazy_static! {
+        static ref WAITING: Mutex<Vec<(usize, Thread)>> = Mutex::new(Vec::new());
+    }
+
+    fn lock() -> impl std::ops::DerefMut<Target = Vec<(usize, Thread)>> {
+        WAITING.lock().unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+
+    /// Parks the current thread until unparked or `timeout` elapsed, possibly waking spuriously.
+    ///
+    /// # Panics
+    /// On targets without threads, as nothing could ever unpark it.
+    fn park_timeout(timeout: Duration) {
+        // loom has no clock, a yield stands in for some time passing
+        #[cfg(loom)]
+        return { let _ = timeout; loom::thread::yield_now() };
+        #[cfg(all(not(loom), target_arch = "wasm32", not(target_feature = "atomics")))]
+        panic!("handshake would block for {timeout:?} on a target without threads");
+        #[cfg(not(any(loom, all(target_arch = "wasm32", not(target_feature = "atomics")))))]
+        std::thread::park_timeout(timeout)
+    }
+
+    pub(super) fn wait(word: &AtomicU32, expected: u32, timeout: Option<Duration>) {
+        let address = word as *const AtomicU32 as usize;
+        let mut waiting = lock();
+        // checked under the lock, which `wake_all` takes after changing the word
+        if word.load(Ordering::Acquire) != expected { return }
+        waiting.push((address, current()));
+        drop(waiting);
+        match timeout {
+            Some(timeout) => park_timeout(timeout),
+            None => super::park(),
+        }
+        // still there if woken by something else
+        let id = current().id();
+        lock().retain(|(at, thread)| *at != address || thread.id() != id)
+    }
+
+    pub(super) fn wake_all(word: &AtomicU32) {
+        let address = word as *const AtomicU32 as usize;
+        let mut woken = Vec::new();
+        lock().retain(|(at, thread)| *at != address || { woken.push(thread.clone()); false });
+        // unparked outside the lock, they go straight for it
+        woken.into_iter().for_each(|thread| thread.unpark())
+    }
+}
+
+#[cfg(all(not(loom), target_arch = "wasm32", not(target_feature = "atomics")))]
+mod single {
+    use std::cell::Cell;
+
+    use super::Ordering;
+
+    pub(crate) fn fence(_: Ordering) {}
+
+    macro_rules! cells {
+        ($($name:ident($ty:ty)),*) => {$(
+            pub(crate) struct $name(Cell<$ty>);
+
+            // no threads, nothing to race
+            unsafe impl Sync for $name {}
+
+            // mirrors the std subset in use, not every type needs all of it
+            #[allow(dead_code)]
+            impl $name {
+                pub(crate) const fn new(value: $ty) -> $name {
+                    $name(Cell::new(value))
+                }
+
+                pub(crate) fn load(&self, _: Ordering) -> $ty {
+                    self.0.get()
+                }
+
+                pub(crate) fn store(&self, value: $ty, _: Ordering) {
+                    self.0.set(value)
+                }
+
+                pub(crate) fn swap(&self, value: $ty, _: Ordering) -> $ty {
+                    self.0.replace(value)
+                }
+
+                pub(crate) fn compare_exchange(
+                    &self, current: $ty, new: $ty, _: Ordering, _: Ordering,
+                ) -> Result<$ty, $ty> {
+                    let value = self.0.get();
+                    if value == current { self.0.set(new); Ok(value) } else { Err(value) }
+                }
+
+                pub(crate) fn compare_exchange_weak(
+                    &self, current: $ty, new: $ty, success: Ordering, failure: Ordering,
+                ) -> Result<$ty, $ty> {
+                    self.compare_exchange(current, new, success, failure)
+                }
+            }
+        )*};
+    }
+
+    cells!(AtomicBool(bool), AtomicU8(u8), AtomicU32(u32), AtomicU64(u64));
+
+    impl AtomicU32 {
+        pub(crate) fn fetch_add(&self, value: u32, _: Ordering) -> u32 {
+            self.0.replace(self.0.get().wrapping_add(value))
+        }
+
+        pub(crate) fn fetch_or(&self, value: u32, _: Ordering) -> u32 {
+            self.0.replace(self.0.get() | value)
+        }
+
+        pub(crate) fn fetch_and(&self, value: u32, _: Ordering) -> u32 {
+            self.0.replace(self.0.get() & value)
+        }
+
+        pub(crate) fn fetch_xor(&self, value: u32, _: Ordering) -> u32 {
+            self.0.replace(self.0.get() ^ value)
+        }
+
+        pub(crate) fn fetch_sub(&self, value: u32, _: Ordering) -> u32 {
+            self.0.replace(self.0.get() - value)
+        }
+    }
+}
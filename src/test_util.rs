@@ -0,0 +1,133 @@
+//! Helpers for testing code built on handshakes without real threads.
+//!
+//! A [`MockEnd`] implements [`Rendezvous`] following a script of outcomes,
+//! standing in for an end whose peer behaves exactly as a test needs, and
+//! [`assert_completed!`](crate::assert_completed) and
+//! [`assert_canceled!`](crate::assert_canceled) check where real exchanges stand.
+
+use std::{collections::VecDeque, fmt::Debug, time::Duration};
+
+use crate::{Canceled, Rendezvous, State};
+
+/// What a [`MockEnd`] does on the next call reaching it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Step<T> {
+    /// A push is accepted, the value kept for [`MockEnd::pushed`].
+    Accept,
+    /// A push is refused as if the peer had pushed first, the end staying usable.
+    Occupied,
+    /// A pull hands out the value, using up the end.
+    Deliver(T),
+    /// A pull finds nothing yet, the end staying usable.
+    Pending,
+    /// The call fails as if the peer went away, using up the end.
+    PeerGone,
+    /// Sleeps before taking the next step for the same call, to script timing.
+    Delay(Duration),
+}
+
+/// An end following a script, see [`Step`].
+///
+/// # Panics
+/// On a call the script has no step for, or one not fitting the call, e.g.
+/// [`Step::Deliver`] for a push, failing the test at the unscripted interaction.
+pub struct MockEnd<T> {
+    script: VecDeque<Step<T>>,
+    pushed: Vec<T>,
+    // the end was used up or given up, as an `Option` of a real one would be `None`
+    used: bool,
+    canceled: bool,
+    state: State,
+}
+
+impl<T> MockEnd<T> {
+    pub fn scripted(script: impl IntoIterator<Item = Step<T>>) -> MockEnd<T> {
+        MockEnd { script: script.into_iter().collect(), pushed: Vec::new(), used: false, canceled: false, state: State::Empty }
+    }
+
+    /// The values accepted so far.
+    pub fn pushed(&self) -> &[T] {
+        &self.pushed
+    }
+
+    /// Whether the end was given up through [`Rendezvous::cancel`].
+    pub fn is_canceled(&self) -> bool {
+        self.canceled
+    }
+
+    /// Number of steps not taken yet.
+    pub fn remaining(&self) -> usize {
+        self.script.len()
+    }
+
+    // the next step that isn't a delay, after sleeping through those
+    fn next(&mut self, call: &str) -> Step<T> {
+        loop {
+            match self.script.pop_front() {
+                Some(Step::Delay(delay)) => std::thread::sleep(delay),
+                Some(step) => return step,
+                None => panic!("mock end has no step scripted for {call}"),
+            }
+        }
+    }
+}
+
+impl<T> Rendezvous<T> for MockEnd<T> {
+    fn push(&mut self, value: T) -> Result<(), T> {
+        if self.used { return Err(value) }
+        match self.next("push") {
+            Step::Accept => { self.pushed.push(value); self.used = true; self.state = State::Set; Ok(()) }
+            Step::Occupied => { self.state = State::Set; Err(value) }
+            Step::PeerGone => { self.used = true; self.state = State::PeerGone; Err(value) }
+            _ => panic!("mock end scripted with a pull step for a push"),
+        }
+    }
+
+    fn try_pull(&mut self) -> Result<Option<T>, Canceled> {
+        if self.used { return Err(Canceled) }
+        match self.next("pull") {
+            Step::Deliver(value) => { self.used = true; Ok(Some(value)) }
+            Step::Pending => Ok(None),
+            Step::PeerGone => { self.used = true; self.state = State::PeerGone; Err(Canceled) }
+            _ => panic!("mock end scripted with a push step for a pull"),
+        }
+    }
+
+    fn cancel(&mut self) {
+        if !self.used { self.canceled = true }
+        self.used = true
+    }
+
+    fn state(&self) -> Option<State> {
+        (!self.used).then_some(self.state)
+    }
+}
+
+impl<T> Debug for MockEnd<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MockEnd").field("remaining", &self.remaining()).field("canceled", &self.canceled).finish()
+    }
+}
+
+/// Asserts that the exchange behind a handshake, receipt or watcher completed,
+/// the value having been collected.
+#[macro_export]
+macro_rules! assert_completed {
+    ($end:expr $(,)?) => {
+        match $end.state() {
+            $crate::State::TakenByPeer => {}
+            state => panic!("expected `{}` to have completed, but it is {:?}", stringify!($end), state),
+        }
+    };
+}
+
+/// Asserts that the exchange behind a handshake, receipt or watcher was canceled.
+#[macro_export]
+macro_rules! assert_canceled {
+    ($end:expr $(,)?) => {
+        match $end.state() {
+            $crate::State::PeerGone => {}
+            state => panic!("expected `{}` to be canceled, but it is {:?}", stringify!($end), state),
+        }
+    };
+}
@@ -0,0 +1,173 @@
+use std::{
+    cmp::Ordering,
+    collections::BinaryHeap,
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Condvar, Mutex, OnceLock, PoisonError},
+    task::Poll,
+    time::Instant,
+};
+
+use crate::{combinators::Pulling, common, relay, Handshake, State, Watcher};
+
+/// Deadlines of every handshake made with [`Handshake::with_deadline`] or
+/// [`Handshake::with_fallback`], enforced
+/// by a single background thread started along with the first one.
+///
+/// Entries go as soon as their exchange is over, so they hold on to nothing
+/// of a finished exchange however far off the deadline.
+struct Timer {
+    entries: Mutex<BinaryHeap<Entry>>,
+    // signaled when an earlier deadline comes in
+    changed: Condvar,
+}
+
+struct Entry {
+    deadline: Instant,
+    // the exchange it is for, to take the entry out early
+    id: u64,
+    fire: Box<dyn FnOnce() + Send>,
+}
+
+// the earliest deadline is the greatest, on top of the heap
+impl Ord for Entry {
+    fn cmp(&self, other: &Entry) -> Ordering {
+        other.deadline.cmp(&self.deadline)
+    }
+}
+
+impl PartialOrd for Entry {
+    fn partial_cmp(&self, other: &Entry) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Entry {
+    fn eq(&self, other: &Entry) -> bool {
+        self.deadline == other.deadline
+    }
+}
+
+impl Eq for Entry {}
+
+impl<T: Send + 'static> Handshake<T> {
+    /// Creates a handshake canceled once `deadline` passes without it completing,
+    /// dropping any value still waiting in it.
+    ///
+    /// Waiters see [`Canceled`](crate::Canceled), and [`timed_out`](Self::timed_out)
+    /// tells the deadline from the peer going away.
+    pub fn with_deadline(deadline: Instant) -> (Handshake<T>, Handshake<T>) {
+        let (u, v) = Handshake::new();
+        let (watcher, mut finished) = (u.watcher(), u.watcher());
+        let id = watcher.id();
+        schedule(Entry { deadline, id, fire: Box::new(move || watcher.common().time_out()) });
+        relay::spawn(move |cx| Pin::new(&mut finished).poll(cx).map(|_| unschedule(id)));
+        (u, v)
+    }
+
+    /// Creates a handshake whose first end, used only to push, is answered for
+    /// with `fallback` once `deadline` passes without it pushing, so the peer is
+    /// never left waiting for good, even if it is dropped.
+    ///
+    /// A value pushed in time is forwarded to the peer by whichever thread completes
+    /// the push, like the combinators; one pushed late goes nowhere.
+    pub fn with_fallback(deadline: Instant, fallback: T) -> (Handshake<T>, Handshake<T>) {
+        let (out, peer) = Handshake::new();
+        let id = out.id();
+        let (end, input) = Handshake::new();
+        let out = Arc::new(Mutex::new(Some(out)));
+        let mut input = Pulling::Waiting(input);
+        relay::spawn({
+            let out = out.clone();
+            move |cx| {
+                let mut out = out.lock().unwrap_or_else(PoisonError::into_inner);
+                // answered for already
+                let Some(handshake) = out.as_ref() else { return Poll::Ready(()) };
+                handshake.common().register(cx);
+                // nobody left to deliver to, canceled along with the end
+                let value = if handshake.state() == State::PeerGone { None } else {
+                    // gone without pushing, the fallback goes at the deadline
+                    if !input.poll(cx) { return Poll::Ready(()) }
+                    let Some(value) = input.take() else { return Poll::Pending };
+                    Some(value)
+                };
+                let handshake = out.take().unwrap();
+                // pushed outside the lock, the timer may be waiting on it
+                drop(out);
+                if let Some(value) = value { let _ = handshake.push(value); } else { drop(handshake) }
+                unschedule(id);
+                Poll::Ready(())
+            }
+        });
+        let fire = move || {
+            let handshake = out.lock().unwrap_or_else(PoisonError::into_inner).take();
+            if let Some(handshake) = handshake { let _ = handshake.push(fallback); }
+        };
+        schedule(Entry { deadline, id, fire: Box::new(fire) });
+        (end, peer)
+    }
+
+    /// Like [`with_fallback`](Self::with_fallback), answering with `T::default()`.
+    pub fn with_default_fallback(deadline: Instant) -> (Handshake<T>, Handshake<T>) where T: Default {
+        Handshake::with_fallback(deadline, T::default())
+    }
+}
+
+impl<T> Handshake<T> {
+    /// Whether the exchange was canceled for missing its deadline, see [`with_deadline`](Self::with_deadline).
+    pub fn timed_out(&self) -> bool {
+        self.common().state() == common::TIMED_OUT
+    }
+}
+
+impl<T> Watcher<T> {
+    /// Whether the exchange was canceled for missing its deadline, see [`Handshake::with_deadline`].
+    pub fn timed_out(&self) -> bool {
+        self.common().state() == common::TIMED_OUT
+    }
+}
+
+static TIMER: OnceLock<Timer> = OnceLock::new();
+
+fn schedule(entry: Entry) {
+    let timer = TIMER.get_or_init(|| {
+        std::thread::Builder::new()
+            .name("handshake-timer".into())
+            .spawn(|| run(TIMER.wait()))
+            .expect("failed to spawn the handshake timer thread");
+        Timer { entries: Mutex::new(BinaryHeap::new()), changed: Condvar::new() }
+    });
+    timer.entries.lock().unwrap_or_else(PoisonError::into_inner).push(entry);
+    timer.changed.notify_one()
+}
+
+// takes out the entry for exchange `id`, if it didn't fire yet
+fn unschedule(id: u64) {
+    let Some(timer) = TIMER.get() else { return };
+    let mut entries = timer.entries.lock().unwrap_or_else(PoisonError::into_inner);
+    let (gone, kept): (Vec<_>, Vec<_>) = std::mem::take(&mut *entries).into_vec().into_iter().partition(|entry| entry.id == id);
+    *entries = kept.into();
+    // dropped outside the lock, freeing the exchange may run anything
+    drop(entries);
+    drop(gone)
+}
+
+fn run(timer: &Timer) {
+    let mut entries = timer.entries.lock().unwrap_or_else(PoisonError::into_inner);
+    loop {
+        let now = Instant::now();
+        entries = match entries.peek().map(|entry| entry.deadline) {
+            None => timer.changed.wait(entries).unwrap_or_else(PoisonError::into_inner),
+            Some(deadline) if deadline > now => {
+                timer.changed.wait_timeout(entries, deadline - now).unwrap_or_else(PoisonError::into_inner).0
+            }
+            Some(_) => {
+                let entry = entries.pop().unwrap();
+                // fired outside the lock, wakers may schedule more
+                drop(entries);
+                (entry.fire)();
+                timer.entries.lock().unwrap_or_else(PoisonError::into_inner)
+            }
+        };
+    }
+}
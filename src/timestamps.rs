@@ -0,0 +1,37 @@
+use std::time::{Duration, Instant};
+
+use crate::{Handshake, Watcher};
+
+impl<T, S> Handshake<T, S> {
+    /// When the exchange was made, or last recycled for a new one.
+    ///
+    /// `None` for a [`StaticHandshake`](crate::StaticHandshake), made at compile time.
+    pub fn created_at(&self) -> Option<Instant> {
+        self.common().created_at()
+    }
+
+    /// When the value last deposited was pushed, e.g. to discard stale results
+    /// without wrapping every payload in `(Instant, T)`.
+    ///
+    /// `None` before any push, or once the depositor took its value back.
+    pub fn pushed_at(&self) -> Option<Instant> {
+        self.common().pushed_at()
+    }
+
+    /// How long ago the value last deposited was pushed, see [`pushed_at`](Self::pushed_at).
+    pub fn age(&self) -> Option<Duration> {
+        self.pushed_at().map(|at| at.elapsed())
+    }
+}
+
+impl<T> Watcher<T> {
+    /// When the value last deposited was pushed, see [`Handshake::pushed_at`].
+    pub fn pushed_at(&self) -> Option<Instant> {
+        self.common().pushed_at()
+    }
+
+    /// How long ago the value last deposited was pushed, see [`Handshake::pushed_at`].
+    pub fn age(&self) -> Option<Duration> {
+        self.pushed_at().map(|at| at.elapsed())
+    }
+}
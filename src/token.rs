@@ -0,0 +1,27 @@
+use std::{future::Future, task::Poll};
+
+use tokio_util::sync::CancellationToken;
+
+use crate::{relay, Handshake};
+
+impl<T: Send + 'static> Handshake<T> {
+    /// Cancels the handshake once `token` is canceled, as if the peer went away,
+    /// waking anyone waiting on either end with [`Canceled`](crate::Canceled).
+    ///
+    /// A value waiting to be pulled is lost then. Nothing happens once the exchange is over.
+    pub fn linked_to(self, token: CancellationToken) -> Handshake<T> {
+        let watcher = self.watcher();
+        let mut canceled = Box::pin(token.cancelled_owned());
+        relay::spawn(move |cx| {
+            if watcher.is_finished() { return Poll::Ready(()) }
+            if canceled.as_mut().poll(cx).is_ready() {
+                watcher.common().cancel();
+                return Poll::Ready(())
+            }
+            watcher.common().register(cx);
+            // re-check after registering so a wake-up can't slip through
+            if watcher.is_finished() { Poll::Ready(()) } else { Poll::Pending }
+        });
+        self
+    }
+}
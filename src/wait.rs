@@ -0,0 +1,50 @@
+//! How blocking calls wait for the peer, chosen process-wide.
+
+use std::sync::atomic::{AtomicU8, Ordering};
+
+/// How a blocked thread waits for the peer to act.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[non_exhaustive]
+pub enum WaitStrategy {
+    /// Parks right away, the default.
+    #[default]
+    Park,
+    /// Spins for a few microseconds before parking.
+    SpinThenPark,
+    /// Never parks, burning a core for the lowest latency between pinned threads.
+    Spin,
+}
+
+// spins before parking with `SpinThenPark`
+const SPINS: u32 = 1 << 10;
+
+static STRATEGY: AtomicU8 = AtomicU8::new(WaitStrategy::Park as u8);
+
+/// Sets how blocking calls on every handshake wait from now on.
+pub fn set_wait_strategy(strategy: WaitStrategy) {
+    STRATEGY.store(strategy as u8, Ordering::Relaxed)
+}
+
+pub fn wait_strategy() -> WaitStrategy {
+    match STRATEGY.load(Ordering::Relaxed) {
+        1 => WaitStrategy::SpinThenPark,
+        2 => WaitStrategy::Spin,
+        _ => WaitStrategy::Park,
+    }
+}
+
+// whether a blocked thread should retry after a spin rather than park yet
+pub(crate) fn spin(spins: &mut u32) -> bool {
+    // loom explores parking, spinning would just blow up its state space
+    if cfg!(loom) { return false }
+    let spin = match wait_strategy() {
+        WaitStrategy::Park => false,
+        WaitStrategy::SpinThenPark => *spins < SPINS,
+        WaitStrategy::Spin => true,
+    };
+    if spin {
+        *spins = spins.saturating_add(1);
+        std::hint::spin_loop()
+    }
+    spin
+}
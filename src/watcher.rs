@@ -0,0 +1,93 @@
+use std::{fmt::Debug, future::Future, pin::Pin, ptr::NonNull, task::{Context, Poll}};
+
+use crate::{common::{self, Common}, Handshake, State};
+
+/// Observes an exchange without taking part in it, see [`Handshake::watcher`].
+///
+/// Awaiting a watcher resolves with the final state once the exchange completed or was canceled.
+pub struct Watcher<T> {
+    common: NonNull<Common<T>>,
+}
+
+impl<T> Handshake<T> {
+    /// Makes a handle observing this exchange, which can neither push nor pull.
+    pub fn watcher(&self) -> Watcher<T> {
+        self.common().acquire();
+        Watcher { common: self.common }
+    }
+}
+
+impl<T> Watcher<T> {
+    pub(crate) fn common(&self) -> &Common<T> {
+        // watcher owns a reference
+        unsafe { self.common.as_ref() }
+    }
+
+    /// Identifies the exchange, see [`Handshake::id`].
+    pub fn id(&self) -> u64 {
+        common::id(self.common)
+    }
+
+    pub fn state(&self) -> State {
+        State::of(self.common().state())
+    }
+
+    /// Copies the value waiting for the peer, see [`Handshake::peek_copied`].
+    pub fn peek_copied(&self) -> Option<T> where T: Copy {
+        self.common().read()
+    }
+
+    /// Whether an end went away without completing the exchange, see [`Handshake::is_canceled`].
+    pub fn is_canceled(&self) -> bool {
+        self.state() == State::PeerGone
+    }
+
+    /// Whether the exchange completed or was canceled, either way for good.
+    pub fn is_finished(&self) -> bool {
+        finished(self.common().state())
+    }
+
+    /// Blocks until the exchange completed or was canceled, returning the final state.
+    pub fn wait(&self) -> State {
+        State::of(self.common().block_while(|state| !finished(state)))
+    }
+}
+
+fn finished(state: u8) -> bool {
+    matches!(state, common::TAKEN | common::SHARED | common::CANCELED | common::LOST | common::ORPHANED | common::TIMED_OUT)
+}
+
+impl<T> Future for Watcher<T> {
+    type Output = State;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<State> {
+        if self.is_finished() { return Poll::Ready(self.state()); }
+        self.common().register(cx);
+        // re-check after registering so a wake-up can't slip through
+        if self.is_finished() { Poll::Ready(self.state()) } else { Poll::Pending }
+    }
+}
+
+impl<T> Clone for Watcher<T> {
+    fn clone(&self) -> Watcher<T> {
+        self.common().acquire();
+        Watcher { common: self.common }
+    }
+}
+
+impl<T> Drop for Watcher<T> {
+    fn drop(&mut self) {
+        // watcher owns a reference
+        unsafe { Common::release(self.common) }
+    }
+}
+
+unsafe impl<T: Send> Sync for Watcher<T> {}
+
+unsafe impl<T: Send> Send for Watcher<T> {}
+
+impl<T> Debug for Watcher<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Watcher").field("state", &self.state()).finish()
+    }
+}
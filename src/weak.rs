@@ -0,0 +1,63 @@
+use std::{
+    fmt::Debug,
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+    task::Poll,
+};
+
+use crate::{relay, Handshake, State};
+
+/// An end parked for later, let go of as soon as its peer goes away, see [`Handshake::downgrade`].
+pub struct WeakHandshake<T> {
+    end: Arc<Mutex<Option<Handshake<T>>>>,
+}
+
+impl<T: Send + 'static> Handshake<T> {
+    /// Parks this end in a handle that drops it, and with it its share of the
+    /// exchange, the moment the peer goes away, e.g. for caches holding many
+    /// handles whose exchanges may have died.
+    ///
+    /// Dropping the weak handle cancels like dropping the end.
+    pub fn downgrade(self) -> WeakHandshake<T> {
+        let end = Arc::new(Mutex::new(Some(self)));
+        let weak = Arc::downgrade(&end);
+        relay::spawn(move |cx| {
+            // the weak handle dropped the end itself
+            let Some(end) = weak.upgrade() else { return Poll::Ready(()) };
+            let mut end = end.lock().unwrap_or_else(PoisonError::into_inner);
+            // upgraded in the meantime
+            let Some(handshake) = end.as_ref() else { return Poll::Ready(()) };
+            handshake.common().register(cx);
+            if handshake.state() != State::PeerGone { return Poll::Pending; }
+            let dead = end.take();
+            // dropped outside the lock, in case it wakes anyone
+            drop(end);
+            drop(dead);
+            Poll::Ready(())
+        });
+        WeakHandshake { end }
+    }
+}
+
+impl<T> WeakHandshake<T> {
+    fn lock(&self) -> MutexGuard<'_, Option<Handshake<T>>> {
+        self.end.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Takes the end back to push or pull, unless the peer went away or it was taken already.
+    pub fn upgrade(&self) -> Option<Handshake<T>> {
+        let mut end = self.lock();
+        // the relay may not have run yet
+        end.take().filter(|handshake| handshake.state() != State::PeerGone)
+    }
+
+    /// Whether [`upgrade`](Self::upgrade) would still hand out the end.
+    pub fn is_live(&self) -> bool {
+        self.lock().as_ref().is_some_and(|handshake| handshake.state() != State::PeerGone)
+    }
+}
+
+impl<T> Debug for WeakHandshake<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WeakHandshake").field("live", &self.is_live()).finish()
+    }
+}
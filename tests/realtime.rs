@@ -0,0 +1,53 @@
+//! The real-time path, in a binary of its own so the counting allocator sees
+//! nothing but this test.
+
+#![cfg(not(loom))]
+
+use std::{alloc::{GlobalAlloc, Layout, System}, cell::Cell, sync::Arc};
+
+use handshake::SignalHandshake;
+
+// counts allocations made by the current thread, for asserting there are none
+struct CountingAlloc;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.with(|count| count.set(count.get() + 1));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        ALLOCATIONS.with(|count| count.set(count.get() + 1));
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+#[test]
+fn realtime_test() {
+    // one buffer going round between a real-time thread and the main thread
+    let (to_audio, to_main) = (Arc::new(SignalHandshake::<Vec<f32>>::new()), Arc::new(SignalHandshake::new()));
+    let (inbox, outbox) = (to_audio.clone(), to_main.clone());
+    let audio = std::thread::spawn(move || {
+        let before = ALLOCATIONS.with(Cell::get);
+        for _ in 0..64 {
+            let mut buffer = loop { if let Some(buffer) = inbox.try_pull() { break buffer } std::thread::yield_now() };
+            buffer.iter_mut().for_each(|sample| *sample += 1.0);
+            while let Err(rejected) = outbox.try_push(buffer) { buffer = rejected }
+        }
+        ALLOCATIONS.with(Cell::get) - before
+    });
+    let mut buffer = vec![0.0; 256];
+    for _ in 0..64 {
+        to_audio.try_push(buffer).unwrap();
+        buffer = loop { if let Some(buffer) = to_main.try_pull() { break buffer } std::thread::yield_now() };
+    }
+    assert_eq!(audio.join().unwrap(), 0);
+    assert_eq!(buffer[0], 64.0)
+}